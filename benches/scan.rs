@@ -0,0 +1,80 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use port_hawk::{
+    args::{parser::PortRange, target::parse_targets},
+    scan::{
+        connect::{self, ConnectOptions},
+        jobs::{jobs, Job},
+        pool::WorkerPool,
+    },
+};
+use std::{net::TcpListener, sync::atomic::AtomicBool, thread};
+
+fn bench_port_range(c: &mut Criterion) {
+    let ports = PortRange::Range(vec![(1, 1024), (3000, 4000), (8000, 65535)]);
+
+    c.bench_function("port_range_count", |b| b.iter(|| black_box(&ports).count()));
+    c.bench_function("port_range_iter", |b| {
+        b.iter(|| black_box(&ports).iter().map(u64::from).sum::<u64>())
+    });
+}
+
+fn bench_job_stream(c: &mut Criterion) {
+    let targets = parse_targets("10.0.0.0/24".to_string()).unwrap();
+    let ports = PortRange::Range(vec![(1, 1024)]);
+
+    let mut group = c.benchmark_group("job_stream");
+    group.throughput(Throughput::Elements(256 * 1024));
+    group.bench_function("cidr_24_x_1024", |b| {
+        b.iter(|| jobs(black_box(&targets), black_box(&ports), &[]).count())
+    });
+    group.bench_function("cidr_24_x_1024_priority", |b| {
+        b.iter(|| jobs(black_box(&targets), black_box(&ports), &[22, 80, 443]).count())
+    });
+    group.finish();
+}
+
+fn bench_connect_throughput(c: &mut Criterion) {
+    // A no-op listener: accept and immediately drop every connection.
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    thread::spawn(move || for _ in listener.incoming() {});
+
+    let job = Job {
+        address: "127.0.0.1".parse().unwrap(),
+        port,
+        scope_id: 0,
+        protocol: None,
+    };
+    let options = ConnectOptions::default();
+    let connects = 64;
+
+    // Every connect leaves a TIME_WAIT entry behind, so keep the sample count small.
+    let mut group = c.benchmark_group("connect_throughput");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(connects as u64));
+    for concurrency in [1, 8, 32] {
+        group.bench_with_input(
+            BenchmarkId::from_parameter(concurrency),
+            &concurrency,
+            |b, &concurrency| {
+                b.iter(|| {
+                    let stop = AtomicBool::new(false);
+                    WorkerPool::new(concurrency).run(
+                        std::iter::repeat(job).take(connects),
+                        &stop,
+                        |job| connect::probe(job, &options),
+                    )
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_port_range,
+    bench_job_stream,
+    bench_connect_throughput
+);
+criterion_main!(benches);
@@ -0,0 +1,87 @@
+use std::{collections::HashMap, net::IpAddr};
+
+/// Name-to-address mappings loaded from an `/etc/hosts`-style file.
+///
+/// Names are matched case-insensitively. When a name is listed more than once, the first
+/// line wins, as with the system resolver.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HostsFile {
+    names: HashMap<String, IpAddr>,
+}
+
+impl HostsFile {
+    /// Returns the address mapped to `name`, if the file lists it.
+    pub fn get(&self, name: &str) -> Option<IpAddr> {
+        let name = name.strip_suffix('.').unwrap_or(name);
+        self.names.get(&name.to_ascii_lowercase()).copied()
+    }
+}
+
+/// Parses a hosts file: `IP name [aliases...]` per line.
+///
+/// Blank lines are ignored and `#` starts a comment that runs to the end of the line.
+///
+/// # Returns
+///
+/// * `Ok(HostsFile)` - If every entry parses, returns the mappings.
+/// * `Err(String)` - If a line has a bad address or no name, returns an error message.
+pub fn parse_hosts_file(contents: &str) -> Result<HostsFile, String> {
+    let mut names = HashMap::new();
+
+    for line in contents.lines() {
+        let entry = line.split('#').next().unwrap_or_default().trim();
+        if entry.is_empty() {
+            continue;
+        }
+
+        let mut fields = entry.split_whitespace();
+        let address = fields
+            .next()
+            .and_then(|address| address.parse::<IpAddr>().ok())
+            .ok_or_else(|| format!("Invalid hosts file entry: {}", line.trim()))?;
+        let mut aliases = fields.peekable();
+        if aliases.peek().is_none() {
+            return Err(format!("Invalid hosts file entry: {}", line.trim()));
+        }
+        for name in aliases {
+            names.entry(name.to_ascii_lowercase()).or_insert(address);
+        }
+    }
+
+    Ok(HostsFile { names })
+}
+
+// Unit tests >------------------------------------------------------------<
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_hosts_file() {
+        let hosts = parse_hosts_file(
+            "# lab hosts\n\
+             10.0.0.5   router gw # the gateway\n\
+             \n\
+             fd00::10   nas.lab\n\
+             10.0.0.9   Router\n",
+        )
+        .unwrap();
+
+        assert_eq!(hosts.get("router"), Some("10.0.0.5".parse().unwrap()));
+        assert_eq!(hosts.get("GW"), Some("10.0.0.5".parse().unwrap()));
+        assert_eq!(hosts.get("nas.lab."), Some("fd00::10".parse().unwrap()));
+        assert_eq!(hosts.get("printer"), None);
+    }
+
+    #[test]
+    fn test_parse_hosts_file_rejects_bad_entries() {
+        assert_eq!(
+            parse_hosts_file("10.0.0.256 router"),
+            Err("Invalid hosts file entry: 10.0.0.256 router".to_string())
+        );
+        assert_eq!(
+            parse_hosts_file("10.0.0.5"),
+            Err("Invalid hosts file entry: 10.0.0.5".to_string())
+        );
+    }
+}
@@ -0,0 +1,265 @@
+use crate::args::target::Target;
+use std::{
+    ffi::CStr,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+};
+
+/// A network interface that can reach IPv6 link-local addresses.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Interface {
+    pub name: String,
+    /// Interface index, used as the scope of link-local addresses reached through it.
+    pub index: u32,
+    /// The interface's own link-local address.
+    pub link_local: Ipv6Addr,
+}
+
+/// The outgoing interface chosen with `--interface`, and the addresses probes are sent from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutgoingInterface {
+    pub name: String,
+    /// The interface's first IPv4 address, if it has one.
+    pub ipv4: Option<Ipv4Addr>,
+    /// The interface's first routable IPv6 address, if it has one; link-local addresses only
+    /// reach the link itself, so they are not used as a source.
+    pub ipv6: Option<Ipv6Addr>,
+}
+
+impl OutgoingInterface {
+    /// Returns the address to send probes to `target` from.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(IpAddr)` - If the interface has an address of `target`'s family.
+    /// * `Err(String)` - If it has none, returns an error message naming the interface.
+    pub fn source_for(&self, target: IpAddr) -> Result<IpAddr, String> {
+        let (source, family) = match target {
+            IpAddr::V4(_) => (self.ipv4.map(IpAddr::V4), "IPv4"),
+            IpAddr::V6(_) => (self.ipv6.map(IpAddr::V6), "IPv6"),
+        };
+        source.ok_or_else(|| format!("Interface {} has no {} address", self.name, family))
+    }
+}
+
+/// Resolves the interface called `name` to the source addresses of its probes.
+///
+/// # Returns
+///
+/// * `Ok(OutgoingInterface)` - If the interface exists; it may have no address of a family.
+/// * `Err(String)` - If no interface has that name, returns an error message.
+pub fn resolve_interface(name: &str) -> Result<OutgoingInterface, String> {
+    let addresses: Vec<(IpAddr, u32)> = interface_addresses()?
+        .into_iter()
+        .filter(|(interface, _, _)| interface == name)
+        .map(|(_, address, scope)| (address, scope))
+        .collect();
+    if addresses.is_empty() && !interface_exists(name) {
+        return Err(format!("Unknown interface: {}", name));
+    }
+
+    Ok(OutgoingInterface {
+        name: name.to_string(),
+        ipv4: addresses.iter().find_map(|(address, _)| match address {
+            IpAddr::V4(address) => Some(*address),
+            IpAddr::V6(_) => None,
+        }),
+        ipv6: addresses.iter().find_map(|(address, _)| match address {
+            IpAddr::V6(address) if !is_link_local(IpAddr::V6(*address)) => Some(*address),
+            _ => None,
+        }),
+    })
+}
+
+fn interface_exists(name: &str) -> bool {
+    let Ok(name) = std::ffi::CString::new(name) else {
+        return false;
+    };
+    // SAFETY: `name` is a valid NUL-terminated string.
+    unsafe { libc::if_nametoindex(name.as_ptr()) != 0 }
+}
+
+/// Returns the IPv6 link-local block, `fe80::/10`.
+pub fn link_local_block() -> Target {
+    Target::Cidr(IpAddr::V6(Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 0)), 10)
+}
+
+/// Returns whether `address` is an IPv6 link-local address, which needs a scope to be reached.
+pub fn is_link_local(address: IpAddr) -> bool {
+    Target::Single(address).is_within(&link_local_block())
+}
+
+/// Returns whether any of `targets` lies entirely in the link-local block.
+pub fn has_link_local(targets: &[Target]) -> bool {
+    targets
+        .iter()
+        .any(|target| target.is_within(&link_local_block()))
+}
+
+/// Lists the interfaces that have an IPv6 link-local address, sorted by index, once each.
+///
+/// Interfaces without one (IPv4-only links, most tunnels) cannot reach link-local peers and
+/// are left out.
+pub fn link_local_interfaces() -> Result<Vec<Interface>, String> {
+    let found = interface_addresses()?
+        .into_iter()
+        .filter_map(|(name, address, scope)| match address {
+            IpAddr::V6(address) if is_link_local(IpAddr::V6(address)) => Some(Interface {
+                name,
+                index: scope,
+                link_local: address,
+            }),
+            _ => None,
+        })
+        .collect();
+
+    Ok(unique_by_index(found))
+}
+
+/// Lists every IPv4 and IPv6 address of every interface, as (interface name, address, scope);
+/// the scope is 0 for IPv4 addresses.
+fn interface_addresses() -> Result<Vec<(String, IpAddr, u32)>, String> {
+    let mut addrs: *mut libc::ifaddrs = std::ptr::null_mut();
+    // SAFETY: `addrs` is a valid out-pointer; the list is freed below.
+    if unsafe { libc::getifaddrs(&mut addrs) } != 0 {
+        return Err(format!(
+            "Failed to list interfaces: {}",
+            std::io::Error::last_os_error()
+        ));
+    }
+
+    let mut found = Vec::new();
+    let mut cursor = addrs;
+    while !cursor.is_null() {
+        // SAFETY: `cursor` walks the list getifaddrs returned, which stays alive until freed.
+        let entry = unsafe { &*cursor };
+        cursor = entry.ifa_next;
+
+        if entry.ifa_addr.is_null() {
+            continue;
+        }
+        // SAFETY: a non-null `ifa_addr` points at a sockaddr of the family it names.
+        let (address, scope) = match unsafe { (*entry.ifa_addr).sa_family } as i32 {
+            libc::AF_INET => {
+                let sockaddr = unsafe { &*(entry.ifa_addr as *const libc::sockaddr_in) };
+                let address = Ipv4Addr::from(u32::from_be(sockaddr.sin_addr.s_addr));
+                (IpAddr::V4(address), 0)
+            }
+            libc::AF_INET6 => {
+                let sockaddr = unsafe { &*(entry.ifa_addr as *const libc::sockaddr_in6) };
+                let address = Ipv6Addr::from(sockaddr.sin6_addr.s6_addr);
+                (IpAddr::V6(address), sockaddr.sin6_scope_id)
+            }
+            _ => continue,
+        };
+        // SAFETY: `ifa_name` is a NUL-terminated string owned by the list.
+        let name = unsafe { CStr::from_ptr(entry.ifa_name) }
+            .to_string_lossy()
+            .into_owned();
+        found.push((name, address, scope));
+    }
+    // SAFETY: `addrs` came from getifaddrs and is freed exactly once.
+    unsafe { libc::freeifaddrs(addrs) };
+
+    Ok(found)
+}
+
+/// Sorts interfaces by index and keeps the first address of each.
+fn unique_by_index(mut interfaces: Vec<Interface>) -> Vec<Interface> {
+    interfaces.sort_by_key(|interface| interface.index);
+    interfaces.dedup_by_key(|interface| interface.index);
+    interfaces
+}
+
+// Unit tests >------------------------------------------------------------<
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::args::target::parse_targets;
+
+    #[test]
+    fn test_is_link_local() {
+        assert!(is_link_local("fe80::1".parse().unwrap()));
+        assert!(is_link_local("febf::1".parse().unwrap()));
+        assert!(!is_link_local("fec0::1".parse().unwrap()));
+        assert!(!is_link_local("::1".parse().unwrap()));
+        assert!(!is_link_local("169.254.0.1".parse().unwrap()));
+    }
+
+    /// Name of the loopback interface.
+    const LOOPBACK: &str = if cfg!(target_os = "linux") {
+        "lo"
+    } else {
+        "lo0"
+    };
+
+    #[test]
+    fn test_resolve_interface_loopback() {
+        let interface = resolve_interface(LOOPBACK).unwrap();
+        assert_eq!(interface.name, LOOPBACK);
+        assert_eq!(interface.ipv4, Some(Ipv4Addr::LOCALHOST));
+        assert_eq!(
+            interface.source_for("127.0.0.1".parse().unwrap()),
+            Ok(IpAddr::V4(Ipv4Addr::LOCALHOST))
+        );
+        if let Some(ipv6) = interface.ipv6 {
+            assert_eq!(ipv6, Ipv6Addr::LOCALHOST);
+        }
+    }
+
+    #[test]
+    fn test_resolve_interface_unknown_name() {
+        assert_eq!(
+            resolve_interface("nosuchnic0"),
+            Err("Unknown interface: nosuchnic0".to_string())
+        );
+    }
+
+    #[test]
+    fn test_source_for_missing_family() {
+        let interface = OutgoingInterface {
+            name: "eth0".to_string(),
+            ipv4: Some(Ipv4Addr::new(10, 0, 0, 5)),
+            ipv6: None,
+        };
+        assert_eq!(
+            interface.source_for("10.0.0.1".parse().unwrap()),
+            Ok("10.0.0.5".parse().unwrap())
+        );
+        assert_eq!(
+            interface.source_for("2001:db8::1".parse().unwrap()),
+            Err("Interface eth0 has no IPv6 address".to_string())
+        );
+    }
+
+    #[test]
+    fn test_has_link_local() {
+        assert!(has_link_local(
+            &parse_targets("10.0.0.1,fe80::/120".to_string()).unwrap()
+        ));
+        assert!(!has_link_local(
+            &parse_targets("10.0.0.1,fd00::/120".to_string()).unwrap()
+        ));
+    }
+
+    #[test]
+    fn test_link_local_interfaces_only_lists_link_local_addresses() {
+        for interface in link_local_interfaces().unwrap() {
+            assert!(is_link_local(IpAddr::V6(interface.link_local)));
+            assert_ne!(interface.index, 0);
+        }
+
+        let interface = |name: &str, index, last| Interface {
+            name: name.to_string(),
+            index,
+            link_local: Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, last),
+        };
+        assert_eq!(
+            unique_by_index(vec![
+                interface("eth1", 3, 1),
+                interface("eth0", 2, 1),
+                interface("eth1", 3, 2)
+            ]),
+            vec![interface("eth0", 2, 1), interface("eth1", 3, 1)]
+        );
+    }
+}
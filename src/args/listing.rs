@@ -0,0 +1,53 @@
+use clap::ValueEnum;
+
+/// Lists the values `T` accepts on the command line, one per line in declaration order, each
+/// followed by its help text.
+///
+/// Names and descriptions come from the same `ValueEnum` the flags parse into, so the listing
+/// always matches what the flags accept.
+pub fn list<T: ValueEnum>() -> String {
+    let values: Vec<(String, String)> = T::value_variants()
+        .iter()
+        .filter_map(|variant| variant.to_possible_value())
+        .filter(|value| !value.is_hide_set())
+        .map(|value| {
+            let help = value.get_help().map(|help| help.to_string());
+            (value.get_name().to_string(), help.unwrap_or_default())
+        })
+        .collect();
+    let width = values.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+
+    values
+        .iter()
+        .map(|(name, help)| format!("{:width$}  {}", name, help).trim_end().to_string() + "\n")
+        .collect()
+}
+
+// Unit tests >------------------------------------------------------------<
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{output::OutputFormat, scan::raw::ScanType};
+
+    #[test]
+    fn test_list_formats() {
+        let listing = list::<OutputFormat>();
+        let names: Vec<&str> = listing
+            .lines()
+            .map(|line| line.split_whitespace().next().unwrap())
+            .collect();
+        assert_eq!(names, vec!["text", "json", "jsonl", "sarif", "targets"]);
+        assert!(listing.contains("jsonl    One JSON object per line\n"));
+        assert!(listing.contains("text     Human-readable lines for open ports\n"));
+    }
+
+    #[test]
+    fn test_list_scan_types() {
+        let listing = list::<ScanType>();
+        assert_eq!(listing.lines().count(), ScanType::value_variants().len());
+        for name in ["connect", "null", "fin", "xmas"] {
+            assert!(listing.lines().any(|line| line.starts_with(name)));
+        }
+        assert!(listing.starts_with("connect  Full TCP connect (no privileges needed)\n"));
+    }
+}
@@ -1 +1,9 @@
-pub mod parser;
\ No newline at end of file
+pub mod hosts;
+pub mod interfaces;
+pub mod listing;
+pub mod parser;
+pub mod presets;
+pub mod profile;
+pub mod resolver;
+pub mod target;
+pub mod top_ports;
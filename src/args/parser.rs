@@ -1,3 +1,66 @@
+use crate::scan::raw::ScanType;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Ports scanned when none are given.
+pub const DEFAULT_PORTS: &str = "1-1024";
+
+/// Every port, as `--all-ports` and `--ports all` scan.
+pub const ALL_PORTS: &str = "1-65535";
+
+/// Ports scanned by UDP scans when none are given: sweeping a range is impractical over UDP,
+/// where every silent port costs a full timeout, so only common services are probed.
+pub const DEFAULT_UDP_PORTS: &str = "53,67,68,123,161,500,514,1900,5353";
+
+/// Represents the transport a `--ports` item suffixed with `/tcp` or `/udp` is probed over.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Protocol {
+    #[default]
+    Tcp,
+    Udp,
+}
+
+impl Protocol {
+    /// Returns the protocol a scan type probes over.
+    pub fn of(scan_type: ScanType) -> Protocol {
+        match scan_type {
+            ScanType::Udp => Protocol::Udp,
+            _ => Protocol::Tcp,
+        }
+    }
+
+    /// Returns whether the protocol is TCP, which results leave out of their JSON.
+    pub fn is_tcp(&self) -> bool {
+        *self == Protocol::Tcp
+    }
+
+    /// Returns the scan type a port tagged with this protocol is probed with, given the
+    /// scan's `--scan-type`: TCP ports keep a TCP scan type and fall back to connect under a
+    /// UDP scan.
+    pub fn scan_type(self, global: ScanType) -> ScanType {
+        match (self, global) {
+            (Protocol::Udp, _) => ScanType::Udp,
+            (Protocol::Tcp, ScanType::Udp) => ScanType::Connect,
+            (Protocol::Tcp, global) => global,
+        }
+    }
+}
+
+impl fmt::Display for Protocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Protocol::Tcp => write!(f, "tcp"),
+            Protocol::Udp => write!(f, "udp"),
+        }
+    }
+}
+
+/// The protocol each tagged range of a `--ports` spec is probed over.
+pub type PortProtocols = Vec<((u16, u16), Protocol)>;
+
 /// Represents a port range, which can be either a single port or a range of ports.
 #[derive(Debug, Clone, PartialEq)]
 pub enum PortRange {
@@ -5,8 +68,63 @@ pub enum PortRange {
     Single(u16),
 }
 
+impl PortRange {
+    /// Returns the number of ports covered by the range.
+    pub fn count(&self) -> usize {
+        match self {
+            PortRange::Single(_) => 1,
+            PortRange::Range(ranges) => ranges
+                .iter()
+                .map(|(start, end)| (end - start) as usize + 1)
+                .sum(),
+        }
+    }
+
+    /// Returns whether `port` is covered by the range.
+    pub fn contains(&self, port: u16) -> bool {
+        match self {
+            PortRange::Single(single) => *single == port,
+            PortRange::Range(ranges) => ranges
+                .iter()
+                .any(|(start, end)| (*start..=*end).contains(&port)),
+        }
+    }
+
+    /// Returns the `index`th port of the range, in `iter` order.
+    pub fn nth(&self, index: usize) -> Option<u16> {
+        match self {
+            PortRange::Single(port) => (index == 0).then_some(*port),
+            PortRange::Range(ranges) => {
+                let mut index = index;
+                for &(start, end) in ranges {
+                    let len = (end - start) as usize + 1;
+                    if index < len {
+                        return Some(start + index as u16);
+                    }
+                    index -= len;
+                }
+                None
+            }
+        }
+    }
+
+    /// Returns an iterator over every port covered by the range, in the given order.
+    pub fn iter(&self) -> Box<dyn Iterator<Item = u16> + Send + '_> {
+        match self {
+            PortRange::Single(port) => Box::new(std::iter::once(*port)),
+            PortRange::Range(ranges) => {
+                Box::new(ranges.iter().flat_map(|&(start, end)| start..=end))
+            }
+        }
+    }
+}
+
 /// Parses a string representation of a port range and returns a `PortRange` enum.
 ///
+/// Ranges may be written with `-` or `:` (`8000-8080`, `8000:8080`); `,` separates list items,
+/// which may also be single ports (`22,8000:8080`); consecutive items are merged into one
+/// range. Port 0 is rejected; see `parse_port_range_with` to allow it.
+///
 /// # Arguments
 ///
 /// * `target_ports` - A string representing the target port range.
@@ -16,11 +134,127 @@ pub enum PortRange {
 /// * `Ok(PortRange)` - If the parsing is successful, returns a `PortRange` enum.
 /// * `Err(String)` - If the parsing fails, returns an error message.
 pub fn parse_port_range(target_ports: String) -> Result<PortRange, String> {
+    parse_port_range_with(target_ports, false)
+}
+
+/// Parses a port range like `parse_port_range`, accepting port 0 when `allow_port_zero` is set.
+///
+/// Port 0 is reserved and never listened on, so it is only useful for testing how stacks and
+/// middleboxes handle it.
+pub fn parse_port_range_with(
+    target_ports: String,
+    allow_port_zero: bool,
+) -> Result<PortRange, String> {
+    let port_range = parse_ports(&target_ports)?;
+    if !allow_port_zero && port_range.contains(0) {
+        return Err(format!(
+            "Port 0 is reserved: {} (pass --allow-port-zero to scan it)",
+            target_ports
+        ));
+    }
+    Ok(port_range)
+}
+
+/// Parses a port range like `parse_port_range_with`, where items may carry a protocol suffix
+/// (`80/tcp,53/udp,8000-8010`) that overrides `--scan-type` for their ports.
+///
+/// Returns every port, suffixed or not, and the protocol of each suffixed item in the order
+/// given. A port given as both `tcp` and `udp` (`53/tcp,53/udp`) is scanned over both.
+pub fn parse_tagged_ports(
+    target_ports: String,
+    allow_port_zero: bool,
+) -> Result<(PortRange, PortProtocols), String> {
+    if !target_ports.contains('/') {
+        return Ok((
+            parse_port_range_with(target_ports, allow_port_zero)?,
+            Vec::new(),
+        ));
+    }
+
+    let mut protocols: PortProtocols = Vec::new();
+    let mut items = Vec::new();
+    for item in target_ports.split(',') {
+        let Some((ports, protocol)) = item.trim().split_once('/') else {
+            items.push(item);
+            continue;
+        };
+        let protocol = match protocol.trim().to_ascii_lowercase().as_str() {
+            "tcp" => Protocol::Tcp,
+            "udp" => Protocol::Udp,
+            _ => {
+                return Err(format!(
+                    "Unknown protocol: {} (expected tcp or udp)",
+                    protocol
+                ))
+            }
+        };
+        let range = match parse_ports(ports)? {
+            PortRange::Single(port) => (port, port),
+            PortRange::Range(ranges) => ranges[0],
+        };
+        protocols.push((range, protocol));
+        // The ports are listed once however many protocols they are scanned over.
+        if !items.contains(&ports) {
+            items.push(ports);
+        }
+    }
+
+    let ports = parse_port_range_with(items.join(","), allow_port_zero)?;
+    Ok((ports, protocols))
+}
+
+/// A `--ports` item that was dropped because it does not parse, and why.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkippedPorts {
+    pub ports: String,
+    pub reason: String,
+}
+
+/// Parses a port range like `parse_tagged_ports`, but drops the items that do not parse
+/// instead of failing, so a scan can go ahead on the rest unless `--strict` is given.
+///
+/// Returns the ports and protocols of the items that parsed, and every dropped item with its
+/// error. If no item parses, the range is empty.
+pub fn assemble_ports(
+    target_ports: String,
+    allow_port_zero: bool,
+) -> (PortRange, PortProtocols, Vec<SkippedPorts>) {
+    if let Ok((ports, protocols)) = parse_tagged_ports(target_ports.clone(), allow_port_zero) {
+        return (ports, protocols, Vec::new());
+    }
+
+    let mut items = Vec::new();
+    let mut skipped = Vec::new();
+    for item in target_ports.split(',') {
+        match parse_tagged_ports(item.to_string(), allow_port_zero) {
+            Ok(_) => items.push(item),
+            Err(reason) => skipped.push(SkippedPorts {
+                ports: item.trim().to_string(),
+                reason,
+            }),
+        }
+    }
+    if items.is_empty() {
+        return (PortRange::Range(Vec::new()), Vec::new(), skipped);
+    }
+    // Items that parse alone can still conflict with each other.
+    match parse_tagged_ports(items.join(","), allow_port_zero) {
+        Ok((ports, protocols)) => (ports, protocols, skipped),
+        Err(reason) => {
+            skipped.push(SkippedPorts {
+                ports: items.join(","),
+                reason,
+            });
+            (PortRange::Range(Vec::new()), Vec::new(), skipped)
+        }
+    }
+}
+
+fn parse_ports(target_ports: &str) -> Result<PortRange, String> {
     // Single port
-    if !target_ports.contains('-') && !target_ports.contains(',') {
-        let port = target_ports
-            .parse::<u16>()
-            .map_err(|_| format!("Invalid port: {}", target_ports))?;
+    if !target_ports.contains(['-', ':', ',']) {
+        let port =
+            parse_port(target_ports).ok_or_else(|| format!("Invalid port: {}", target_ports))?;
         return Ok(PortRange::Single(port));
     }
 
@@ -29,25 +263,27 @@ pub fn parse_port_range(target_ports: String) -> Result<PortRange, String> {
     let mut port_ranges = Vec::new();
 
     for range in ranges {
-        let ports: Vec<&str> = range.split('-').collect();
+        let ports: Vec<&str> = range.split(['-', ':']).collect();
+        if ports.len() == 1 {
+            let port =
+                parse_port(ports[0].trim()).ok_or_else(|| format!("Invalid port: {}", ports[0]))?;
+            push_coalesced(&mut port_ranges, (port, port));
+            continue;
+        }
         if ports.len() != 2 {
             return Err(format!("Invalid port range: {}", range));
         }
 
-        let start = ports[0]
-            .trim()
-            .parse::<u16>()
-            .map_err(|_| format!("Invalid start port: {}", ports[0]))?;
-        let end = ports[1]
-            .trim()
-            .parse::<u16>()
-            .map_err(|_| format!("Invalid end port: {}", ports[1]))?;
+        let start = parse_port(ports[0].trim())
+            .ok_or_else(|| format!("Invalid start port: {}", ports[0]))?;
+        let end =
+            parse_port(ports[1].trim()).ok_or_else(|| format!("Invalid end port: {}", ports[1]))?;
 
         if start > end {
             return Err(format!("Start port is greater than end port: {}", range));
         }
 
-        port_ranges.push((start, end));
+        push_coalesced(&mut port_ranges, (start, end));
     }
 
     match port_ranges.len() {
@@ -56,6 +292,83 @@ pub fn parse_port_range(target_ports: String) -> Result<PortRange, String> {
     }
 }
 
+/// Appends `range` to `ranges`, extending the last range instead when `range` starts right
+/// after it, so a long list of consecutive singles (`80,81,82`) becomes one range (`80-82`).
+///
+/// Only adjacent list items are merged, which keeps the ports in the order given.
+fn push_coalesced(ranges: &mut Vec<(u16, u16)>, range: (u16, u16)) {
+    match ranges.last_mut() {
+        Some(last) if last.1.checked_add(1) == Some(range.0) => last.1 = range.1,
+        _ => ranges.push(range),
+    }
+}
+
+/// Parses an ordered, comma-separated list of ports and ranges (`22,443,8000-8002`).
+///
+/// The ports are returned in the order given, with ranges expanded in place.
+///
+/// # Arguments
+///
+/// * `ports` - A string representing the ports, in order.
+///
+/// # Returns
+///
+/// * `Ok(Vec<u16>)` - If the parsing is successful, returns the ports in the given order.
+/// * `Err(String)` - If the parsing fails, returns an error message.
+pub fn parse_port_list(ports: String) -> Result<Vec<u16>, String> {
+    let mut list = Vec::new();
+
+    for token in ports.split(',') {
+        match parse_port_range(token.trim().to_string())? {
+            PortRange::Single(port) => list.push(port),
+            PortRange::Range(ranges) => {
+                list.extend(ranges.into_iter().flat_map(|(start, end)| start..=end))
+            }
+        }
+    }
+
+    Ok(list)
+}
+
+/// Parses an ordered, comma-separated knock sequence (`7000,8000,9000`).
+///
+/// Unlike `parse_port_range`, order is significant and ranges are not accepted. Like it,
+/// port 0 is rejected unless `allow_port_zero` is set.
+///
+/// # Arguments
+///
+/// * `sequence` - A string representing the ports to knock, in order.
+/// * `allow_port_zero` - Whether port 0 may be knocked on.
+///
+/// # Returns
+///
+/// * `Ok(Vec<u16>)` - If the parsing is successful, returns the ports in the given order.
+/// * `Err(String)` - If the parsing fails, returns an error message.
+pub fn parse_knock_sequence(sequence: String, allow_port_zero: bool) -> Result<Vec<u16>, String> {
+    let ports = sequence
+        .split(',')
+        .map(|port| parse_port(port.trim()).ok_or_else(|| format!("Invalid knock port: {}", port)))
+        .collect::<Result<Vec<u16>, String>>()?;
+    if !allow_port_zero && ports.contains(&0) {
+        return Err(format!(
+            "Port 0 is reserved: {} (pass --allow-port-zero to knock on it)",
+            sequence
+        ));
+    }
+    Ok(ports)
+}
+
+/// Parses a single port token, either decimal (`8080`) or hex with a `0x` prefix (`0x1F90`).
+fn parse_port(token: &str) -> Option<u16> {
+    match token
+        .strip_prefix("0x")
+        .or_else(|| token.strip_prefix("0X"))
+    {
+        Some(hex) => u16::from_str_radix(hex, 16).ok(),
+        None => token.parse::<u16>().ok(),
+    }
+}
+
 // Unit tests >------------------------------------------------------------<
 #[cfg(test)]
 mod tests {
@@ -68,6 +381,29 @@ mod tests {
         assert_eq!(result, Ok(PortRange::Single(8080)));
     }
 
+    #[test]
+    fn test_parse_port_range_port_zero() {
+        assert_eq!(
+            parse_port_range("0".to_string()),
+            Err("Port 0 is reserved: 0 (pass --allow-port-zero to scan it)".to_string())
+        );
+        assert!(parse_port_range("0-10".to_string()).is_err());
+        assert!(parse_port_range("22,0".to_string()).is_err());
+
+        assert_eq!(
+            parse_port_range_with("0".to_string(), true),
+            Ok(PortRange::Single(0))
+        );
+        assert_eq!(
+            parse_port_range_with("0-10".to_string(), true),
+            Ok(PortRange::Range(vec![(0, 10)]))
+        );
+        assert_eq!(
+            parse_port_range_with("1-10".to_string(), false),
+            Ok(PortRange::Range(vec![(1, 10)]))
+        );
+    }
+
     #[test]
     fn test_parse_port_range_invalid_single_port() {
         // Invalid single port
@@ -123,4 +459,229 @@ mod tests {
             Ok(PortRange::Range(vec![(8000, 8080), (9000, 9090)]))
         );
     }
+
+    #[test]
+    fn test_port_range_count() {
+        assert_eq!(PortRange::Single(80).count(), 1);
+        assert_eq!(PortRange::Range(vec![(1, 1024)]).count(), 1024);
+        assert_eq!(PortRange::Range(vec![(1, 65535)]).count(), 65535);
+        assert_eq!(PortRange::Range(vec![(20, 22), (80, 80)]).count(), 4);
+    }
+
+    #[test]
+    fn test_port_range_iter() {
+        let ports: Vec<u16> = PortRange::Range(vec![(20, 22), (80, 80)]).iter().collect();
+        assert_eq!(ports, vec![20, 21, 22, 80]);
+    }
+
+    #[test]
+    fn test_parse_knock_sequence_keeps_order() {
+        let result = parse_knock_sequence("9000,7000,8000".to_string(), false);
+        assert_eq!(result, Ok(vec![9000, 7000, 8000]));
+    }
+
+    #[test]
+    fn test_parse_knock_sequence_invalid_port() {
+        let result = parse_knock_sequence("7000,80-90".to_string(), false);
+        assert_eq!(result, Err("Invalid knock port: 80-90".to_string()));
+    }
+
+    #[test]
+    fn test_parse_knock_sequence_port_zero() {
+        assert_eq!(
+            parse_knock_sequence("0,7000".to_string(), false),
+            Err("Port 0 is reserved: 0,7000 (pass --allow-port-zero to knock on it)".to_string())
+        );
+        assert_eq!(
+            parse_knock_sequence("0,7000".to_string(), true),
+            Ok(vec![0, 7000])
+        );
+    }
+
+    #[test]
+    fn test_port_range_contains() {
+        let ports = PortRange::Range(vec![(20, 22), (80, 80)]);
+        assert!(ports.contains(21));
+        assert!(ports.contains(80));
+        assert!(!ports.contains(23));
+        assert!(PortRange::Single(443).contains(443));
+    }
+
+    #[test]
+    fn test_parse_port_list_keeps_order() {
+        let result = parse_port_list("443,22,8000-8002".to_string());
+        assert_eq!(result, Ok(vec![443, 22, 8000, 8001, 8002]));
+    }
+
+    #[test]
+    fn test_parse_port_list_invalid_port() {
+        let result = parse_port_list("22,abc".to_string());
+        assert_eq!(result, Err("Invalid port: abc".to_string()));
+    }
+
+    #[test]
+    fn test_parse_port_range_hex_single() {
+        let result = parse_port_range("0x1F90".to_string());
+        assert_eq!(result, Ok(PortRange::Single(8080)));
+    }
+
+    #[test]
+    fn test_parse_port_range_hex_range() {
+        let result = parse_port_range("0x50-0x60".to_string());
+        assert_eq!(result, Ok(PortRange::Range(vec![(80, 96)])));
+
+        let result = parse_port_range("20-22,0x50-0x60".to_string());
+        assert_eq!(result, Ok(PortRange::Range(vec![(20, 22), (80, 96)])));
+    }
+
+    #[test]
+    fn test_parse_port_range_hex_overflow() {
+        let result = parse_port_range("0x10000".to_string());
+        assert_eq!(result, Err("Invalid port: 0x10000".to_string()));
+
+        let result = parse_port_range("0x50-0x10000".to_string());
+        assert_eq!(result, Err("Invalid end port: 0x10000".to_string()));
+    }
+
+    #[test]
+    fn test_parse_port_range_colon_separator() {
+        let result = parse_port_range("8000:8080".to_string());
+        assert_eq!(result, Ok(PortRange::Range(vec![(8000, 8080)])));
+
+        let result = parse_port_range("8080:8000".to_string());
+        assert_eq!(
+            result,
+            Err("Start port is greater than end port: 8080:8000".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_port_range_mixed_separators() {
+        let result = parse_port_range("22,8000:8080,9000-9001".to_string());
+        assert_eq!(
+            result,
+            Ok(PortRange::Range(vec![(22, 22), (8000, 8080), (9000, 9001)]))
+        );
+
+        let result = parse_port_range("8000:8080-9000".to_string());
+        assert_eq!(
+            result,
+            Err("Invalid port range: 8000:8080-9000".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_port_range_coalesces_consecutive_singles() {
+        let result = parse_port_range("80,81,82".to_string());
+        assert_eq!(result, Ok(PortRange::Range(vec![(80, 82)])));
+
+        let result = parse_port_range("20-22,23,24-30,443".to_string());
+        assert_eq!(result, Ok(PortRange::Range(vec![(20, 30), (443, 443)])));
+
+        let list: Vec<String> = (1000..1500).map(|port| port.to_string()).collect();
+        let result = parse_port_range(list.join(","));
+        assert_eq!(result, Ok(PortRange::Range(vec![(1000, 1499)])));
+    }
+
+    #[test]
+    fn test_parse_port_range_keeps_non_consecutive_singles_apart() {
+        let result = parse_port_range("22,80,443".to_string());
+        assert_eq!(
+            result,
+            Ok(PortRange::Range(vec![(22, 22), (80, 80), (443, 443)]))
+        );
+
+        // Only neighbours in the list merge, so the given order is kept.
+        let result = parse_port_range("82,81,80,65535".to_string());
+        assert_eq!(
+            result,
+            Ok(PortRange::Range(vec![
+                (82, 82),
+                (81, 81),
+                (80, 80),
+                (65535, 65535)
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_port_range_nth() {
+        let ports = PortRange::Range(vec![(20, 22), (80, 80)]);
+        let nth: Vec<Option<u16>> = (0..5).map(|i| ports.nth(i)).collect();
+        assert_eq!(nth, vec![Some(20), Some(21), Some(22), Some(80), None]);
+        assert_eq!(PortRange::Single(443).nth(0), Some(443));
+    }
+
+    #[test]
+    fn test_parse_tagged_ports() {
+        let (ports, protocols) = parse_tagged_ports("80/tcp,53/udp".to_string(), false).unwrap();
+        assert_eq!(ports, PortRange::Range(vec![(80, 80), (53, 53)]));
+        assert_eq!(
+            protocols,
+            vec![((80, 80), Protocol::Tcp), ((53, 53), Protocol::Udp)]
+        );
+
+        let (ports, protocols) =
+            parse_tagged_ports("22,160-162/UDP,443".to_string(), false).unwrap();
+        assert_eq!(
+            ports,
+            PortRange::Range(vec![(22, 22), (160, 162), (443, 443)])
+        );
+        assert_eq!(protocols, vec![((160, 162), Protocol::Udp)]);
+
+        assert_eq!(
+            parse_tagged_ports("22,80".to_string(), false),
+            Ok((PortRange::Range(vec![(22, 22), (80, 80)]), Vec::new()))
+        );
+
+        // A port may be scanned over both protocols.
+        let (ports, protocols) = parse_tagged_ports("53/tcp,53/udp".to_string(), false).unwrap();
+        assert_eq!(ports, PortRange::Single(53));
+        assert_eq!(
+            protocols,
+            vec![((53, 53), Protocol::Tcp), ((53, 53), Protocol::Udp)]
+        );
+    }
+
+    #[test]
+    fn test_parse_tagged_ports_rejected() {
+        assert_eq!(
+            parse_tagged_ports("80/sctp".to_string(), false),
+            Err("Unknown protocol: sctp (expected tcp or udp)".to_string())
+        );
+        assert!(parse_tagged_ports("0/tcp".to_string(), false).is_err());
+    }
+
+    #[test]
+    fn test_assemble_ports_skips_invalid_items() {
+        let (ports, protocols, skipped) = assemble_ports("22,99999,53/udp".to_string(), false);
+        assert_eq!(ports, PortRange::Range(vec![(22, 22), (53, 53)]));
+        assert_eq!(protocols, vec![((53, 53), Protocol::Udp)]);
+        assert_eq!(
+            skipped,
+            vec![SkippedPorts {
+                ports: "99999".to_string(),
+                reason: "Invalid port: 99999".to_string(),
+            }]
+        );
+
+        let (ports, _, skipped) = assemble_ports("0".to_string(), false);
+        assert_eq!(ports.count(), 0);
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(
+            assemble_ports("22,80".to_string(), false),
+            (
+                PortRange::Range(vec![(22, 22), (80, 80)]),
+                Vec::new(),
+                Vec::new()
+            )
+        );
+    }
+
+    #[test]
+    fn test_protocol_scan_type() {
+        assert_eq!(Protocol::Udp.scan_type(ScanType::Fin), ScanType::Udp);
+        assert_eq!(Protocol::Tcp.scan_type(ScanType::Fin), ScanType::Fin);
+        assert_eq!(Protocol::Tcp.scan_type(ScanType::Udp), ScanType::Connect);
+    }
 }
@@ -0,0 +1,116 @@
+use std::collections::HashMap;
+
+/// Named port lists loaded from a presets file, referenced as `@name` in `--ports`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PortPresets {
+    presets: HashMap<String, String>,
+}
+
+impl PortPresets {
+    /// Replaces every `@name` entry of a comma-separated port specification with that preset's
+    /// ports, leaving literal entries as they are.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(String)` - If every preset is known, returns the expanded specification.
+    /// * `Err(String)` - If a preset is unknown, returns an error message naming it.
+    pub fn expand(&self, ports: &str) -> Result<String, String> {
+        let entries: Result<Vec<&str>, String> = ports
+            .split(',')
+            .map(str::trim)
+            .map(|entry| match entry.strip_prefix('@') {
+                Some(name) => self
+                    .presets
+                    .get(name)
+                    .map(String::as_str)
+                    .ok_or_else(|| format!("Unknown port preset: @{}", name)),
+                None => Ok(entry),
+            })
+            .collect();
+        Ok(entries?.join(","))
+    }
+}
+
+/// Parses a presets file: one `name = "ports"` line per preset, in TOML syntax.
+///
+/// Blank lines are ignored and `#` starts a comment that runs to the end of the line. When a
+/// name is defined more than once, the last definition wins.
+///
+/// # Returns
+///
+/// * `Ok(PortPresets)` - If every line parses, returns the presets.
+/// * `Err(String)` - If a line is not a quoted assignment, returns an error message.
+pub fn parse_presets(contents: &str) -> Result<PortPresets, String> {
+    let mut presets = HashMap::new();
+
+    for line in contents.lines() {
+        let entry = line.split('#').next().unwrap_or_default().trim();
+        if entry.is_empty() {
+            continue;
+        }
+
+        let invalid = || format!("Invalid port preset: {}", line.trim());
+        let (name, value) = entry.split_once('=').ok_or_else(invalid)?;
+        let name = name.trim();
+        let ports = value
+            .trim()
+            .strip_prefix('"')
+            .and_then(|value| value.strip_suffix('"'))
+            .ok_or_else(invalid)?;
+        let valid_name = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        if !valid_name {
+            return Err(invalid());
+        }
+        presets.insert(name.to_string(), ports.to_string());
+    }
+
+    Ok(PortPresets { presets })
+}
+
+// Unit tests >------------------------------------------------------------<
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::args::parser::parse_port_range;
+
+    const PRESETS: &str = "# Common service groups\n\
+                           web = \"80,443,8080\"\n\
+                           \n\
+                           mail = \"25,110,143\"  # no submission\n";
+
+    #[test]
+    fn test_expand_presets() {
+        let presets = parse_presets(PRESETS).unwrap();
+        assert_eq!(presets.expand("@web"), Ok("80,443,8080".to_string()));
+        assert_eq!(presets.expand("@web,22"), Ok("80,443,8080,22".to_string()));
+        assert_eq!(presets.expand("1-1024"), Ok("1-1024".to_string()));
+
+        let ports = parse_port_range(presets.expand("@web,22").unwrap()).unwrap();
+        assert_eq!(ports.count(), 4);
+        assert!([22, 80, 443, 8080].iter().all(|port| ports.contains(*port)));
+    }
+
+    #[test]
+    fn test_expand_unknown_preset() {
+        let presets = parse_presets(PRESETS).unwrap();
+        assert_eq!(
+            presets.expand("@web,@db"),
+            Err("Unknown port preset: @db".to_string())
+        );
+        assert!(PortPresets::default().expand("@web").is_err());
+    }
+
+    #[test]
+    fn test_parse_presets_rejects_malformed_lines() {
+        assert_eq!(
+            parse_presets("web = 80,443"),
+            Err("Invalid port preset: web = 80,443".to_string())
+        );
+        assert!(parse_presets("= \"80\"").is_err());
+        assert!(parse_presets("web \"80\"").is_err());
+        assert!(parse_presets("my web = \"80\"").is_err());
+    }
+}
@@ -0,0 +1,22 @@
+use clap::ValueEnum;
+
+/// Ports a fast scan probes: the most common ones, from the top-ports table.
+pub const FAST_TOP_PORTS: usize = 100;
+/// Connection attempts a fast scan keeps in flight.
+pub const FAST_CONCURRENCY: usize = 500;
+/// Per-port timeout of a fast scan, in milliseconds.
+pub const FAST_TIMEOUT_MS: u32 = 300;
+/// Retries a thorough scan gives ports that did not answer.
+pub const THOROUGH_RETRIES: u32 = 2;
+
+/// Represents a bundle of defaults for a common kind of scan.
+///
+/// A profile only changes options that were not given explicitly, so `--profile fast
+/// --timeout 1000` keeps the longer timeout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Profile {
+    /// The 100 most common ports, 500 connects in flight, a 300ms timeout and shuffled order
+    Fast,
+    /// Every port, two retries for ports that did not answer, and service detection
+    Thorough,
+}
@@ -0,0 +1,254 @@
+use crate::{args::hosts::HostsFile, scan::random::entropy_seed};
+use std::{
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs, UdpSocket},
+    time::Duration,
+};
+
+/// Port DNS servers listen on when `--resolver` gives no port.
+pub const DNS_PORT: u16 = 53;
+
+/// How long to wait for each answer from a custom nameserver.
+const QUERY_TIMEOUT: Duration = Duration::from_secs(3);
+
+const TYPE_A: u16 = 1;
+const TYPE_AAAA: u16 = 28;
+const CLASS_IN: u16 = 1;
+const RCODE_NXDOMAIN: u8 = 3;
+
+/// How hostnames are turned into addresses: a hosts file first, then a nameserver.
+///
+/// Without a `nameserver`, names missing from `hosts` go to the system resolver. With one,
+/// they are sent to it directly over UDP and the system resolver is never consulted, so no
+/// query leaks to the default servers.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Resolver {
+    pub hosts: HostsFile,
+    pub nameserver: Option<SocketAddr>,
+}
+
+impl Resolver {
+    /// Resolves `name` to a single address, preferring IPv4.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(IpAddr)` - If the name resolves, returns the mapped or first resolved address.
+    /// * `Err(String)` - If resolution fails or yields no addresses, returns an error message.
+    pub fn resolve(&self, name: &str) -> Result<IpAddr, String> {
+        if let Some(address) = self.hosts.get(name) {
+            return Ok(address);
+        }
+        match self.nameserver {
+            Some(nameserver) => query_nameserver(nameserver, name),
+            None => query_system(name),
+        }
+    }
+}
+
+/// Parses a `--resolver` value: `ip` or `ip:port`, with the port defaulting to 53.
+pub fn parse_nameserver(value: &str) -> Result<SocketAddr, String> {
+    value
+        .parse::<SocketAddr>()
+        .or_else(|_| {
+            value
+                .parse::<IpAddr>()
+                .map(|ip| SocketAddr::new(ip, DNS_PORT))
+        })
+        .map_err(|_| format!("Invalid resolver address: {}", value))
+}
+
+fn query_system(name: &str) -> Result<IpAddr, String> {
+    (name, 0)
+        .to_socket_addrs()
+        .map_err(|e| format!("Failed to resolve {}: {}", name, e))?
+        .next()
+        .map(|addr| addr.ip())
+        .ok_or_else(|| format!("No addresses found for {}", name))
+}
+
+/// Asks `nameserver` for an A record, then an AAAA record if there is none.
+fn query_nameserver(nameserver: SocketAddr, name: &str) -> Result<IpAddr, String> {
+    let fail = |e: std::io::Error| format!("Failed to resolve {} via {}: {}", name, nameserver, e);
+    let local: SocketAddr = match nameserver {
+        SocketAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+        SocketAddr::V6(_) => (Ipv6Addr::UNSPECIFIED, 0).into(),
+    };
+    let socket = UdpSocket::bind(local).map_err(fail)?;
+    socket.connect(nameserver).map_err(fail)?;
+    socket.set_read_timeout(Some(QUERY_TIMEOUT)).map_err(fail)?;
+
+    for record_type in [TYPE_A, TYPE_AAAA] {
+        let id = entropy_seed() as u16;
+        socket
+            .send(&build_query(id, name, record_type)?)
+            .map_err(fail)?;
+
+        let mut response = [0u8; 512];
+        let len = socket.recv(&mut response).map_err(fail)?;
+        if let Some(address) = parse_response(&response[..len], id, record_type)
+            .map_err(|e| format!("Failed to resolve {} via {}: {}", name, nameserver, e))?
+        {
+            return Ok(address);
+        }
+    }
+    Err(format!("No addresses found for {}", name))
+}
+
+/// Builds a recursive query for one record of `record_type`.
+fn build_query(id: u16, name: &str, record_type: u16) -> Result<Vec<u8>, String> {
+    let mut query = Vec::with_capacity(18 + name.len());
+    query.extend_from_slice(&id.to_be_bytes());
+    // Standard query, recursion desired, one question.
+    query.extend_from_slice(&[0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0]);
+    for label in name.trim_end_matches('.').split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err(format!("Invalid hostname: {}", name));
+        }
+        query.push(label.len() as u8);
+        query.extend_from_slice(label.as_bytes());
+    }
+    query.push(0);
+    query.extend_from_slice(&record_type.to_be_bytes());
+    query.extend_from_slice(&CLASS_IN.to_be_bytes());
+    Ok(query)
+}
+
+/// Returns the first answer of `record_type` in a response to query `id`, if it has one.
+fn parse_response(response: &[u8], id: u16, record_type: u16) -> Result<Option<IpAddr>, String> {
+    let truncated = || "truncated DNS response".to_string();
+    let u16_at = |offset: usize| -> Result<u16, String> {
+        response
+            .get(offset..offset + 2)
+            .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+            .ok_or_else(truncated)
+    };
+
+    if response.len() < 12 {
+        return Err(truncated());
+    }
+    if u16_at(0)? != id || response[2] & 0x80 == 0 {
+        return Err("unexpected DNS response".to_string());
+    }
+    match response[3] & 0x0F {
+        0 => {}
+        RCODE_NXDOMAIN => return Ok(None),
+        rcode => return Err(format!("DNS error code {}", rcode)),
+    }
+
+    let (questions, answers) = (u16_at(4)?, u16_at(6)?);
+    let mut offset = 12;
+    for _ in 0..questions {
+        offset = skip_name(response, offset).ok_or_else(truncated)? + 4;
+    }
+    for _ in 0..answers {
+        offset = skip_name(response, offset).ok_or_else(truncated)?;
+        let (kind, class) = (u16_at(offset)?, u16_at(offset + 2)?);
+        let len = u16_at(offset + 8)? as usize;
+        let data = response
+            .get(offset + 10..offset + 10 + len)
+            .ok_or_else(truncated)?;
+        offset += 10 + len;
+
+        if kind != record_type || class != CLASS_IN {
+            continue;
+        }
+        if let Ok(octets) = <[u8; 4]>::try_from(data) {
+            return Ok(Some(IpAddr::from(octets)));
+        }
+        if let Ok(octets) = <[u8; 16]>::try_from(data) {
+            return Ok(Some(IpAddr::from(octets)));
+        }
+    }
+    Ok(None)
+}
+
+/// Returns the offset just past the (possibly compressed) name starting at `offset`.
+fn skip_name(message: &[u8], mut offset: usize) -> Option<usize> {
+    loop {
+        let len = *message.get(offset)?;
+        match len {
+            0 => return Some(offset + 1),
+            len if len & 0xC0 == 0xC0 => return Some(offset + 2),
+            len => offset += 1 + len as usize,
+        }
+    }
+}
+
+// Unit tests >------------------------------------------------------------<
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    /// Answers A queries with 10.20.30.40 and everything else with no records.
+    fn fake_nameserver(queries: usize) -> (SocketAddr, thread::JoinHandle<()>) {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = socket.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            for _ in 0..queries {
+                let mut query = [0u8; 512];
+                let (len, peer) = socket.recv_from(&mut query).unwrap();
+                let query = &query[..len];
+                let is_a = query[len - 4..len - 2] == TYPE_A.to_be_bytes();
+
+                let mut response = query[..2].to_vec();
+                response.extend_from_slice(&[0x81, 0x80, 0, 1, 0, is_a as u8, 0, 0, 0, 0]);
+                response.extend_from_slice(&query[12..]);
+                if is_a {
+                    response.extend_from_slice(&[0xC0, 0x0C, 0, 1, 0, 1, 0, 0, 0, 60, 0, 4]);
+                    response.extend_from_slice(&[10, 20, 30, 40]);
+                }
+                socket.send_to(&response, peer).unwrap();
+            }
+        });
+        (addr, server)
+    }
+
+    #[test]
+    fn test_resolver_uses_configured_nameserver() {
+        let (nameserver, server) = fake_nameserver(1);
+        let resolver = Resolver {
+            nameserver: Some(nameserver),
+            ..Resolver::default()
+        };
+
+        // The name only exists on the fake server, so this can't come from the system resolver.
+        assert_eq!(
+            resolver.resolve("lab-box.invalid"),
+            Ok("10.20.30.40".parse().unwrap())
+        );
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_resolver_hosts_file_wins_over_nameserver() {
+        let resolver = Resolver {
+            hosts: crate::args::hosts::parse_hosts_file("10.9.8.7 lab-box.invalid").unwrap(),
+            // Nothing listens here; reaching it would fail the lookup.
+            nameserver: Some("127.0.0.1:9".parse().unwrap()),
+        };
+        assert_eq!(
+            resolver.resolve("lab-box.invalid"),
+            Ok("10.9.8.7".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn test_parse_nameserver() {
+        assert_eq!(
+            parse_nameserver("10.0.0.53"),
+            Ok("10.0.0.53:53".parse().unwrap())
+        );
+        assert_eq!(
+            parse_nameserver("10.0.0.53:5353"),
+            Ok("10.0.0.53:5353".parse().unwrap())
+        );
+        assert_eq!(
+            parse_nameserver("[fd00::53]:53"),
+            Ok("[fd00::53]:53".parse().unwrap())
+        );
+        assert_eq!(
+            parse_nameserver("dns.example"),
+            Err("Invalid resolver address: dns.example".to_string())
+        );
+    }
+}
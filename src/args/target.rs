@@ -0,0 +1,912 @@
+use crate::{args::resolver::Resolver, scan::jobs::Job};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+};
+
+/// Longest IPv6 block, as a prefix, that may be given as a scan target.
+///
+/// A `/112` holds 65,536 addresses; a typical `/64` subnet holds 2^64, which no scan could ever
+/// enumerate.
+pub const MIN_IPV6_PREFIX: u8 = 112;
+
+/// Most addresses an IPv6 range or stepped target may hold: as many as a `/MIN_IPV6_PREFIX`.
+const MAX_IPV6_HOSTS: u128 = 1 << (128 - MIN_IPV6_PREFIX as u32);
+
+/// Represents a scan target, which can be a single address, a CIDR block or an inclusive range.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Target {
+    Single(IpAddr),
+    Cidr(IpAddr, u8),
+    Range(IpAddr, IpAddr),
+    /// Every `step`th address from the first address up to the last, inclusive.
+    Stepped(IpAddr, IpAddr, u128),
+}
+
+impl Target {
+    /// Returns the number of host addresses covered by the target.
+    ///
+    /// Saturates at `u128::MAX` for the IPv6 `/0` block, or a step of 1 over it, which cannot be
+    /// represented exactly.
+    pub fn count(&self) -> u128 {
+        match self {
+            Target::Single(_) => 1,
+            Target::Cidr(network, prefix) => {
+                let host_bits = address_bits(network) - *prefix as u32;
+                1u128.checked_shl(host_bits).unwrap_or(u128::MAX)
+            }
+            Target::Range(start, end) => (address_value(end) - address_value(start))
+                .checked_add(1)
+                .unwrap_or(u128::MAX),
+            Target::Stepped(first, last, step) => ((address_value(last) - address_value(first))
+                / step)
+                .checked_add(1)
+                .unwrap_or(u128::MAX),
+        }
+    }
+
+    /// Returns an iterator over every host address covered by the target.
+    pub fn hosts(&self) -> Box<dyn Iterator<Item = IpAddr> + Send> {
+        match *self {
+            Target::Single(address) => Box::new(std::iter::once(address)),
+            Target::Cidr(IpAddr::V4(network), prefix) => {
+                let start = u32::from(network);
+                let end = start | host_mask_v4(prefix);
+                Box::new((start..=end).map(|n| IpAddr::V4(Ipv4Addr::from(n))))
+            }
+            Target::Cidr(IpAddr::V6(network), prefix) => {
+                let start = u128::from(network);
+                let end = start | host_mask_v6(prefix);
+                Box::new((start..=end).map(|n| IpAddr::V6(Ipv6Addr::from(n))))
+            }
+            Target::Range(IpAddr::V4(start), IpAddr::V4(end)) => {
+                Box::new((u32::from(start)..=u32::from(end)).map(|n| IpAddr::V4(Ipv4Addr::from(n))))
+            }
+            Target::Range(start, end) => Box::new(
+                (address_value(&start)..=address_value(&end))
+                    .map(|n| IpAddr::V6(Ipv6Addr::from(n))),
+            ),
+            Target::Stepped(first, _, step) => Box::new(
+                (0..self.count()).map(move |i| from_value(first, address_value(&first) + i * step)),
+            ),
+        }
+    }
+
+    /// Returns the `index`th host address of the target, in `hosts` order.
+    pub fn nth_host(&self, index: u128) -> Option<IpAddr> {
+        if index >= self.count() {
+            return None;
+        }
+        let step = match self {
+            Target::Stepped(_, _, step) => *step,
+            _ => 1,
+        };
+        let first = self.bounds().0;
+        Some(from_value(first, address_value(&first) + index * step))
+    }
+
+    /// Returns whether every address of the target is also covered by `other`.
+    pub fn is_within(&self, other: &Target) -> bool {
+        let (first, last) = self.bounds();
+        let (other_first, other_last) = other.bounds();
+        first.is_ipv4() == other_first.is_ipv4()
+            && address_value(&other_first) <= address_value(&first)
+            && address_value(&last) <= address_value(&other_last)
+    }
+
+    /// Returns the first and last address of the target.
+    fn bounds(&self) -> (IpAddr, IpAddr) {
+        match *self {
+            Target::Single(address) => (address, address),
+            Target::Cidr(IpAddr::V4(network), prefix) => (
+                IpAddr::V4(network),
+                IpAddr::V4(Ipv4Addr::from(u32::from(network) | host_mask_v4(prefix))),
+            ),
+            Target::Cidr(IpAddr::V6(network), prefix) => (
+                IpAddr::V6(network),
+                IpAddr::V6(Ipv6Addr::from(u128::from(network) | host_mask_v6(prefix))),
+            ),
+            Target::Range(start, end) => (start, end),
+            Target::Stepped(first, last, step) => {
+                // The last address probed, which `last` only is when the step lands on it.
+                let span = address_value(&last) - address_value(&first);
+                (first, from_value(first, address_value(&last) - span % step))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Target::Single(address) => write!(f, "{}", address),
+            Target::Cidr(network, prefix) => write!(f, "{}/{}", network, prefix),
+            Target::Range(start, end) => write!(f, "{}-{}", start, end),
+            Target::Stepped(first, last, step) => write!(f, "{}-{} step {:#x}", first, last, step),
+        }
+    }
+}
+
+/// A target that was dropped while assembling the target list, and why.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SkippedTarget {
+    pub target: String,
+    pub reason: String,
+}
+
+/// Returns the one spelling of `address` every target, pair and result uses: IPv4-mapped IPv6
+/// addresses (`::ffff:10.0.0.1`) become the IPv4 address they map, and any other address is
+/// kept as is.
+///
+/// IPv6 addresses need nothing more: they are compared as numbers, not strings, and always
+/// written in RFC 5952 compressed form, so `2001:0db8:0000::1` and `2001:db8::1` are already
+/// the same host.
+pub fn normalize_address(address: IpAddr) -> IpAddr {
+    address.to_canonical()
+}
+
+/// Parses a single target specification (`10.0.0.1`, `10.0.0.0/24`, `2001:db8::/120`,
+/// `10.0.0.10-10.0.0.50`, `10.0.0.10-50`, `10.0.0.*`).
+///
+/// CIDR blocks are normalized to their network address, so `10.0.0.7/24` becomes `10.0.0.0/24`.
+/// Dashed ranges are inclusive; the last-octet shorthand (`10.0.0.10-50`) is IPv4 only.
+/// Wildcards are IPv4 only and must be trailing octets: `10.0.*.*` is `10.0.0.0/16`.
+/// IPv6 CIDR blocks must have a prefix of at least `MIN_IPV6_PREFIX`, since larger blocks are
+/// too big to scan, and IPv6 ranges may hold no more addresses than such a block.
+///
+/// A block or range followed by `step N` (decimal or `0x` hex) only covers every `N`th address,
+/// for sparse sweeps of large IPv6 blocks: `2001:db8::1/96 step 0x10000` is `::1` of every
+/// `/112` in the block. A CIDR's host bits are kept as the starting address, and the stepped
+/// target may hold no more addresses than a `/MIN_IPV6_PREFIX` block.
+///
+/// # Arguments
+///
+/// * `target` - A string representing a single target.
+///
+/// # Returns
+///
+/// * `Ok(Target)` - If the parsing is successful, returns a `Target` enum.
+/// * `Err(String)` - If the parsing fails, returns an error message.
+pub fn parse_target(target: &str) -> Result<Target, String> {
+    if let Some((block, step)) = target.trim().split_once(" step ") {
+        return parse_stepped(target.trim(), block.trim(), step.trim());
+    }
+    match parse_block(target)? {
+        Target::Cidr(IpAddr::V6(_), prefix) if prefix < MIN_IPV6_PREFIX => Err(format!(
+            "IPv6 block too large to scan: {} holds 2^{} addresses; use a /{} or longer prefix",
+            target.trim(),
+            128 - prefix as u32,
+            MIN_IPV6_PREFIX
+        )),
+        range @ Target::Range(IpAddr::V6(_), _) if range.count() > MAX_IPV6_HOSTS => {
+            Err(format!(
+                "IPv6 range too large to scan: {} holds {} addresses, more than {}; use a /{} or smaller range",
+                target.trim(),
+                range.count(),
+                MAX_IPV6_HOSTS,
+                MIN_IPV6_PREFIX
+            ))
+        }
+        parsed => Ok(parsed),
+    }
+}
+
+/// Parses a target specification like `parse_target`, but accepts IPv6 blocks of any size.
+///
+/// Allowlists describe scope rather than hosts to enumerate, so they may name a whole `/64`.
+fn parse_block(target: &str) -> Result<Target, String> {
+    let target = target.trim();
+
+    // CIDR block
+    if let Some((address, prefix)) = target.split_once('/') {
+        let address = address
+            .parse::<IpAddr>()
+            .map_err(|_| format!("Invalid CIDR address: {}", target))?;
+        let prefix = prefix
+            .parse::<u8>()
+            .ok()
+            .filter(|p| *p as u32 <= address_bits(&address))
+            .ok_or_else(|| format!("Invalid CIDR prefix: {}", target))?;
+
+        let network = match address {
+            IpAddr::V4(v4) => IpAddr::V4(Ipv4Addr::from(u32::from(v4) & !host_mask_v4(prefix))),
+            IpAddr::V6(v6) => IpAddr::V6(Ipv6Addr::from(u128::from(v6) & !host_mask_v6(prefix))),
+        };
+        return Ok(Target::Cidr(network, prefix));
+    }
+
+    // Dashed range
+    if let Some((start, end)) = target.split_once('-') {
+        return parse_range(target, start.trim(), end.trim());
+    }
+
+    // Wildcard octets
+    if target.contains('*') {
+        return parse_wildcard(target);
+    }
+
+    // Single address
+    target
+        .parse::<IpAddr>()
+        .map(|address| Target::Single(normalize_address(address)))
+        .map_err(|_| format!("Invalid target: {}", target))
+}
+
+/// Parses a comma-separated list of target specifications.
+///
+/// # Arguments
+///
+/// * `targets` - A string representing one or more targets (`10.0.0.1,10.0.1.0/24`).
+///
+/// # Returns
+///
+/// * `Ok(Vec<Target>)` - If every target parses, returns them in the given order.
+/// * `Err(String)` - If any target fails to parse, returns its error message.
+pub fn parse_targets(targets: String) -> Result<Vec<Target>, String> {
+    targets.split(',').map(parse_target).collect()
+}
+
+/// Builds the scan target list from a comma-separated specification, resolving hostnames.
+///
+/// Unlike `parse_targets`, a bad entry does not fail the whole list: entries that don't parse
+/// and names that don't resolve are returned as `SkippedTarget`s so they can be reported.
+/// Hostnames are resolved through `resolver`.
+pub fn assemble_targets(targets: String, resolver: &Resolver) -> (Vec<Target>, Vec<SkippedTarget>) {
+    let mut assembled = Vec::new();
+    let mut skipped = Vec::new();
+
+    for target in targets.split(',').map(str::trim) {
+        let result = match parse_target(target) {
+            Err(_) if is_hostname(target) => resolve_target(target, resolver).map(Target::Single),
+            result => result,
+        };
+
+        match result {
+            Ok(parsed) => assembled.push(parsed),
+            Err(reason) => skipped.push(SkippedTarget {
+                target: target.to_string(),
+                reason,
+            }),
+        }
+    }
+
+    (assembled, skipped)
+}
+
+/// Parses an allowlist file: one address, CIDR block or range per line.
+///
+/// Blank lines and lines starting with `#` are ignored.
+///
+/// # Returns
+///
+/// * `Ok(Vec<Target>)` - If every entry parses, returns them in file order.
+/// * `Err(String)` - If an entry fails to parse, returns its error message.
+pub fn parse_allowlist(contents: &str) -> Result<Vec<Target>, String> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(parse_block)
+        .collect()
+}
+
+/// Parses a pairs file: one `host:port` or `host port` per line, scanned exactly as listed.
+///
+/// IPv6 addresses use brackets in the `host:port` form (`[fd00::1]:22`). Hostnames are
+/// resolved through `resolver`. Blank lines and lines starting with `#` are ignored, and
+/// repeated pairs are kept once.
+///
+/// # Returns
+///
+/// * `Ok(Vec<Job>)` - If every line parses, returns the pairs in file order.
+/// * `Err(String)` - If a line is malformed or its host does not resolve, returns an error.
+pub fn parse_pairs(contents: &str, resolver: &Resolver) -> Result<Vec<Job>, String> {
+    let mut pairs: Vec<Job> = Vec::new();
+    for line in contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+    {
+        let invalid = || format!("Invalid host and port pair: {}", line);
+        let (host, port) = match line.split_once(char::is_whitespace) {
+            Some((host, port)) => (host, port.trim()),
+            None => line.rsplit_once(':').ok_or_else(invalid)?,
+        };
+        let host = host
+            .strip_prefix('[')
+            .and_then(|host| host.strip_suffix(']'))
+            .unwrap_or(host);
+
+        let address = match host.parse::<IpAddr>() {
+            Ok(address) => normalize_address(address),
+            Err(_) if is_hostname(host) => resolve_target(host, resolver)?,
+            Err(_) => return Err(invalid()),
+        };
+        let port = port
+            .parse::<u16>()
+            .ok()
+            .filter(|port| *port != 0)
+            .ok_or_else(invalid)?;
+
+        let job = Job {
+            address,
+            port,
+            scope_id: 0,
+            protocol: None,
+        };
+        if !pairs.contains(&job) {
+            pairs.push(job);
+        }
+    }
+    Ok(pairs)
+}
+
+/// Refuses any target that is not entirely inside a single allowlist entry.
+///
+/// An empty allowlist refuses every target.
+///
+/// # Returns
+///
+/// * `Ok(())` - If every target is in scope.
+/// * `Err(String)` - If a target is out of scope, naming the first one found.
+pub fn check_allowlist(targets: &[Target], allowlist: &[Target]) -> Result<(), String> {
+    match targets
+        .iter()
+        .find(|target| !allowlist.iter().any(|allowed| target.is_within(allowed)))
+    {
+        Some(target) => Err(format!("Target is outside the allowlist: {}", target)),
+        None => Ok(()),
+    }
+}
+
+/// Resolves a hostname through `resolver`: its hosts file, then its nameserver or the system
+/// resolver.
+///
+/// # Returns
+///
+/// * `Ok(IpAddr)` - If the name resolves, returns the mapped or first resolved address.
+/// * `Err(String)` - If resolution fails or yields no addresses, returns an error message.
+pub fn resolve_target(name: &str, resolver: &Resolver) -> Result<IpAddr, String> {
+    resolver.resolve(name).map(normalize_address)
+}
+
+/// Returns whether `name` is a syntactically valid DNS hostname (RFC 1123).
+///
+/// A purely numeric last label is rejected so that malformed addresses like `999.1.1.1` are
+/// reported as invalid rather than looked up.
+fn is_hostname(name: &str) -> bool {
+    let name = name.strip_suffix('.').unwrap_or(name);
+    let valid_label = |label: &str| {
+        (1..=63).contains(&label.len())
+            && label
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b == b'-')
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+    };
+
+    name.len() <= 253
+        && name.split('.').all(valid_label)
+        && !name
+            .rsplit('.')
+            .next()
+            .is_some_and(|tld| tld.bytes().all(|b| b.is_ascii_digit()))
+}
+
+fn parse_range(target: &str, start: &str, end: &str) -> Result<Target, String> {
+    let start = start
+        .parse::<IpAddr>()
+        .map(normalize_address)
+        .map_err(|_| format!("Invalid start address: {}", target))?;
+
+    let end = match (start, end.parse::<IpAddr>(), end.parse::<u8>()) {
+        (_, Ok(end), _) => normalize_address(end),
+        (IpAddr::V4(v4), Err(_), Ok(last_octet)) => {
+            let [a, b, c, _] = v4.octets();
+            IpAddr::V4(Ipv4Addr::new(a, b, c, last_octet))
+        }
+        _ => return Err(format!("Invalid end address: {}", target)),
+    };
+
+    if start.is_ipv4() != end.is_ipv4() {
+        return Err(format!("Range mixes IPv4 and IPv6: {}", target));
+    }
+    if address_value(&start) > address_value(&end) {
+        return Err(format!(
+            "Start address is greater than end address: {}",
+            target
+        ));
+    }
+
+    Ok(Target::Range(start, end))
+}
+
+/// Parses a block or range with a `step`, starting at the block's own address rather than its
+/// network address.
+fn parse_stepped(target: &str, block: &str, step: &str) -> Result<Target, String> {
+    let step = match step.strip_prefix("0x") {
+        Some(hex) => u128::from_str_radix(hex, 16),
+        None => step.parse::<u128>(),
+    }
+    .ok()
+    .filter(|step| *step > 0)
+    .ok_or_else(|| format!("Invalid step: {}", target))?;
+
+    let (network, last) = parse_block(block)?.bounds();
+    let first = match block.split_once('/') {
+        Some((address, _)) => address.trim().parse::<IpAddr>().unwrap_or(network),
+        None => network,
+    };
+    let stepped = Target::Stepped(first, last, step);
+
+    if first.is_ipv6() && stepped.count() > MAX_IPV6_HOSTS {
+        return Err(format!(
+            "IPv6 block too large to scan: {} holds {} addresses, more than {}; use a larger step",
+            target,
+            stepped.count(),
+            MAX_IPV6_HOSTS
+        ));
+    }
+    Ok(stepped)
+}
+
+/// Parses an IPv4 address whose trailing octets are `*` into the equivalent CIDR block.
+fn parse_wildcard(target: &str) -> Result<Target, String> {
+    let invalid = || format!("Invalid wildcard target: {}", target);
+    let octets: Vec<&str> = target.split('.').collect();
+    if octets.len() != 4 {
+        return Err(invalid());
+    }
+
+    let fixed = octets.iter().take_while(|octet| **octet != "*").count();
+    if octets[fixed..].iter().any(|octet| *octet != "*") {
+        return Err(invalid());
+    }
+
+    let mut address = [0u8; 4];
+    for (byte, octet) in address.iter_mut().zip(&octets[..fixed]) {
+        *byte = octet.parse().map_err(|_| invalid())?;
+    }
+    Ok(Target::Cidr(
+        IpAddr::V4(Ipv4Addr::from(address)),
+        (fixed * 8) as u8,
+    ))
+}
+
+fn address_value(address: &IpAddr) -> u128 {
+    match address {
+        IpAddr::V4(v4) => u32::from(*v4) as u128,
+        IpAddr::V6(v6) => u128::from(*v6),
+    }
+}
+
+/// Returns the address of `address`'s family with the numeric value `value`.
+fn from_value(address: IpAddr, value: u128) -> IpAddr {
+    match address {
+        IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::from(value as u32)),
+        IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::from(value)),
+    }
+}
+
+fn address_bits(address: &IpAddr) -> u32 {
+    match address {
+        IpAddr::V4(_) => 32,
+        IpAddr::V6(_) => 128,
+    }
+}
+
+fn host_mask_v4(prefix: u8) -> u32 {
+    u32::MAX.checked_shr(prefix as u32).unwrap_or(0)
+}
+
+fn host_mask_v6(prefix: u8) -> u128 {
+    u128::MAX.checked_shr(prefix as u32).unwrap_or(0)
+}
+
+// Unit tests >------------------------------------------------------------<
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_target_single() {
+        let result = parse_target("192.168.1.1");
+        assert_eq!(result, Ok(Target::Single("192.168.1.1".parse().unwrap())));
+    }
+
+    #[test]
+    fn test_parse_target_invalid() {
+        let result = parse_target("999.1.1.1");
+        assert_eq!(result, Err("Invalid target: 999.1.1.1".to_string()));
+    }
+
+    #[test]
+    fn test_parse_target_cidr_normalizes_network() {
+        let result = parse_target("10.0.0.7/24");
+        assert_eq!(result, Ok(Target::Cidr("10.0.0.0".parse().unwrap(), 24)));
+    }
+
+    #[test]
+    fn test_parse_target_invalid_prefix() {
+        let result = parse_target("10.0.0.0/33");
+        assert_eq!(result, Err("Invalid CIDR prefix: 10.0.0.0/33".to_string()));
+    }
+
+    #[test]
+    fn test_target_count() {
+        assert_eq!(parse_target("10.0.0.1").unwrap().count(), 1);
+        assert_eq!(parse_target("10.0.0.0/24").unwrap().count(), 256);
+        assert_eq!(parse_target("10.0.0.0/8").unwrap().count(), 1 << 24);
+        assert_eq!(parse_target("2001:db8::/120").unwrap().count(), 256);
+        assert_eq!(Target::Cidr("::".parse().unwrap(), 0).count(), u128::MAX);
+    }
+
+    #[test]
+    fn test_parse_target_stepped() {
+        let target = parse_target("2001:db8::1/96 step 0x10000").unwrap();
+        assert_eq!(target.count(), 65536);
+        let first: Vec<IpAddr> = target.hosts().take(3).collect();
+        assert_eq!(
+            first,
+            vec![
+                "2001:db8::1".parse::<IpAddr>().unwrap(),
+                "2001:db8::1:1".parse().unwrap(),
+                "2001:db8::2:1".parse().unwrap(),
+            ]
+        );
+        assert_eq!(
+            target.nth_host(65535),
+            Some("2001:db8::ffff:1".parse().unwrap())
+        );
+        assert_eq!(target.nth_host(65536), None);
+        assert!(target.is_within(&Target::Cidr("2001:db8::".parse().unwrap(), 96)));
+
+        let range = parse_target("2001:db8::10-2001:db8::30 step 16").unwrap();
+        let hosts: Vec<IpAddr> = range.hosts().collect();
+        assert_eq!(
+            hosts,
+            vec![
+                "2001:db8::10".parse::<IpAddr>().unwrap(),
+                "2001:db8::20".parse().unwrap(),
+                "2001:db8::30".parse().unwrap(),
+            ]
+        );
+        assert_eq!(range.to_string(), "2001:db8::10-2001:db8::30 step 0x10");
+    }
+
+    #[test]
+    fn test_parse_target_stepped_guardrails() {
+        assert_eq!(
+            parse_target("2001:db8::/48 step 0x10000"),
+            Err("IPv6 block too large to scan: 2001:db8::/48 step 0x10000 holds 18446744073709551616 addresses, more than 65536; use a larger step".to_string())
+        );
+        // One address per /64 of a /48.
+        let sparse = parse_target("2001:db8::1/48 step 0x10000000000000000").unwrap();
+        assert_eq!(sparse.count(), 65536);
+        assert_eq!(sparse.nth_host(1), Some("2001:db8:0:1::1".parse().unwrap()));
+        assert_eq!(
+            parse_target("2001:db8::/112 step 0"),
+            Err("Invalid step: 2001:db8::/112 step 0".to_string())
+        );
+        assert!(parse_target("2001:db8::/112 step x").is_err());
+
+        // The whole space one by one holds 2^128 addresses, one more than a u128 can count.
+        let everything = Target::Stepped(
+            "::".parse().unwrap(),
+            "ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff".parse().unwrap(),
+            1,
+        );
+        assert_eq!(everything.count(), u128::MAX);
+        assert!(parse_target("::/0 step 1").is_err());
+    }
+
+    #[test]
+    fn test_parse_target_rejects_huge_ipv6_blocks() {
+        assert_eq!(
+            parse_target("2001:db8::/64"),
+            Err("IPv6 block too large to scan: 2001:db8::/64 holds 2^64 addresses; use a /112 or longer prefix".to_string())
+        );
+        assert!(parse_target("::/0").is_err());
+        assert_eq!(
+            parse_target("2001:db8::/120"),
+            Ok(Target::Cidr("2001:db8::".parse().unwrap(), 120))
+        );
+        assert_eq!(parse_target("2001:db8::/112").unwrap().count(), 65536);
+        // Dashed ranges are capped at the same host count.
+        assert_eq!(
+            parse_target("2001:db8::-2001:db8::ffff:ffff:ffff:ffff"),
+            Err("IPv6 range too large to scan: 2001:db8::-2001:db8::ffff:ffff:ffff:ffff holds 18446744073709551616 addresses, more than 65536; use a /112 or smaller range".to_string())
+        );
+        assert!(parse_target("2001:db8::1-2001:db8::1:0").is_ok());
+        assert!(parse_target("2001:db8::1-2001:db8::1:1").is_err());
+        // IPv4 blocks and allowlist scopes are not capped: allowlists are never enumerated.
+        assert!(parse_target("0.0.0.0/0").is_ok());
+        assert_eq!(
+            parse_allowlist("2001:db8::/64"),
+            Ok(vec![Target::Cidr("2001:db8::".parse().unwrap(), 64)])
+        );
+        assert!(parse_allowlist("2001:db8::-2001:db8::ffff:ffff:ffff:ffff").is_ok());
+    }
+
+    #[test]
+    fn test_target_hosts() {
+        let hosts: Vec<IpAddr> = parse_target("10.0.0.0/30").unwrap().hosts().collect();
+        assert_eq!(
+            hosts,
+            vec![
+                "10.0.0.0".parse::<IpAddr>().unwrap(),
+                "10.0.0.1".parse().unwrap(),
+                "10.0.0.2".parse().unwrap(),
+                "10.0.0.3".parse().unwrap(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_equivalent_addresses_normalize_to_one_spelling() {
+        let targets = parse_targets(
+            "2001:db8::1,2001:0db8:0000::1,2001:DB8:0:0:0:0:0:1,2001:db8:0::0:1".to_string(),
+        )
+        .unwrap();
+        let expected = Target::Single("2001:db8::1".parse().unwrap());
+        assert!(targets.iter().all(|target| *target == expected));
+        assert_eq!(targets[1].to_string(), "2001:db8::1");
+
+        assert_eq!(
+            parse_target("::ffff:10.0.0.1"),
+            Ok(Target::Single("10.0.0.1".parse().unwrap()))
+        );
+        assert_eq!(
+            parse_target("::ffff:10.0.0.1-::ffff:10.0.0.3"),
+            parse_target("10.0.0.1-3")
+        );
+        assert_eq!(normalize_address("::1".parse().unwrap()).to_string(), "::1");
+
+        let pairs = parse_pairs(
+            "[2001:db8::1]:22\n[2001:0db8:0000::1]:22\n::ffff:10.0.0.1:22\n10.0.0.1:22\n",
+            &Resolver::default(),
+        )
+        .unwrap();
+        let pairs: Vec<String> = pairs
+            .iter()
+            .map(|job| std::net::SocketAddr::new(job.address, job.port).to_string())
+            .collect();
+        assert_eq!(pairs, vec!["[2001:db8::1]:22", "10.0.0.1:22"]);
+    }
+
+    #[test]
+    fn test_parse_targets_list() {
+        let result = parse_targets("10.0.0.1,10.0.1.0/31".to_string());
+        assert_eq!(
+            result,
+            Ok(vec![
+                Target::Single("10.0.0.1".parse().unwrap()),
+                Target::Cidr("10.0.1.0".parse().unwrap(), 31),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_target_range_full_form() {
+        let target = parse_target("192.168.1.10-192.168.1.12").unwrap();
+        assert_eq!(
+            target,
+            Target::Range(
+                "192.168.1.10".parse().unwrap(),
+                "192.168.1.12".parse().unwrap()
+            )
+        );
+        assert_eq!(target.count(), 3);
+
+        let hosts: Vec<String> = target.hosts().map(|h| h.to_string()).collect();
+        assert_eq!(hosts, vec!["192.168.1.10", "192.168.1.11", "192.168.1.12"]);
+    }
+
+    #[test]
+    fn test_parse_target_range_last_octet_shorthand() {
+        let target = parse_target("192.168.1.10-50").unwrap();
+        assert_eq!(
+            target,
+            Target::Range(
+                "192.168.1.10".parse().unwrap(),
+                "192.168.1.50".parse().unwrap()
+            )
+        );
+        assert_eq!(target.count(), 41);
+    }
+
+    #[test]
+    fn test_parse_target_range_crosses_octet() {
+        let target = parse_target("10.0.0.255-10.0.1.1").unwrap();
+        let hosts: Vec<String> = target.hosts().map(|h| h.to_string()).collect();
+        assert_eq!(hosts, vec!["10.0.0.255", "10.0.1.0", "10.0.1.1"]);
+    }
+
+    #[test]
+    fn test_parse_target_range_ipv6() {
+        let target = parse_target("2001:db8::1-2001:db8::3").unwrap();
+        assert_eq!(target.count(), 3);
+    }
+
+    #[test]
+    fn test_parse_target_range_reversed() {
+        let result = parse_target("192.168.1.50-10");
+        assert_eq!(
+            result,
+            Err("Start address is greater than end address: 192.168.1.50-10".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_target_range_mixed_families() {
+        let result = parse_target("10.0.0.1-::1");
+        assert_eq!(
+            result,
+            Err("Range mixes IPv4 and IPv6: 10.0.0.1-::1".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_pairs() {
+        let resolver = Resolver {
+            hosts: crate::args::hosts::parse_hosts_file("10.9.8.7 nas.lab").unwrap(),
+            nameserver: None,
+        };
+        let pairs = parse_pairs(
+            "# re-checks\n\
+             10.0.0.1:22\n\
+             10.0.0.2 443\n\
+             \n\
+             [fd00::1]:8443\n\
+             fd00::2 80\n\
+             nas.lab:445\n\
+             10.0.0.1:22\n",
+            &resolver,
+        )
+        .unwrap();
+
+        let pairs: Vec<String> = pairs
+            .iter()
+            .map(|job| std::net::SocketAddr::new(job.address, job.port).to_string())
+            .collect();
+        assert_eq!(
+            pairs,
+            vec![
+                "10.0.0.1:22",
+                "10.0.0.2:443",
+                "[fd00::1]:8443",
+                "[fd00::2]:80",
+                "10.9.8.7:445"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_pairs_rejects_malformed_lines() {
+        let resolver = Resolver::default();
+        for line in ["10.0.0.1", "10.0.0.1:http", "10.0.0.1:0", "10.0.0.256:22"] {
+            assert_eq!(
+                parse_pairs(line, &resolver),
+                Err(format!("Invalid host and port pair: {}", line))
+            );
+        }
+    }
+
+    #[test]
+    fn test_is_hostname() {
+        assert!(is_hostname("localhost"));
+        assert!(is_hostname("scan-me.example.com."));
+        assert!(!is_hostname("999.1.1.1"));
+        assert!(!is_hostname("-bad.example.com"));
+        assert!(!is_hostname("bad..example.com"));
+    }
+
+    #[test]
+    fn test_assemble_targets_resolves_localhost() {
+        let (targets, skipped) = assemble_targets("localhost".to_string(), &Resolver::default());
+        assert_eq!(skipped, vec![]);
+        assert_eq!(targets.len(), 1);
+        assert!(matches!(targets[0], Target::Single(address) if address.is_loopback()));
+    }
+
+    #[test]
+    fn test_resolve_target_prefers_hosts_file() {
+        let hosts = Resolver {
+            hosts: crate::args::hosts::parse_hosts_file("10.9.8.7 localhost lab-box.invalid")
+                .unwrap(),
+            nameserver: None,
+        };
+        assert_eq!(
+            resolve_target("localhost", &hosts),
+            Ok("10.9.8.7".parse().unwrap())
+        );
+
+        let (targets, skipped) = assemble_targets("lab-box.invalid".to_string(), &hosts);
+        assert_eq!(skipped, vec![]);
+        assert_eq!(targets, vec![Target::Single("10.9.8.7".parse().unwrap())]);
+    }
+
+    #[test]
+    fn test_assemble_targets_collects_skipped() {
+        let (targets, skipped) = assemble_targets(
+            "nonexistent.invalid,127.0.0.1,999.1.1.1".to_string(),
+            &Resolver::default(),
+        );
+
+        assert_eq!(targets, vec![Target::Single("127.0.0.1".parse().unwrap())]);
+        assert_eq!(skipped.len(), 2);
+        assert_eq!(skipped[0].target, "nonexistent.invalid");
+        assert_eq!(
+            skipped[1],
+            SkippedTarget {
+                target: "999.1.1.1".to_string(),
+                reason: "Invalid target: 999.1.1.1".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_target_wildcard() {
+        assert_eq!(
+            parse_target("192.168.1.*"),
+            Ok(Target::Cidr("192.168.1.0".parse().unwrap(), 24))
+        );
+        assert_eq!(
+            parse_target("192.168.*.*"),
+            Ok(Target::Cidr("192.168.0.0".parse().unwrap(), 16))
+        );
+        assert_eq!(parse_target("192.168.1.*").unwrap().count(), 256);
+    }
+
+    #[test]
+    fn test_parse_target_wildcard_malformed() {
+        assert_eq!(
+            parse_target("192.*.1.5"),
+            Err("Invalid wildcard target: 192.*.1.5".to_string())
+        );
+        assert_eq!(
+            parse_target("192.168.*"),
+            Err("Invalid wildcard target: 192.168.*".to_string())
+        );
+        assert_eq!(
+            parse_target("192.168.1*.*"),
+            Err("Invalid wildcard target: 192.168.1*.*".to_string())
+        );
+    }
+
+    #[test]
+    fn test_check_allowlist_in_scope() {
+        let allowlist =
+            parse_allowlist("# lab\n10.0.0.0/16\n\n192.168.1.10-192.168.1.20\n").unwrap();
+        let targets = parse_targets("10.0.3.0/24,10.0.0.1,192.168.1.12-15".to_string()).unwrap();
+        assert_eq!(check_allowlist(&targets, &allowlist), Ok(()));
+    }
+
+    #[test]
+    fn test_check_allowlist_out_of_scope() {
+        let allowlist = parse_allowlist("10.0.0.0/16\n192.168.1.10-192.168.1.20").unwrap();
+
+        let targets = parse_targets("10.0.0.1,10.1.0.1".to_string()).unwrap();
+        assert_eq!(
+            check_allowlist(&targets, &allowlist),
+            Err("Target is outside the allowlist: 10.1.0.1".to_string())
+        );
+
+        // Partially overlapping blocks are out of scope too.
+        let targets = parse_targets("192.168.1.0/24".to_string()).unwrap();
+        assert_eq!(
+            check_allowlist(&targets, &allowlist),
+            Err("Target is outside the allowlist: 192.168.1.0/24".to_string())
+        );
+    }
+
+    #[test]
+    fn test_check_allowlist_empty_refuses_everything() {
+        let targets = parse_targets("127.0.0.1".to_string()).unwrap();
+        assert_eq!(
+            check_allowlist(&targets, &parse_allowlist("# nothing yet\n").unwrap()),
+            Err("Target is outside the allowlist: 127.0.0.1".to_string())
+        );
+    }
+}
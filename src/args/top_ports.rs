@@ -0,0 +1,86 @@
+use crate::args::parser::PortRange;
+
+/// TCP ports ranked by how often they are found open on internet hosts, most common first.
+const RANKED_PORTS: &[u16] = &[
+    80, 23, 443, 21, 22, 25, 3389, 110, 445, 139, 143, 53, 135, 3306, 8080, 1723, 111, 995, 993,
+    5900, 1025, 587, 8888, 199, 1720, 465, 548, 113, 81, 6001, 10000, 514, 5060, 179, 1026, 2000,
+    8443, 8000, 32768, 554, 26, 1433, 49152, 2001, 515, 8008, 49154, 1027, 5666, 646, 5000, 5631,
+    631, 49153, 8081, 2049, 88, 79, 5800, 106, 2121, 1110, 49155, 6000, 513, 990, 5357, 427, 49156,
+    543, 544, 5101, 144, 7, 389, 8009, 3128, 444, 9999, 5009, 7070, 5190, 3000, 5432, 1900, 3986,
+    13, 1029, 9, 5051, 6646, 49157, 1028, 873, 1755, 2717, 4899, 9100, 119, 37,
+];
+
+/// Returns the `count` most common ports of the ranked table, or the whole table if it is
+/// shorter.
+///
+/// # Returns
+///
+/// * `Ok(PortRange)` - If `count` is at least 1, returns the ports in ascending order.
+/// * `Err(String)` - If `count` is 0, returns an error message.
+pub fn top_ports(count: usize) -> Result<PortRange, String> {
+    if count == 0 {
+        return Err("Top ports count must be at least 1".to_string());
+    }
+    let mut ports: Vec<u16> = RANKED_PORTS.iter().copied().take(count).collect();
+    ports.sort_unstable();
+    Ok(PortRange::Range(
+        ports.into_iter().map(|port| (port, port)).collect(),
+    ))
+}
+
+/// Returns the most common `percent`% of the ranked table, rounded up and at least one port.
+///
+/// # Returns
+///
+/// * `Ok(PortRange)` - If `percent` is above 0 and at most 100, returns the ports.
+/// * `Err(String)` - Otherwise, returns an error message.
+pub fn top_percent(percent: f64) -> Result<PortRange, String> {
+    if !(percent > 0.0 && percent <= 100.0) {
+        return Err(format!(
+            "Invalid top ports percentage: {} (must be above 0 and at most 100)",
+            percent
+        ));
+    }
+    let count = (RANKED_PORTS.len() as f64 * percent / 100.0).ceil() as usize;
+    top_ports(count.max(1))
+}
+
+// Unit tests >------------------------------------------------------------<
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ranked_ports_are_unique() {
+        let mut ports = RANKED_PORTS.to_vec();
+        ports.sort_unstable();
+        ports.dedup();
+        assert_eq!(ports.len(), RANKED_PORTS.len());
+    }
+
+    #[test]
+    fn test_top_percent_counts() {
+        assert_eq!(RANKED_PORTS.len(), 100);
+        assert_eq!(top_percent(5.0).unwrap().count(), 5);
+        assert_eq!(top_percent(100.0).unwrap().count(), 100);
+        // Rounded up and never below one port.
+        assert_eq!(top_percent(2.5).unwrap().count(), 3);
+        assert_eq!(top_percent(0.01).unwrap().count(), 1);
+
+        let top = top_percent(3.0).unwrap();
+        assert_eq!(top.iter().collect::<Vec<u16>>(), vec![23, 80, 443]);
+    }
+
+    #[test]
+    fn test_top_percent_rejects_out_of_range() {
+        assert_eq!(
+            top_percent(0.0),
+            Err("Invalid top ports percentage: 0 (must be above 0 and at most 100)".to_string())
+        );
+        assert!(top_percent(-5.0).is_err());
+        assert!(top_percent(100.5).is_err());
+        assert!(top_percent(f64::NAN).is_err());
+        assert!(top_ports(0).is_err());
+        assert_eq!(top_ports(1000).unwrap().count(), 100);
+    }
+}
@@ -1,29 +1,118 @@
-use crate::args::parser::{parse_port_range, PortRange};
-use clap::Parser;
-use std::{net::IpAddr, path::PathBuf};
+use crate::{
+    args::{
+        hosts::parse_hosts_file,
+        interfaces::{
+            has_link_local, is_link_local, link_local_interfaces, resolve_interface, Interface,
+            OutgoingInterface,
+        },
+        parser::{
+            assemble_ports, parse_knock_sequence, parse_port_list, parse_port_range,
+            parse_tagged_ports, PortProtocols, PortRange, SkippedPorts, ALL_PORTS, DEFAULT_PORTS,
+            DEFAULT_UDP_PORTS,
+        },
+        presets::parse_presets,
+        profile::{Profile, FAST_CONCURRENCY, FAST_TIMEOUT_MS, FAST_TOP_PORTS, THOROUGH_RETRIES},
+        resolver::{parse_nameserver, Resolver},
+        target::{assemble_targets, parse_allowlist, parse_pairs, SkippedTarget, Target},
+        top_ports::{top_percent, top_ports},
+    },
+    output::{
+        severity::SortOrder,
+        template::{parse_template, Template},
+        theme::{resolve_theme, ColorTheme},
+        OutputFormat,
+    },
+    scan::{
+        banner::{BannerCharset, BannerOptions, DEFAULT_BANNER_BYTES},
+        connect::PostConnect,
+        diff::{parse_baseline, Baseline},
+        history::recently_scanned,
+        hook::{parse_hook, HookCommand, ResultHook, SharedResultHook},
+        jobs::{parse_shard, Job, Shard},
+        plan::parse_plan,
+        pool::{Window, DEFAULT_BATCH_SIZE},
+        proxy::{parse_proxy, Proxy},
+        raw::ScanType,
+        result::ScanResult,
+        watch::WatchOptions,
+        DEFAULT_CONCURRENCY, DEFAULT_MAX_JOBS,
+    },
+};
+use clap::{parser::ValueSource, ArgMatches, CommandFactory, FromArgMatches, Parser};
+use std::{
+    collections::BTreeSet,
+    env,
+    ffi::OsString,
+    fs,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    path::PathBuf,
+    time::{Duration, SystemTime},
+};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about = "Simple and fast port scanner built in Rust.")]
 struct CliArgs {
-    /// Target ip address(IPv4, IPv6)
+    /// Target ip address or CIDR block(IPv4, IPv6), comma separated(10.0.0.1,10.0.1.0/24)
     #[arg(default_value = "127.0.0.1")]
-    address: IpAddr,
+    address: String,
 
-    /// Ports of target ip address(1-1024,3000-4000)
-    #[arg(
-        short,
-        long,
-        value_name = "target_ports",
-        default_value = "1-1024",
-        conflicts_with = "all_ports"
-    )]
-    ports: String,
+    /// File of addresses, CIDR blocks and ranges(one per line) that targets must fall within
+    #[arg(long, value_name = "file")]
+    allowlist: Option<PathBuf>,
+
+    /// File of "IP name [aliases...]" lines(/etc/hosts format) consulted before DNS
+    #[arg(long, value_name = "path")]
+    hosts_file: Option<PathBuf>,
+
+    /// DNS server(ip or ip:port) to resolve target names with instead of the system resolver
+    #[arg(long, value_name = "ip:port", value_parser = parse_nameserver)]
+    resolver: Option<SocketAddr>,
+
+    /// Ports of target ip address(1-1024,3000-4000), or "all" for every port; suffix an item
+    /// with /tcp or /udp to scan it over that protocol whatever --scan-type(80/tcp,53/udp)
+    /// [default: 1-1024, or common UDP services with --scan-type udp]
+    #[arg(short, long, value_name = "target_ports", conflicts_with = "all_ports")]
+    ports: Option<String>,
+
+    /// File of named port lists(web = "80,443,8080" per line, TOML syntax) that --ports can
+    /// reference as @name(@web,22)
+    #[arg(long, value_name = "path")]
+    presets: Option<PathBuf>,
 
     ///All ports scan flag(1~65535)
     #[arg(short, long, default_value_t = false, conflicts_with = "ports")]
     all_ports: bool,
 
-    /// Number of threads used for scanning
+    /// Scan this percentage of the most commonly open ports(e.g. 5 for the top 5%)
+    #[arg(long, value_name = "percent", conflicts_with_all = ["ports", "all_ports"])]
+    top_percent: Option<f64>,
+
+    /// Number of most common ports to scan, set by --profile
+    #[arg(skip)]
+    top_ports: Option<usize>,
+
+    /// Defaults for a common kind of scan; explicit options still override them
+    #[arg(long, value_enum)]
+    profile: Option<Profile>,
+
+    /// Accept port 0 in --ports and --knock, for testing how hosts and middleboxes handle it
+    #[arg(long, default_value_t = false)]
+    allow_port_zero: bool,
+
+    /// How ports are probed; every type but connect needs raw socket privileges
+    #[arg(long, value_enum, default_value_t = ScanType::Connect)]
+    scan_type: ScanType,
+
+    /// Send raw probes with MSS and timestamp options and record the TTL and window of replies,
+    /// with an OS family guess from the TTL
+    #[arg(long, default_value_t = false)]
+    fingerprint: bool,
+
+    /// Print the available scan types and exit
+    #[arg(long, default_value_t = false)]
+    list_scan_types: bool,
+
+    /// Number of threads used for CPU-bound work (formatting sorted output)
     #[arg(
         short = 'n',
         long,
@@ -32,48 +121,1043 @@ struct CliArgs {
     )]
     threads: usize,
 
+    /// Number of connection attempts kept in flight at once
+    #[arg(
+        short,
+        long,
+        value_name = "in_flight_connects",
+        default_value_t = DEFAULT_CONCURRENCY
+    )]
+    concurrency: usize,
+
+    /// Raise concurrency while fewer than this many probes per second complete
+    #[arg(long, value_name = "pps")]
+    min_rate: Option<f64>,
+
+    /// Start at low concurrency and double it every window while few probes time out or fail,
+    /// halving it when they spike
+    #[arg(long, default_value_t = false, conflicts_with = "min_rate")]
+    slow_start: bool,
+
+    /// Number of jobs each worker takes from the queue at a time
+    #[arg(long, value_name = "n", default_value_t = DEFAULT_BATCH_SIZE)]
+    batch_size: usize,
+
+    /// Hand out jobs in windows of this many, pausing --window-pause between windows
+    #[arg(long, value_name = "n")]
+    window: Option<usize>,
+
+    /// How long to pause between --window windows(500ms, 10s)
+    #[arg(
+        long,
+        value_name = "duration",
+        value_parser = humantime::parse_duration,
+        default_value = "1s",
+        requires = "window"
+    )]
+    window_pause: Duration,
+
+    /// Stop the scan once this many open ports have been found(e.g. 100)
+    #[arg(long, value_name = "n")]
+    max_results: Option<usize>,
+
+    /// Record every probe(time, source, destination, port, result) as JSON lines in this file
+    #[arg(long, value_name = "path")]
+    trace_file: Option<PathBuf>,
+
+    /// Send probes through this network interface(e.g. eth0), from its address; raw scans also
+    /// bind to the device(Linux only)
+    #[arg(long, value_name = "name")]
+    interface: Option<String>,
+
+    /// Retry ports that did not answer this many times, each time from a new source port
+    #[arg(long, value_name = "count", default_value_t = 0)]
+    retries: u32,
+
+    /// Also retry refused connects (up to --retries) before marking a port closed
+    #[arg(long, default_value_t = false)]
+    retry_on_reset: bool,
+
+    /// Request TCP Fast Open on connects (Linux only; helps only probes that send data)
+    #[arg(long = "tfo", default_value_t = false)]
+    fast_open: bool,
+
     /// Specifies the timeout in milliseconds for each port scan
     #[arg(long = "timeout", value_name = "timeout_ms", default_value_t = 1000)]
     timeout: u32,
 
+    /// Once a host answers, give its ports 4x its smoothed round-trip time (100ms to 10s)
+    /// instead of --timeout
+    #[arg(long, default_value_t = false)]
+    rtt_timeout: bool,
+
     /// File name to save the scan results
     #[arg(short, long, value_name = "output_file_name")]
     output: Option<PathBuf>,
+
+    /// Write one file per host, named after its address, into this directory instead
+    #[arg(long, value_name = "dir", conflicts_with = "output")]
+    output_dir: Option<PathBuf>,
+
+    /// Write the scan results to this inherited file descriptor instead of stdout(Unix only)
+    #[arg(long, value_name = "fd", conflicts_with_all = ["output", "output_dir"])]
+    results_fd: Option<i32>,
+
+    /// Write warnings and skipped targets to this inherited file descriptor instead of
+    /// stderr(Unix only)
+    #[arg(long, value_name = "fd")]
+    progress_fd: Option<i32>,
+
+    /// Write Prometheus metrics of the scan to this file(node exporter textfile format),
+    /// updated every cycle with --watch
+    #[arg(long, value_name = "path")]
+    metrics_file: Option<PathBuf>,
+
+    /// Run this command for every open port found, e.g. "notify.sh {addr} {port}"(fields as in
+    /// --template; run without a shell, so each word stays one argument; at most 8 at once)
+    #[arg(long, value_name = "command")]
+    on_open: Option<String>,
+
+    /// Line template for text output, e.g. "{addr}:{port} {state} {latency}ms {service}"
+    /// (fields: addr, hostname, port, state, latency, service, banner, source)
+    #[arg(long, value_name = "template")]
+    template: Option<String>,
+
+    /// Write the SHA-256 of the output file to <output>.sha256(sha256sum format)
+    #[arg(long, default_value_t = false, requires = "output")]
+    checksum: bool,
+
+    /// Gzip-compress the scan results(implied by an output file ending in .gz)
+    #[arg(long, default_value_t = false)]
+    compress: bool,
+
+    /// Format of the scan results
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Text)]
+    format: OutputFormat,
+
+    /// Print the available output formats and exit
+    #[arg(long, default_value_t = false)]
+    list_formats: bool,
+
+    /// Write text and JSONL results as they arrive instead of sorted once the scan ends
+    #[arg(long, default_value_t = false)]
+    no_sort: bool,
+
+    /// Order of sorted results
+    #[arg(long, value_enum, default_value_t = SortOrder::Address, conflicts_with = "no_sort")]
+    sort: SortOrder,
+
+    /// Indent JSON output (has no effect on JSONL)
+    #[arg(long, default_value_t = false)]
+    pretty: bool,
+
+    /// Append a summary (host and port counts, latency percentiles, elapsed time) to the results
+    #[arg(long, default_value_t = false)]
+    summary: bool,
+
+    /// Print only the summary(counts, top services, host states), without per-port lines
+    #[arg(long, default_value_t = false)]
+    summary_only: bool,
+
+    /// Print only open, closed and filtered counts(one "address open closed filtered" line per
+    /// host, then a "total" line)
+    #[arg(long, default_value_t = false, conflicts_with = "summary_only")]
+    count_only: bool,
+
+    /// Leave hosts with no open or closed port(all filtered or down) out of text and count
+    /// output; JSON, JSONL and SARIF keep them
+    #[arg(long, default_value_t = false)]
+    only_responsive: bool,
+
+    /// Record how each port's state was decided("connection refused after 3ms") and print every
+    /// probed port with it; JSON and JSONL results carry it as "detail"
+    #[arg(long, default_value_t = false)]
+    explain: bool,
+
+    /// Start each text line with the time its probe completed; JSON and JSONL results always
+    /// carry it as "timestamp"
+    #[arg(long, default_value_t = false)]
+    show_time: bool,
+
+    /// Print one text line per group of a host's ports with identical banners and services
+    /// ("10.0.0.1 ports [8000-8010] open service=http") instead of one per port
+    #[arg(long, default_value_t = false, conflicts_with_all = ["template", "explain", "show_time"])]
+    group_services: bool,
+
+    /// Print each host with an open port as a header line with its open ports, services and
+    /// latencies indented beneath; hosts with nothing open collapse into one count line
+    #[arg(
+        long,
+        default_value_t = false,
+        conflicts_with_all = ["template", "explain", "show_time", "group_services"]
+    )]
+    tree: bool,
+
+    /// Print a final "SCAN_COMPLETE hosts=.. open=.. closed=.. filtered=.. elapsed=.." line to
+    /// stderr for log collectors(once per cycle with --watch)
+    #[arg(long, default_value_t = false)]
+    log_summary: bool,
+
+    /// Colors of the text summary counts(disabled whenever NO_COLOR is set)
+    #[arg(long, value_enum, default_value_t = ColorTheme::None)]
+    color_theme: ColorTheme,
+
+    /// Look up the PTR name of every scanned host and include it in the results
+    #[arg(long, default_value_t = false)]
+    reverse_dns: bool,
+
+    /// Read a banner from every open port
+    #[arg(short, long, default_value_t = false)]
+    banner: bool,
+
+    /// Maximum number of banner bytes to read
+    #[arg(long, value_name = "bytes", default_value_t = DEFAULT_BANNER_BYTES)]
+    banner_bytes: usize,
+
+    /// Keep reading the banner until this many lines arrive or the timeout expires
+    #[arg(long, value_name = "lines")]
+    banner_lines: Option<usize>,
+
+    /// How to decode banners that are not valid UTF-8; binary banners are always hex-escaped
+    #[arg(long, value_enum, value_name = "charset", default_value_t = BannerCharset::Utf8)]
+    banner_charset: BannerCharset,
+
+    /// Strip ANSI escape sequences and control characters from banners before showing them
+    #[arg(long, default_value_t = false)]
+    quiet_banner: bool,
+
+    /// Also keep each banner as read, before --quiet-banner stripped it, in structured output
+    #[arg(long, default_value_t = false, requires = "quiet_banner")]
+    raw_banner: bool,
+
+    /// Hold each open connection this long(5s, 1m) before closing it, for manual interaction
+    #[arg(long, value_name = "duration", value_parser = humantime::parse_duration)]
+    keep_open: Option<Duration>,
+
+    /// Always read a banner from open ports before closing them, even without --banner
+    #[arg(long, default_value_t = false, conflicts_with = "keep_open")]
+    grab_then_close: bool,
+
+    /// Inspect TLS on every open port(protocol version, cipher suite, ALPN)
+    #[arg(long, default_value_t = false)]
+    tls: bool,
+
+    /// Identify the service on every open port from its greeting or its answer to a probe
+    #[arg(long, default_value_t = false)]
+    service_detect: bool,
+
+    /// Milliseconds --service-detect waits for each protocol response(defaults to --timeout)
+    #[arg(long, value_name = "timeout_ms", requires = "service_detect")]
+    probe_timeout: Option<u32>,
+
+    /// Tunnel connections through this proxy(socks5://host:port, http://host:port); repeat to
+    /// chain proxies in order
+    #[arg(long, value_name = "proxy_url")]
+    proxy: Vec<String>,
+
+    /// File of exact host:port(or "host port") pairs to scan instead of targets x ports
+    #[arg(long, value_name = "file", conflicts_with_all = ["shuffle", "only_ports_from_baseline"])]
+    pairs_file: Option<PathBuf>,
+
+    /// JSON scan plan(an array of {"address", "port", "protocol"} jobs, as --emit-plan writes)
+    /// to scan exactly, instead of targets x ports
+    #[arg(
+        long,
+        value_name = "file",
+        conflicts_with_all = ["pairs_file", "shuffle", "only_ports_from_baseline"]
+    )]
+    plan: Option<PathBuf>,
+
+    /// Write the jobs the scan would probe to this file as a JSON plan, without scanning
+    #[arg(long, value_name = "file")]
+    emit_plan: Option<PathBuf>,
+
+    /// JSON report of an earlier scan(--format json) to compare against
+    #[arg(long, value_name = "file")]
+    baseline: Option<PathBuf>,
+
+    /// JSON reports of earlier scans(--format json), oldest first, to print a matrix of each
+    /// port that was ever open across them and this scan(a.json,b.json,c.json)
+    #[arg(long, value_name = "files", value_delimiter = ',')]
+    baselines: Vec<PathBuf>,
+
+    /// JSON reports of earlier scans with start times(--watch --format json) for
+    /// --skip-if-scanned-within
+    #[arg(long, value_name = "file", requires = "skip_if_scanned_within")]
+    scan_history: Option<PathBuf>,
+
+    /// Skip hosts the --scan-history shows were scanned less than this long ago(30m, 6h)
+    #[arg(
+        long,
+        value_name = "duration",
+        value_parser = humantime::parse_duration,
+        requires = "scan_history"
+    )]
+    skip_if_scanned_within: Option<Duration>,
+
+    /// Only probe the ports the baseline found open on each host, within --ports
+    #[arg(
+        long,
+        default_value_t = false,
+        requires = "baseline",
+        conflicts_with = "shuffle"
+    )]
+    only_ports_from_baseline: bool,
+
+    /// Ports to knock on, in order, before each probe to open knock-gated services(7000,8000,9000)
+    #[arg(long, value_name = "sequence")]
+    knock: Option<String>,
+
+    /// Probe (host, port) pairs in a pseudo-random order instead of host by host
+    #[arg(long, default_value_t = false, conflicts_with = "priority_ports")]
+    shuffle: bool,
+
+    /// Wait a random 0 to this many milliseconds before each probe, fixed per port by --seed
+    #[arg(long, value_name = "max_ms")]
+    jitter: Option<u32>,
+
+    /// Seed for every randomized choice(e.g. --shuffle order, --jitter delays), making the scan
+    /// reproducible
+    #[arg(long, value_name = "seed")]
+    seed: Option<u64>,
+
+    /// Scan only the i-th of n disjoint, equal slices of the jobs(1/4), to split a scan across
+    /// machines; combine with --emit-plan to hand each machine its slice
+    #[arg(long, value_name = "i/n", value_parser = parse_shard)]
+    shard: Option<Shard>,
+
+    /// Ports probed first on every host, in the given order(22,80,443,3389)
+    #[arg(long, value_name = "priority_ports")]
+    priority_ports: Option<String>,
+
+    /// Repeat the scan at this interval(30s, 5m), printing a timestamped report per cycle
+    #[arg(long, value_name = "interval", value_parser = humantime::parse_duration)]
+    watch: Option<Duration>,
+
+    /// Stop watching after this many cycles
+    #[arg(long, value_name = "cycles", requires = "watch")]
+    max_cycles: Option<u64>,
+
+    /// In watch mode, print only hosts whose port states changed since the previous cycle
+    #[arg(long, default_value_t = false, requires = "watch")]
+    only_changes: bool,
+
+    /// Exit with status 0 if any open port was found and 1 if none was
+    #[arg(long, default_value_t = false)]
+    exit_code: bool,
+
+    /// Proceed even if the scan exceeds the job limit
+    #[arg(short, long, default_value_t = false)]
+    yes: bool,
+
+    /// Abort the whole run if any target or port cannot be parsed, or any probe fails,
+    /// instead of skipping it
+    #[arg(long, default_value_t = false)]
+    strict: bool,
+
+    /// Maximum number of (host, port) jobs allowed without --yes
+    #[arg(long, value_name = "max_jobs", default_value_t = DEFAULT_MAX_JOBS)]
+    max_jobs: u64,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Args {
-    pub address: IpAddr,
+    pub targets: Vec<Target>,
+    pub skipped: Vec<SkippedTarget>,
+    pub allowlist: Option<Vec<Target>>,
     pub ports: PortRange,
+    /// `--ports` items that do not parse, left out of `ports`.
+    pub skipped_ports: Vec<SkippedPorts>,
+    /// Protocols of the `--ports` items suffixed with `/tcp` or `/udp`, overriding `scan_type`.
+    pub port_protocols: PortProtocols,
+    pub pairs: Option<Vec<Job>>,
+    pub emit_plan: Option<PathBuf>,
+    /// Interfaces link-local targets are scanned through; empty without link-local targets.
+    pub interfaces: Vec<Interface>,
+    /// Interface probes are sent through, chosen with `--interface`.
+    pub interface: Option<OutgoingInterface>,
+    pub baseline: Option<Vec<ScanResult>>,
+    /// Earlier scans, oldest first, compared with this one in a drift matrix.
+    pub baselines: Vec<Baseline>,
+    pub only_baseline_ports: bool,
+    /// Hosts left out of the scan because `--scan-history` shows a recent scan of them.
+    pub recently_scanned: BTreeSet<IpAddr>,
+    pub scan_type: ScanType,
+    pub fingerprint: bool,
+    pub list_scan_types: bool,
     pub threads: usize,
+    pub concurrency: usize,
+    pub min_rate: Option<f64>,
+    pub slow_start: bool,
+    pub batch_size: usize,
+    pub window: Option<Window>,
+    pub max_results: Option<usize>,
+    pub trace_file: Option<PathBuf>,
     pub timeout: u32,
+    pub rtt_timeout: bool,
+    pub retries: u32,
+    pub retry_on_reset: bool,
+    pub fast_open: bool,
     pub output: Option<PathBuf>,
+    pub output_dir: Option<PathBuf>,
+    pub metrics_file: Option<PathBuf>,
+    pub results_fd: Option<i32>,
+    pub progress_fd: Option<i32>,
+    pub checksum: bool,
+    pub compress: bool,
+    pub format: OutputFormat,
+    pub list_formats: bool,
+    pub no_sort: bool,
+    pub sort: SortOrder,
+    pub pretty: bool,
+    pub template: Option<Template>,
+    pub on_open: Option<HookCommand>,
+    /// Post-processing for every result, set through `ArgsBuilder::result_hook`.
+    pub result_hook: Option<SharedResultHook>,
+    pub summary: bool,
+    pub summary_only: bool,
+    pub count_only: bool,
+    pub only_responsive: bool,
+    pub explain: bool,
+    pub show_time: bool,
+    pub group_services: bool,
+    pub tree: bool,
+    pub log_summary: bool,
+    pub color_theme: ColorTheme,
+    pub reverse_dns: bool,
+    pub banner: bool,
+    pub banner_options: BannerOptions,
+    pub quiet_banner: bool,
+    pub raw_banner: bool,
+    pub post_connect: PostConnect,
+    pub tls: bool,
+    pub service_detect: bool,
+    pub probe_timeout: Option<u32>,
+    pub proxies: Vec<Proxy>,
+    pub knock: Vec<u16>,
+    pub priority_ports: Vec<u16>,
+    pub shuffle: bool,
+    pub seed: Option<u64>,
+    pub shard: Option<Shard>,
+    pub jitter: Option<u32>,
+    pub watch: Option<WatchOptions>,
+    pub exit_code: bool,
+    pub yes: bool,
+    pub strict: bool,
+    pub max_jobs: u64,
+}
+
+impl Default for Args {
+    /// Scans the default ports on loopback with every option at its CLI default.
+    fn default() -> Self {
+        Self {
+            targets: vec![Target::Single(IpAddr::V4(Ipv4Addr::LOCALHOST))],
+            skipped: Vec::new(),
+            allowlist: None,
+            ports: parse_port_range(DEFAULT_PORTS.to_string()).expect("DEFAULT_PORTS is valid"),
+            skipped_ports: Vec::new(),
+            port_protocols: Vec::new(),
+            pairs: None,
+            emit_plan: None,
+            interfaces: Vec::new(),
+            interface: None,
+            baseline: None,
+            baselines: Vec::new(),
+            only_baseline_ports: false,
+            recently_scanned: BTreeSet::new(),
+            scan_type: ScanType::Connect,
+            fingerprint: false,
+            list_scan_types: false,
+            threads: 1,
+            concurrency: DEFAULT_CONCURRENCY,
+            min_rate: None,
+            slow_start: false,
+            batch_size: DEFAULT_BATCH_SIZE,
+            window: None,
+            max_results: None,
+            trace_file: None,
+            timeout: 1000,
+            rtt_timeout: false,
+            retries: 0,
+            retry_on_reset: false,
+            fast_open: false,
+            output: None,
+            output_dir: None,
+            metrics_file: None,
+            results_fd: None,
+            progress_fd: None,
+            checksum: false,
+            compress: false,
+            format: OutputFormat::Text,
+            list_formats: false,
+            no_sort: false,
+            sort: SortOrder::Address,
+            pretty: false,
+            template: None,
+            on_open: None,
+            result_hook: None,
+            summary: false,
+            summary_only: false,
+            count_only: false,
+            only_responsive: false,
+            explain: false,
+            show_time: false,
+            group_services: false,
+            tree: false,
+            log_summary: false,
+            color_theme: ColorTheme::None,
+            reverse_dns: false,
+            banner: false,
+            banner_options: BannerOptions::default(),
+            quiet_banner: false,
+            raw_banner: false,
+            post_connect: PostConnect::Close,
+            tls: false,
+            service_detect: false,
+            probe_timeout: None,
+            proxies: Vec::new(),
+            knock: Vec::new(),
+            priority_ports: Vec::new(),
+            shuffle: false,
+            seed: None,
+            shard: None,
+            jitter: None,
+            watch: None,
+            exit_code: false,
+            yes: false,
+            strict: false,
+            max_jobs: DEFAULT_MAX_JOBS,
+        }
+    }
 }
 
-#[allow(clippy::new_without_default)]
 impl Args {
     pub fn new() -> Self {
-        let cli = CliArgs::parse();
+        Self::from_matches(CliArgs::command().get_matches())
+    }
 
-        let target_ports = if cli.all_ports {
-            "1-65535".to_string()
-        } else {
-            cli.ports
+    /// Builds `Args` from an explicit argument list instead of the process arguments.
+    pub fn parse_from<I, T>(itr: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        Self::from_matches(CliArgs::command().get_matches_from(itr))
+    }
+
+    fn from_matches(matches: ArgMatches) -> Self {
+        let mut cli = CliArgs::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+        apply_profile(&mut cli, &matches);
+        Self::from_cli(cli)
+    }
+
+    fn from_cli(cli: CliArgs) -> Self {
+        let target_ports = match cli.ports {
+            _ if cli.all_ports => ALL_PORTS.to_string(),
+            Some(ports) if is_all_ports(&ports) => ALL_PORTS.to_string(),
+            Some(ports) if ports.contains('@') => {
+                let presets = cli
+                    .presets
+                    .map(|path| {
+                        let contents = fs::read_to_string(path).expect("Failed to read presets.");
+                        parse_presets(&contents).expect("Failed to parse presets.")
+                    })
+                    .unwrap_or_default();
+                presets
+                    .expand(&ports)
+                    .expect("Failed to expand port presets.")
+            }
+            Some(ports) => ports,
+            None if cli.scan_type == ScanType::Udp => DEFAULT_UDP_PORTS.to_string(),
+            None => DEFAULT_PORTS.to_string(),
         };
 
-        let ports = parse_port_range(target_ports).expect("Failed to parse ports range.");
+        let (ports, mut port_protocols, skipped_ports) = match (cli.top_percent, cli.top_ports) {
+            (Some(percent), _) => (
+                top_percent(percent).expect("Failed to select top ports."),
+                Vec::new(),
+                Vec::new(),
+            ),
+            (None, Some(count)) => (
+                top_ports(count).expect("Failed to select top ports."),
+                Vec::new(),
+                Vec::new(),
+            ),
+            (None, None) => assemble_ports(target_ports, cli.allow_port_zero),
+        };
+        let hosts = cli
+            .hosts_file
+            .map(|path| {
+                let contents = fs::read_to_string(path).expect("Failed to read hosts file.");
+                parse_hosts_file(&contents).expect("Failed to parse hosts file.")
+            })
+            .unwrap_or_default();
+        let resolver = Resolver {
+            hosts,
+            nameserver: cli.resolver,
+        };
+        let (targets, skipped) = assemble_targets(cli.address, &resolver);
+        let mut pairs = cli.pairs_file.map(|path| {
+            let contents = fs::read_to_string(path).expect("Failed to read pairs file.");
+            parse_pairs(&contents, &resolver).expect("Failed to parse pairs file.")
+        });
+        if let Some(path) = cli.plan {
+            let contents = fs::read_to_string(path).expect("Failed to read scan plan.");
+            let (jobs, protocols) = parse_plan(&contents).expect("Failed to parse scan plan.");
+            pairs = Some(jobs);
+            port_protocols = protocols;
+        }
+        let link_local_pairs = pairs.iter().flatten().any(|job| is_link_local(job.address));
+        let interface = cli
+            .interface
+            .map(|name| resolve_interface(&name).expect("Failed to resolve interface."));
+        let mut interfaces = if has_link_local(&targets) || link_local_pairs {
+            link_local_interfaces().expect("Failed to list network interfaces.")
+        } else {
+            Vec::new()
+        };
+        if let Some(interface) = &interface {
+            interfaces.retain(|link_local| link_local.name == interface.name);
+        }
+        let allowlist = cli.allowlist.map(|path| {
+            let contents = fs::read_to_string(path).expect("Failed to read allowlist.");
+            parse_allowlist(&contents).expect("Failed to parse allowlist.")
+        });
+        let recently_scanned = match (cli.scan_history, cli.skip_if_scanned_within) {
+            (Some(path), Some(within)) => {
+                let contents = fs::read_to_string(path).expect("Failed to read scan history.");
+                recently_scanned(&contents, within, SystemTime::now())
+                    .expect("Failed to parse scan history.")
+            }
+            _ => BTreeSet::new(),
+        };
+        let baseline = cli.baseline.map(|path| {
+            let contents = fs::read_to_string(path).expect("Failed to read baseline.");
+            parse_baseline(&contents).expect("Failed to parse baseline.")
+        });
+        let baselines = cli
+            .baselines
+            .into_iter()
+            .map(|path| {
+                let contents = fs::read_to_string(&path).expect("Failed to read baseline.");
+                Baseline {
+                    name: path.file_name().map_or_else(
+                        || path.display().to_string(),
+                        |name| name.to_string_lossy().into_owned(),
+                    ),
+                    results: parse_baseline(&contents).expect("Failed to parse baseline."),
+                }
+            })
+            .collect();
+        let knock = cli
+            .knock
+            .map(|sequence| {
+                parse_knock_sequence(sequence, cli.allow_port_zero)
+                    .expect("Failed to parse knock sequence.")
+            })
+            .unwrap_or_default();
+        let priority_ports = cli
+            .priority_ports
+            .map(|ports| parse_port_list(ports).expect("Failed to parse priority ports."))
+            .unwrap_or_default();
 
         Self {
+            targets,
+            skipped,
+            allowlist,
             ports,
-            address: cli.address,
+            skipped_ports,
+            port_protocols,
+            pairs,
+            emit_plan: cli.emit_plan,
+            interfaces,
+            interface,
+            baseline,
+            baselines,
+            only_baseline_ports: cli.only_ports_from_baseline,
+            recently_scanned,
+            scan_type: cli.scan_type,
+            fingerprint: cli.fingerprint,
+            list_scan_types: cli.list_scan_types,
             threads: cli.threads,
+            concurrency: cli.concurrency,
+            min_rate: cli.min_rate,
+            slow_start: cli.slow_start,
+            batch_size: cli.batch_size,
+            window: cli.window.map(|size| Window {
+                size,
+                pause: cli.window_pause,
+            }),
+            max_results: cli.max_results,
+            trace_file: cli.trace_file,
             timeout: cli.timeout,
+            rtt_timeout: cli.rtt_timeout,
+            retries: cli.retries,
+            retry_on_reset: cli.retry_on_reset,
+            fast_open: cli.fast_open,
             output: cli.output,
+            output_dir: cli.output_dir,
+            metrics_file: cli.metrics_file,
+            results_fd: cli.results_fd,
+            progress_fd: cli.progress_fd,
+            checksum: cli.checksum,
+            compress: cli.compress,
+            format: cli.format,
+            list_formats: cli.list_formats,
+            no_sort: cli.no_sort,
+            sort: cli.sort,
+            pretty: cli.pretty,
+            template: cli
+                .template
+                .map(|template| parse_template(&template).expect("Failed to parse template.")),
+            on_open: cli
+                .on_open
+                .map(|command| parse_hook(&command).expect("Failed to parse --on-open command.")),
+            result_hook: None,
+            summary: cli.summary || cli.summary_only,
+            summary_only: cli.summary_only,
+            count_only: cli.count_only,
+            only_responsive: cli.only_responsive,
+            explain: cli.explain,
+            show_time: cli.show_time,
+            group_services: cli.group_services,
+            tree: cli.tree,
+            log_summary: cli.log_summary,
+            color_theme: resolve_theme(cli.color_theme, env::var_os("NO_COLOR").as_deref()),
+            reverse_dns: cli.reverse_dns,
+            banner: cli.banner,
+            banner_options: BannerOptions {
+                bytes: cli.banner_bytes,
+                lines: cli.banner_lines,
+                charset: cli.banner_charset,
+            },
+            quiet_banner: cli.quiet_banner,
+            raw_banner: cli.raw_banner,
+            post_connect: match (cli.keep_open, cli.grab_then_close) {
+                (Some(hold), _) => PostConnect::KeepOpen(hold),
+                (None, true) => PostConnect::GrabThenClose,
+                (None, false) => PostConnect::Close,
+            },
+            tls: cli.tls,
+            service_detect: cli.service_detect,
+            probe_timeout: cli.probe_timeout,
+            proxies: cli
+                .proxy
+                .iter()
+                .map(|url| parse_proxy(url).expect("Failed to parse proxy."))
+                .collect(),
+            knock,
+            priority_ports,
+            shuffle: cli.shuffle,
+            seed: cli.seed,
+            shard: cli.shard,
+            jitter: cli.jitter,
+            watch: cli.watch.map(|interval| WatchOptions {
+                interval,
+                max_cycles: cli.max_cycles,
+                only_changes: cli.only_changes,
+            }),
+            exit_code: cli.exit_code,
+            yes: cli.yes,
+            strict: cli.strict,
+            max_jobs: cli.max_jobs,
+        }
+    }
+
+    /// Starts building `Args` in code, from the same defaults as the command line.
+    pub fn builder() -> ArgsBuilder {
+        ArgsBuilder::default()
+    }
+}
+
+/// Builds `Args` programmatically, validating the settings once in `build`.
+///
+/// Options without a setter keep their defaults and can be changed on the built `Args`.
+#[derive(Debug, Clone, Default)]
+pub struct ArgsBuilder {
+    address: Option<String>,
+    ports: Option<String>,
+    threads: Option<usize>,
+    concurrency: Option<usize>,
+    timeout: Option<u32>,
+    result_hook: Option<SharedResultHook>,
+}
+
+impl ArgsBuilder {
+    /// Sets the comma-separated targets: addresses, CIDR blocks, ranges or hostnames.
+    pub fn address(mut self, address: impl Into<String>) -> Self {
+        self.address = Some(address.into());
+        self
+    }
+
+    /// Sets the ports, in `--ports` syntax (`22,80/tcp,53/udp,8000-8080`).
+    pub fn ports(mut self, ports: impl Into<String>) -> Self {
+        self.ports = Some(ports.into());
+        self
+    }
+
+    /// Sets the number of threads used for CPU-bound work (formatting sorted output).
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = Some(threads);
+        self
+    }
+
+    /// Sets the number of connection attempts kept in flight at once.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = Some(concurrency);
+        self
+    }
+
+    /// Sets the per-port timeout, in milliseconds.
+    pub fn timeout(mut self, timeout_ms: u32) -> Self {
+        self.timeout = Some(timeout_ms);
+        self
+    }
+
+    /// Sets a hook that post-processes every result before it is output.
+    pub fn result_hook(mut self, hook: impl ResultHook + 'static) -> Self {
+        self.result_hook = Some(SharedResultHook::new(hook));
+        self
+    }
+
+    /// Validates the settings and builds the `Args`.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Args)` - If every target and port parses and every count is positive.
+    /// * `Err(String)` - Otherwise, returns an error message naming the first bad setting.
+    pub fn build(self) -> Result<Args, String> {
+        let mut args = Args::default();
+
+        if let Some(address) = self.address {
+            let (targets, skipped) = assemble_targets(address, &Resolver::default());
+            if let Some(skipped) = skipped.first() {
+                return Err(format!(
+                    "Invalid target {}: {}",
+                    skipped.target, skipped.reason
+                ));
+            }
+            if has_link_local(&targets) {
+                args.interfaces = link_local_interfaces()?;
+            }
+            args.targets = targets;
+        }
+        if let Some(ports) = self.ports {
+            let ports = if is_all_ports(&ports) {
+                ALL_PORTS.to_string()
+            } else {
+                ports
+            };
+            (args.ports, args.port_protocols) = parse_tagged_ports(ports, false)?;
+        }
+        for (name, value) in [("threads", self.threads), ("concurrency", self.concurrency)] {
+            if value == Some(0) {
+                return Err(format!("Invalid {}: must be at least 1", name));
+            }
+        }
+        if self.timeout == Some(0) {
+            return Err("Invalid timeout: must be at least 1ms".to_string());
+        }
+
+        args.threads = self.threads.unwrap_or(args.threads);
+        args.concurrency = self.concurrency.unwrap_or(args.concurrency);
+        args.timeout = self.timeout.unwrap_or(args.timeout);
+        args.result_hook = self.result_hook;
+        Ok(args)
+    }
+}
+
+/// Returns whether a `--ports` value is the `all` alias for every port.
+fn is_all_ports(ports: &str) -> bool {
+    ports.trim().eq_ignore_ascii_case("all")
+}
+
+/// Fills in the defaults of `cli.profile` for every option `matches` shows was not given on
+/// the command line.
+fn apply_profile(cli: &mut CliArgs, matches: &ArgMatches) {
+    let Some(profile) = cli.profile else {
+        return;
+    };
+    let given = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+    let ports_given = given("ports") || given("all_ports") || given("top_percent");
+
+    match profile {
+        Profile::Fast => {
+            if !ports_given {
+                cli.top_ports = Some(FAST_TOP_PORTS);
+            }
+            if !given("concurrency") {
+                cli.concurrency = FAST_CONCURRENCY;
+            }
+            if !given("timeout") {
+                cli.timeout = FAST_TIMEOUT_MS;
+            }
+            // Shuffling conflicts with options that fix the probe order.
+            let ordered = cli.priority_ports.is_some()
+                || cli.pairs_file.is_some()
+                || cli.plan.is_some()
+                || cli.only_ports_from_baseline;
+            if !ordered {
+                cli.shuffle = true;
+            }
+        }
+        Profile::Thorough => {
+            if !ports_given {
+                cli.all_ports = true;
+            }
+            if !given("retries") {
+                cli.retries = THOROUGH_RETRIES;
+            }
+            cli.service_detect = true;
         }
     }
 }
 
 // Unit tests >------------------------------------------------------------<
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_args_threads_and_concurrency_are_independent() {
+        let args = Args::parse_from(["port_hawk", "--threads", "2", "--concurrency", "500"]);
+        assert_eq!(args.threads, 2);
+        assert_eq!(args.concurrency, 500);
+
+        let args = Args::parse_from(["port_hawk", "-n", "8"]);
+        assert_eq!(args.threads, 8);
+        assert_eq!(args.concurrency, DEFAULT_CONCURRENCY);
+    }
+
+    #[test]
+    fn test_args_default_ports_follow_scan_type() {
+        let tcp = Args::parse_from(["port_hawk"]).ports;
+        let udp = Args::parse_from(["port_hawk", "--scan-type", "udp"]).ports;
+        assert_ne!(udp, tcp);
+        assert_eq!(tcp.count(), 1024);
+        assert_eq!(
+            udp.iter().collect::<Vec<u16>>(),
+            vec![53, 67, 68, 123, 161, 500, 514, 1900, 5353]
+        );
+
+        let explicit = Args::parse_from(["port_hawk", "--scan-type", "udp", "-p", "1-1024"]);
+        assert_eq!(explicit.ports, tcp);
+    }
+
+    #[test]
+    fn test_args_profile_fast() {
+        let args = Args::parse_from(["port_hawk", "--profile", "fast"]);
+        assert_eq!(args.ports.count(), FAST_TOP_PORTS);
+        assert!(args.ports.contains(80) && args.ports.contains(443));
+        assert_eq!(args.concurrency, FAST_CONCURRENCY);
+        assert_eq!(args.timeout, FAST_TIMEOUT_MS);
+        assert!(args.shuffle);
+        assert_eq!(args.retries, 0);
+
+        // Explicit options win over the profile.
+        let args = Args::parse_from([
+            "port_hawk",
+            "--profile",
+            "fast",
+            "--timeout",
+            "1000",
+            "-p",
+            "22",
+        ]);
+        assert_eq!(args.timeout, 1000);
+        assert_eq!(args.ports, PortRange::Single(22));
+        assert_eq!(args.concurrency, FAST_CONCURRENCY);
+    }
+
+    #[test]
+    fn test_args_profile_thorough() {
+        let args = Args::parse_from(["port_hawk", "--profile", "thorough"]);
+        assert_eq!(args.ports.count(), 65535);
+        assert_eq!(args.retries, THOROUGH_RETRIES);
+        assert!(args.service_detect);
+        assert!(!args.shuffle);
+        assert_eq!(args.timeout, 1000);
+
+        let args = Args::parse_from(["port_hawk", "--profile", "thorough", "--retries", "0"]);
+        assert_eq!(args.retries, 0);
+        assert_eq!(args.ports.count(), 65535);
+    }
+
+    #[test]
+    fn test_args_builder_valid() {
+        let args = Args::builder()
+            .address("127.0.0.1,10.0.0.0/30")
+            .ports("22,80,8000-8002")
+            .threads(4)
+            .concurrency(50)
+            .timeout(250)
+            .build()
+            .unwrap();
+        assert_eq!((args.threads, args.concurrency), (4, 50));
+        assert_eq!(
+            args,
+            Args::parse_from([
+                "port_hawk",
+                "127.0.0.1,10.0.0.0/30",
+                "-p",
+                "22,80,8000-8002",
+                "-n",
+                "4",
+                "-c",
+                "50",
+                "--timeout",
+                "250",
+            ])
+        );
+        assert_eq!(Args::builder().build(), Ok(Args::default()));
+    }
+
+    #[test]
+    fn test_args_ports_all_alias() {
+        let all = Args::parse_from(["port_hawk", "127.0.0.1", "--all-ports"]);
+        assert_eq!(all.ports, PortRange::Range(vec![(1, 65535)]));
+        for alias in ["all", "ALL", " all "] {
+            let args = Args::parse_from(["port_hawk", "127.0.0.1", "-p", alias]);
+            assert_eq!(args.ports, all.ports, "{:?}", alias);
+        }
+        assert_eq!(
+            Args::builder().ports("all").build().unwrap().ports,
+            all.ports
+        );
+    }
+
+    #[test]
+    fn test_args_builder_invalid() {
+        assert!(Args::builder().ports("80-22").build().is_err());
+        assert_eq!(
+            Args::builder().ports("0").build(),
+            Err("Port 0 is reserved: 0 (pass --allow-port-zero to scan it)".to_string())
+        );
+        assert!(Args::builder()
+            .address("10.0.0.1,10.0.0.300/24")
+            .build()
+            .unwrap_err()
+            .starts_with("Invalid target 10.0.0.300/24: "));
+        assert_eq!(
+            Args::builder().threads(0).build(),
+            Err("Invalid threads: must be at least 1".to_string())
+        );
+        assert_eq!(
+            Args::builder().concurrency(0).build(),
+            Err("Invalid concurrency: must be at least 1".to_string())
+        );
+        assert_eq!(
+            Args::builder().timeout(0).build(),
+            Err("Invalid timeout: must be at least 1ms".to_string())
+        );
+    }
+
+    #[test]
+    fn test_args_default_matches_cli_defaults() {
+        assert_eq!(Args::default(), Args::parse_from(["port_hawk"]));
+    }
+}
+
 // #[cfg(test)]
 // mod tests {
 //     use std::process::Command;
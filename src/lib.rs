@@ -1,3 +1,7 @@
 pub mod args;
 
 pub mod input_parse;
+
+pub mod output;
+
+pub mod scan;
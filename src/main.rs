@@ -1,5 +1,245 @@
-use port_hawk::input_parse::Args;
+use port_hawk::{
+    args::listing,
+    input_parse::Args,
+    output::{
+        self, checksum,
+        metrics::{self, Metrics},
+        per_host, stream,
+        summary::Summary,
+        writer::{self, OutputWriter},
+        OutputFormat, OutputOptions, Report,
+    },
+    scan::{
+        self,
+        diff::{self, DriftMatrix},
+        guidance,
+        raw::ScanType,
+        result::{PortState, ScanResult},
+        sanity, watch,
+    },
+};
+use std::{
+    fs::File,
+    io::{self, Write},
+    process,
+    sync::atomic::AtomicBool,
+    time::{Duration, Instant},
+};
 
 fn main() {
-    Args::new();
+    let args = Args::new();
+    if args.list_formats || args.list_scan_types {
+        if args.list_formats {
+            print!("{}", listing::list::<OutputFormat>());
+        }
+        if args.list_scan_types {
+            print!("{}", listing::list::<ScanType>());
+        }
+        return;
+    }
+
+    let mut progress: Box<dyn Write> = match args.progress_fd {
+        Some(fd) => Box::new(writer::open_fd(fd).expect("Failed to open progress descriptor.")),
+        None => Box::new(io::stderr()),
+    };
+    // Under --strict the scan refuses to start instead, naming the first bad target or port.
+    for skipped in args.skipped.iter().filter(|_| !args.strict) {
+        let _ = writeln!(
+            progress,
+            "Skipping target {}: {}",
+            skipped.target, skipped.reason
+        );
+    }
+    for skipped in args.skipped_ports.iter().filter(|_| !args.strict) {
+        let _ = writeln!(
+            progress,
+            "Skipping ports {}: {}",
+            skipped.ports, skipped.reason
+        );
+    }
+    for warning in sanity::validate_sanity(&args) {
+        let _ = writeln!(progress, "Warning: {}", warning);
+    }
+
+    if let Some(path) = &args.emit_plan {
+        let file = File::create(path).expect("Failed to create plan file.");
+        let mut plan = io::BufWriter::new(file);
+        let written = scan::emit_plan(&args, &mut plan)
+            .and_then(|written| {
+                plan.flush()
+                    .map(|_| written)
+                    .map_err(|e| format!("Failed to write scan plan: {}", e))
+            })
+            .unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                process::exit(1);
+            });
+        let _ = writeln!(progress, "Wrote {} jobs to {}", written, path.display());
+        return;
+    }
+
+    // With --output-dir everything goes to the per-host files.
+    let mut checksum = None;
+    let mut writer = match &args.output_dir {
+        Some(_) => OutputWriter::Plain(Box::new(io::sink())),
+        None => match (args.results_fd, &args.output) {
+            (Some(fd), _) => OutputWriter::open_fd(fd, args.compress)
+                .expect("Failed to open results descriptor."),
+            (None, Some(path)) if args.checksum => {
+                let (writer, sum) = OutputWriter::open_checksummed(path, args.compress)
+                    .expect("Failed to create output file.");
+                checksum = Some(sum);
+                writer
+            }
+            (None, output) => OutputWriter::open(output.as_deref(), args.compress)
+                .expect("Failed to create output file."),
+        },
+    };
+    let options = OutputOptions {
+        format: args.format,
+        pretty: args.pretty,
+        template: args.template.clone(),
+        sorted: !args.no_sort,
+        sort: args.sort,
+        summary_only: args.summary_only,
+        count_only: args.count_only,
+        only_responsive: args.only_responsive,
+        explain: args.explain,
+        show_time: args.show_time,
+        group_services: args.group_services,
+        tree: args.tree,
+        theme: args.color_theme,
+        threads: args.threads,
+    };
+
+    let mut any_open = false;
+    let started = Instant::now();
+    let outcome = match &args.watch {
+        Some(watch_options) => {
+            watch::watch(&args, watch_options, &AtomicBool::new(false), |cycle| {
+                let report = Report {
+                    cycle: Some(cycle.number),
+                    started_at: Some(
+                        humantime::format_rfc3339_seconds(cycle.started_at).to_string(),
+                    ),
+                    results: cycle.results.clone(),
+                    errors: args.skipped.clone(),
+                    drift: drift_matrix(&args, &cycle.results),
+                    summary: args
+                        .summary
+                        .then(|| Summary::from_results(&cycle.results).with_elapsed(cycle.elapsed)),
+                    ..Report::default()
+                };
+                any_open |= has_open(&report);
+                output::write_results(&mut writer, &report, &options)
+                    .expect("Failed to write scan results.");
+                write_per_host(&args, &report, &options);
+                write_metrics(&args, &report, cycle.elapsed);
+                write_log_summary(&args, &report, cycle.elapsed);
+                write_hints(&report);
+            })
+            .map(|_| writer)
+        }
+        None => scan::scan_streaming(&args).and_then(|(results, scan)| {
+            let plan = args.clone();
+            let options_for_hosts = options.clone();
+            let output = stream::spawn_writer(writer, results, options, move |results| {
+                let elapsed = started.elapsed();
+                let not_scanned = scan::coverage_gaps(&plan, &results);
+                Report {
+                    summary: plan.summary.then(|| {
+                        Summary::from_results(&results)
+                            .with_gaps(&not_scanned)
+                            .with_elapsed(elapsed)
+                    }),
+                    drift: drift_matrix(&plan, &results),
+                    results,
+                    errors: plan.skipped.clone(),
+                    not_scanned,
+                    ..Report::default()
+                }
+            });
+            scan.join().expect("Scan thread panicked.");
+            let (output, report) = output
+                .join()
+                .expect("Output thread panicked.")
+                .expect("Failed to write scan results.");
+            scan::check_results(&args, &report.results)?;
+            any_open = has_open(&report);
+            write_per_host(&args, &report, &options_for_hosts);
+            write_metrics(&args, &report, started.elapsed());
+            write_log_summary(&args, &report, started.elapsed());
+            write_hints(&report);
+            Ok(output)
+        }),
+    };
+
+    let writer = outcome.unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        if let Some(hint) = guidance::explain_error(&e) {
+            eprintln!("hint: {}", hint);
+        }
+        process::exit(1);
+    });
+    writer.finish().expect("Failed to write scan results.");
+    if let (Some(path), Some(checksum)) = (&args.output, &checksum) {
+        checksum::write_sidecar(path, checksum).expect("Failed to write checksum file.");
+    }
+
+    if args.exit_code {
+        process::exit(exit_code(any_open));
+    }
+}
+
+/// Writes `report` into the `--output-dir` per-host files, if one was given.
+fn write_per_host(args: &Args, report: &Report, options: &OutputOptions) {
+    if let Some(dir) = &args.output_dir {
+        per_host::write_per_host(dir, report, options, args.compress)
+            .expect("Failed to write per-host results.");
+    }
+}
+
+/// Writes the `--metrics-file` of a scan that produced `report` in `elapsed`, if one was given.
+fn write_metrics(args: &Args, report: &Report, elapsed: Duration) {
+    if let Some(path) = &args.metrics_file {
+        metrics::write_metrics_file(path, &Metrics::from_results(&report.results, elapsed))
+            .expect("Failed to write metrics file.");
+    }
+}
+
+/// Prints the `--log-summary` line of a scan that produced `report` in `elapsed` to stderr.
+fn write_log_summary(args: &Args, report: &Report, elapsed: Duration) {
+    if args.log_summary {
+        eprintln!(
+            "{}",
+            Summary::from_results(&report.results)
+                .with_elapsed(elapsed)
+                .log_line()
+        );
+    }
+}
+
+/// Compares `results` with the `--baselines`, if any were given.
+fn drift_matrix(args: &Args, results: &[ScanResult]) -> Option<DriftMatrix> {
+    (!args.baselines.is_empty()).then(|| diff::drift_matrix(&args.baselines, results))
+}
+
+/// Prints one `hint:` line to stderr per kind of failure among the report's errored ports.
+fn write_hints(report: &Report) {
+    for hint in guidance::result_guidance(&report.results) {
+        eprintln!("hint: {}", hint);
+    }
+}
+
+fn has_open(report: &Report) -> bool {
+    report.results.iter().any(|r| r.state == PortState::Open)
+}
+
+/// Computes the `--exit-code` status: 0 if any open port was found, 1 otherwise.
+fn exit_code(any_open: bool) -> i32 {
+    if any_open {
+        0
+    } else {
+        1
+    }
 }
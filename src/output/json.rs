@@ -0,0 +1,107 @@
+use crate::{output::Report, scan::result::ScanResult};
+use std::io::{self, Write};
+
+/// Writes the report as a single JSON document, compact unless `pretty` is set.
+pub fn write_json<W: Write>(writer: &mut W, report: &Report, pretty: bool) -> io::Result<()> {
+    if pretty {
+        serde_json::to_writer_pretty(&mut *writer, report)?;
+    } else {
+        serde_json::to_writer(&mut *writer, report)?;
+    }
+    writeln!(writer)
+}
+
+/// Writes every result as one compact JSON object per line.
+///
+/// `--pretty` is deliberately ignored here: indenting would break the one-object-per-line contract.
+pub fn write_jsonl<W: Write>(writer: &mut W, results: &[ScanResult]) -> io::Result<()> {
+    for result in results {
+        serde_json::to_writer(&mut *writer, result)?;
+        writeln!(writer)?;
+    }
+    Ok(())
+}
+
+// Unit tests >------------------------------------------------------------<
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        args::{resolver::Resolver, target::assemble_targets},
+        scan::result::PortState,
+    };
+    use std::time::Duration;
+
+    fn sample_results() -> Vec<ScanResult> {
+        vec![
+            ScanResult {
+                latency: Some(Duration::from_micros(1500)),
+                source_addr: Some("127.0.0.1".parse().unwrap()),
+                ..ScanResult::new("127.0.0.1".parse().unwrap(), 22, PortState::Open)
+            },
+            ScanResult::new("::1".parse().unwrap(), 80, PortState::Closed),
+        ]
+    }
+
+    fn sample_report() -> Report {
+        Report {
+            results: sample_results(),
+            ..Report::default()
+        }
+    }
+
+    #[test]
+    fn test_write_json_compact_vs_pretty() {
+        let mut compact = Vec::new();
+        let mut pretty = Vec::new();
+        write_json(&mut compact, &sample_report(), false).unwrap();
+        write_json(&mut pretty, &sample_report(), true).unwrap();
+
+        let compact = String::from_utf8(compact).unwrap();
+        let pretty = String::from_utf8(pretty).unwrap();
+        assert_eq!(compact.lines().count(), 1);
+        assert!(pretty.lines().count() > 1);
+
+        let compact: serde_json::Value = serde_json::from_str(&compact).unwrap();
+        let pretty: serde_json::Value = serde_json::from_str(&pretty).unwrap();
+        assert_eq!(compact, pretty);
+    }
+
+    #[test]
+    fn test_write_json_round_trip() {
+        let mut buf = Vec::new();
+        write_json(&mut buf, &sample_report(), true).unwrap();
+
+        let report: Report = serde_json::from_slice(&buf).unwrap();
+        assert_eq!(report, sample_report());
+    }
+
+    #[test]
+    fn test_write_json_errors_section() {
+        let (_, errors) = assemble_targets("nonexistent.invalid".to_string(), &Resolver::default());
+        let report = Report {
+            errors,
+            ..sample_report()
+        };
+
+        let mut buf = Vec::new();
+        write_json(&mut buf, &report, false).unwrap();
+
+        let json: serde_json::Value = serde_json::from_slice(&buf).unwrap();
+        assert_eq!(json["errors"][0]["target"], "nonexistent.invalid");
+        assert!(json["errors"][0]["reason"].is_string());
+    }
+
+    #[test]
+    fn test_write_jsonl_one_object_per_line() {
+        let mut buf = Vec::new();
+        write_jsonl(&mut buf, &sample_results()).unwrap();
+
+        let output = String::from_utf8(buf).unwrap();
+        let parsed: Vec<ScanResult> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(parsed, sample_results());
+    }
+}
@@ -0,0 +1,112 @@
+use crate::scan::result::{PortState, ScanResult};
+use std::{fmt::Write as _, fs, io, path::Path, time::Duration};
+
+/// Statistics of one scan, in the Prometheus text exposition format.
+///
+/// Every value describes the latest scan, so they are exposed as gauges; in `--watch` mode
+/// each cycle replaces the previous cycle's values.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Metrics {
+    pub ports_scanned: usize,
+    pub open_ports: usize,
+    pub duration: Duration,
+}
+
+impl Metrics {
+    /// Computes the metrics of a scan that produced `results` in `duration`.
+    pub fn from_results(results: &[ScanResult], duration: Duration) -> Self {
+        Self {
+            ports_scanned: results.len(),
+            open_ports: results
+                .iter()
+                .filter(|r| r.state == PortState::Open)
+                .count(),
+            duration,
+        }
+    }
+
+    /// Renders the metrics with their `HELP` and `TYPE` lines.
+    pub fn render(&self) -> String {
+        let mut text = String::new();
+        let mut gauge = |name: &str, help: &str, value: &dyn std::fmt::Display| {
+            let _ = writeln!(text, "# HELP {} {}", name, help);
+            let _ = writeln!(text, "# TYPE {} gauge", name);
+            let _ = writeln!(text, "{} {}", name, value);
+        };
+        gauge(
+            "ports_scanned_total",
+            "Ports probed by the latest scan.",
+            &self.ports_scanned,
+        );
+        gauge(
+            "open_ports_total",
+            "Open ports found by the latest scan.",
+            &self.open_ports,
+        );
+        gauge(
+            "scan_duration_seconds",
+            "Wall-clock duration of the latest scan.",
+            &self.duration.as_secs_f64(),
+        );
+        text
+    }
+}
+
+/// Writes `metrics` to `path`, replacing the previous contents atomically.
+///
+/// The file is written next to `path` and renamed over it, so a collector reading the
+/// textfile never sees a half-written exposition.
+pub fn write_metrics_file(path: &Path, metrics: &Metrics) -> io::Result<()> {
+    let mut staging = path.as_os_str().to_owned();
+    staging.push(".tmp");
+    fs::write(&staging, metrics.render())?;
+    fs::rename(&staging, path)
+}
+
+// Unit tests >------------------------------------------------------------<
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metrics_exposition_format() {
+        let results = vec![
+            ScanResult::new("10.0.0.1".parse().unwrap(), 22, PortState::Open),
+            ScanResult::new("10.0.0.1".parse().unwrap(), 23, PortState::Closed),
+            ScanResult::new("10.0.0.2".parse().unwrap(), 80, PortState::Open),
+            ScanResult::new("10.0.0.2".parse().unwrap(), 81, PortState::Filtered),
+        ];
+        let metrics = Metrics::from_results(&results, Duration::from_millis(1500));
+
+        assert_eq!(
+            metrics.render(),
+            "# HELP ports_scanned_total Ports probed by the latest scan.\n\
+             # TYPE ports_scanned_total gauge\n\
+             ports_scanned_total 4\n\
+             # HELP open_ports_total Open ports found by the latest scan.\n\
+             # TYPE open_ports_total gauge\n\
+             open_ports_total 2\n\
+             # HELP scan_duration_seconds Wall-clock duration of the latest scan.\n\
+             # TYPE scan_duration_seconds gauge\n\
+             scan_duration_seconds 1.5\n"
+        );
+    }
+
+    #[test]
+    fn test_write_metrics_file_replaces_contents() {
+        let path =
+            std::env::temp_dir().join(format!("port_hawk_metrics_{}.prom", std::process::id()));
+        let first = Metrics::from_results(&[], Duration::from_secs(1));
+        write_metrics_file(&path, &first).unwrap();
+        let second = Metrics {
+            open_ports: 3,
+            ..first
+        };
+        write_metrics_file(&path, &second).unwrap();
+
+        let contents = fs::read_to_string(&path).unwrap();
+        assert!(contents.contains("\nopen_ports_total 3\n"));
+        assert!(!path.with_extension("prom.tmp").exists());
+        fs::remove_file(path).unwrap();
+    }
+}
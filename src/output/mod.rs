@@ -0,0 +1,482 @@
+pub mod checksum;
+pub mod json;
+pub mod metrics;
+pub mod per_host;
+pub mod sarif;
+pub mod severity;
+pub mod stream;
+pub mod summary;
+pub mod targets;
+pub mod template;
+pub mod text;
+pub mod theme;
+pub mod tree;
+pub mod writer;
+
+use crate::{
+    args::target::SkippedTarget,
+    scan::{
+        aggregate::group_by_host,
+        coverage::CoverageGap,
+        diff::DriftMatrix,
+        result::{PortState, ScanResult},
+    },
+};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use severity::SortOrder;
+use std::{
+    borrow::Cow,
+    collections::HashSet,
+    io::{self, Write},
+    net::IpAddr,
+    slice, thread,
+};
+use summary::Summary;
+use template::Template;
+use theme::ColorTheme;
+
+/// Fewest results worth splitting across formatting threads; below this, spawning them costs
+/// more than it saves.
+pub const PARALLEL_FORMAT_MIN: usize = 1024;
+
+/// Represents the format used to write scan results.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Human-readable lines for open ports
+    Text,
+    /// A single JSON document
+    Json,
+    /// One JSON object per line
+    Jsonl,
+    /// A SARIF 2.1.0 log with one result per open port
+    Sarif,
+    /// Bare address:port lines for open ports, for piping into another tool
+    Targets,
+}
+
+/// Options controlling how results are rendered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputOptions {
+    pub format: OutputFormat,
+    pub pretty: bool,
+    /// Line template used by the text format instead of the default line.
+    pub template: Option<Template>,
+    /// Write results sorted once the scan ends instead of as they arrive.
+    pub sorted: bool,
+    /// Order of sorted results.
+    pub sort: SortOrder,
+    /// Write only the report's summary, without per-port results.
+    pub summary_only: bool,
+    /// Write only open, closed and filtered port counts, per host and in total.
+    pub count_only: bool,
+    /// Leave hosts with no open or closed port out of human-readable output; structured
+    /// formats keep every host.
+    pub only_responsive: bool,
+    /// Write a text line for every probed port, with how its state was decided.
+    pub explain: bool,
+    /// Start text lines with the time the probe completed.
+    pub show_time: bool,
+    /// Write one text line per group of a host's ports with the same banner and service.
+    pub group_services: bool,
+    /// Write text as one header line per host with its open ports indented beneath.
+    pub tree: bool,
+    /// Colors of the text summary.
+    pub theme: ColorTheme,
+    /// Threads the lines of sorted text, JSONL and targets output are formatted on.
+    pub threads: usize,
+}
+
+/// Everything a scan produced: the per-port results and the targets that could not be scanned.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Report {
+    /// Watch cycle number, when the report is one cycle of `--watch`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cycle: Option<u64>,
+    /// RFC 3339 UTC time the cycle started, when the report is one cycle of `--watch`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<String>,
+    pub results: Vec<ScanResult>,
+    #[serde(default)]
+    pub errors: Vec<SkippedTarget>,
+    /// Aggregate counts and latency statistics, when a summary was requested.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<Summary>,
+    /// Planned ports that were never probed, per host; empty when the scan completed.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub not_scanned: Vec<CoverageGap>,
+    /// Every port ever found open across the `--baselines` and this scan, when any were given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub drift: Option<DriftMatrix>,
+}
+
+impl Default for OutputOptions {
+    /// Returns the options of a plain scan: sorted text lines for open ports only.
+    fn default() -> Self {
+        Self {
+            format: OutputFormat::Text,
+            pretty: false,
+            template: None,
+            sorted: true,
+            sort: SortOrder::Address,
+            summary_only: false,
+            count_only: false,
+            only_responsive: false,
+            explain: false,
+            show_time: false,
+            group_services: false,
+            tree: false,
+            theme: ColorTheme::None,
+            threads: 1,
+        }
+    }
+}
+
+/// Writes `report` to `writer` in the format selected by `options`.
+pub fn write_results<W: Write>(
+    writer: &mut W,
+    report: &Report,
+    options: &OutputOptions,
+) -> io::Result<()> {
+    let human = options.count_only || options.format == OutputFormat::Text;
+    let report = if options.only_responsive && human {
+        Cow::Owned(responsive_only(report))
+    } else {
+        Cow::Borrowed(report)
+    };
+    let report = report.as_ref();
+    if options.count_only {
+        return write_counts(writer, report);
+    }
+    if options.summary_only {
+        return write_summary_only(writer, report, options);
+    }
+    let report = match options.sort {
+        SortOrder::Address => Cow::Borrowed(report),
+        SortOrder::Severity => {
+            let mut report = report.clone();
+            severity::sort_by_severity(&mut report.results);
+            Cow::Owned(report)
+        }
+    };
+    let report = report.as_ref();
+    match options.format {
+        OutputFormat::Text if options.tree => tree::write(writer, report, options.theme),
+        OutputFormat::Text if options.group_services => {
+            text::write_grouped(writer, report, options.theme)
+        }
+        OutputFormat::Text => text::write(writer, report, options),
+        OutputFormat::Json => json::write_json(writer, report, options.pretty),
+        OutputFormat::Jsonl => write_each(writer, &report.results, options.threads, |mut w, r| {
+            json::write_jsonl(&mut w, slice::from_ref(r))
+        }),
+        OutputFormat::Sarif => sarif::write_sarif(writer, report, options.pretty),
+        OutputFormat::Targets => {
+            write_each(writer, &report.results, options.threads, |mut w, r| {
+                targets::write_targets(&mut w, slice::from_ref(r))
+            })
+        }
+    }?;
+    // Structured formats carry the matrix in the report itself.
+    match (options.format, &report.drift) {
+        (OutputFormat::Text, Some(drift)) => write!(writer, "{}", drift),
+        _ => Ok(()),
+    }
+}
+
+/// Writes whatever `format` writes for each result, in order.
+///
+/// With more than one thread and at least `PARALLEL_FORMAT_MIN` results, the results are split
+/// into one contiguous chunk per thread, each formatted into its own buffer, and the buffers
+/// are written in order; the output is the same either way.
+pub fn write_each<W, F>(
+    writer: &mut W,
+    results: &[ScanResult],
+    threads: usize,
+    format: F,
+) -> io::Result<()>
+where
+    W: Write,
+    F: Fn(&mut dyn Write, &ScanResult) -> io::Result<()> + Sync,
+{
+    if threads <= 1 || results.len() < PARALLEL_FORMAT_MIN {
+        return results.iter().try_for_each(|result| format(writer, result));
+    }
+
+    let chunk = results.len().div_ceil(threads);
+    let buffers: Vec<io::Result<Vec<u8>>> = thread::scope(|scope| {
+        let workers: Vec<_> = results
+            .chunks(chunk)
+            .map(|results| {
+                let format = &format;
+                scope.spawn(move || {
+                    let mut buf = Vec::new();
+                    for result in results {
+                        format(&mut buf, result)?;
+                    }
+                    Ok(buf)
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .map(|worker| worker.join().expect("formatting thread panicked"))
+            .collect()
+    });
+    for buf in buffers {
+        writer.write_all(&buf?)?;
+    }
+    Ok(())
+}
+
+/// Returns `report` without the results of hosts that had no open or closed port, i.e. hosts
+/// that were entirely filtered or down. The summary, if any, still counts every host.
+pub fn responsive_only(report: &Report) -> Report {
+    let responsive: HashSet<IpAddr> = report
+        .results
+        .iter()
+        .filter(|r| matches!(r.state, PortState::Open | PortState::Closed))
+        .map(|r| r.address)
+        .collect();
+    Report {
+        results: report
+            .results
+            .iter()
+            .filter(|r| responsive.contains(&r.address))
+            .cloned()
+            .collect(),
+        ..report.clone()
+    }
+}
+
+/// Writes just the summary: `# ` lines for text, a single JSON object for every other format.
+fn write_summary_only<W: Write>(
+    writer: &mut W,
+    report: &Report,
+    options: &OutputOptions,
+) -> io::Result<()> {
+    let Some(summary) = &report.summary else {
+        return Ok(());
+    };
+    match options.format {
+        OutputFormat::Text => summary::write(writer, summary, options.theme),
+        _ => {
+            if options.pretty {
+                serde_json::to_writer_pretty(&mut *writer, summary)?;
+            } else {
+                serde_json::to_writer(&mut *writer, summary)?;
+            }
+            writeln!(writer)
+        }
+    }
+}
+
+/// Writes `<address> <open> <closed> <filtered>` for each host, sorted by address, then the
+/// same counts over every host on a `total` line, whatever the format.
+fn write_counts<W: Write>(writer: &mut W, report: &Report) -> io::Result<()> {
+    let counts =
+        |summary: Summary| format!("{} {} {}", summary.open, summary.closed, summary.filtered);
+    for host in group_by_host(report.results.clone()) {
+        writeln!(
+            writer,
+            "{} {}",
+            host.address,
+            counts(Summary::from_results(&host.ports))
+        )?;
+    }
+    writeln!(
+        writer,
+        "total {}",
+        counts(Summary::from_results(&report.results))
+    )
+}
+
+// Unit tests >------------------------------------------------------------<
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_results_summary_only() {
+        let results = vec![
+            ScanResult::new("10.0.0.1".parse().unwrap(), 22, PortState::Open),
+            ScanResult::new("10.0.0.1".parse().unwrap(), 80, PortState::Open),
+            ScanResult::new("10.0.0.2".parse().unwrap(), 22, PortState::Filtered),
+        ];
+        let report = Report {
+            summary: Some(Summary::from_results(&results)),
+            results,
+            ..Report::default()
+        };
+        let options = OutputOptions {
+            summary_only: true,
+            ..OutputOptions::default()
+        };
+
+        let mut buf = Vec::new();
+        write_results(&mut buf, &report, &options).unwrap();
+        let text = String::from_utf8(buf).unwrap();
+        assert_eq!(
+            text,
+            "# 2 hosts (1 up), 2 open, 0 closed, 1 filtered, 0 errors\n\
+             # top services: http (1), ssh (1)\n"
+        );
+
+        let mut buf = Vec::new();
+        let options = OutputOptions {
+            format: OutputFormat::Jsonl,
+            ..options
+        };
+        write_results(&mut buf, &report, &options).unwrap();
+        let summary: Summary = serde_json::from_slice(&buf).unwrap();
+        assert_eq!(Some(summary), report.summary);
+    }
+
+    #[test]
+    fn test_write_results_same_on_any_number_of_threads() {
+        let results: Vec<ScanResult> = (1..=PARALLEL_FORMAT_MIN as u16 * 3)
+            .map(|port| {
+                let state = if port % 3 == 0 {
+                    PortState::Closed
+                } else {
+                    PortState::Open
+                };
+                ScanResult::new("10.0.0.1".parse().unwrap(), port, state)
+            })
+            .collect();
+        let report = Report {
+            results,
+            ..Report::default()
+        };
+
+        for format in [
+            OutputFormat::Text,
+            OutputFormat::Jsonl,
+            OutputFormat::Targets,
+        ] {
+            let written = |threads| {
+                let options = OutputOptions {
+                    format,
+                    threads,
+                    ..OutputOptions::default()
+                };
+                let mut buf = Vec::new();
+                write_results(&mut buf, &report, &options).unwrap();
+                String::from_utf8(buf).unwrap()
+            };
+            let sequential = written(1);
+            // Text and targets lines are written for open ports only; JSONL has every port.
+            assert!(sequential.lines().count() >= PARALLEL_FORMAT_MIN * 2);
+            assert_eq!(written(4), sequential, "{:?}", format);
+            assert_eq!(written(7), sequential, "{:?}", format);
+        }
+    }
+
+    #[test]
+    fn test_write_results_count_only() {
+        let report = Report {
+            results: vec![
+                ScanResult::new("10.0.0.2".parse().unwrap(), 22, PortState::Filtered),
+                ScanResult::new("10.0.0.1".parse().unwrap(), 22, PortState::Open),
+                ScanResult::new("10.0.0.1".parse().unwrap(), 80, PortState::Open),
+                ScanResult::new("10.0.0.1".parse().unwrap(), 81, PortState::Closed),
+            ],
+            ..Report::default()
+        };
+        let options = OutputOptions {
+            format: OutputFormat::Json,
+            pretty: true,
+            count_only: true,
+            ..OutputOptions::default()
+        };
+
+        let mut buf = Vec::new();
+        write_results(&mut buf, &report, &options).unwrap();
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "10.0.0.1 2 1 0\n10.0.0.2 0 0 1\ntotal 2 1 1\n"
+        );
+
+        let mut buf = Vec::new();
+        let options = OutputOptions {
+            only_responsive: true,
+            ..options
+        };
+        write_results(&mut buf, &report, &options).unwrap();
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "10.0.0.1 2 1 0\ntotal 2 1 0\n"
+        );
+    }
+
+    #[test]
+    fn test_write_results_only_responsive() {
+        let report = Report {
+            results: vec![
+                ScanResult::new("10.0.0.1".parse().unwrap(), 22, PortState::Open),
+                ScanResult::new("10.0.0.2".parse().unwrap(), 22, PortState::Filtered),
+                ScanResult::new("10.0.0.2".parse().unwrap(), 80, PortState::OpenFiltered),
+                ScanResult::new("10.0.0.3".parse().unwrap(), 22, PortState::Closed),
+                ScanResult::new("10.0.0.3".parse().unwrap(), 80, PortState::OpenFiltered),
+            ],
+            ..Report::default()
+        };
+        let options = OutputOptions {
+            only_responsive: true,
+            ..OutputOptions::default()
+        };
+
+        let mut buf = Vec::new();
+        write_results(&mut buf, &report, &options).unwrap();
+        let text = String::from_utf8(buf).unwrap();
+        assert!(text.contains("10.0.0.1:22 open"));
+        assert!(text.contains("10.0.0.3:80 open|filtered"));
+        assert!(!text.contains("10.0.0.2"));
+
+        let mut buf = Vec::new();
+        let options = OutputOptions {
+            format: OutputFormat::Json,
+            ..options
+        };
+        write_results(&mut buf, &report, &options).unwrap();
+        let parsed: Report = serde_json::from_slice(&buf).unwrap();
+        assert_eq!(parsed.results, report.results);
+    }
+
+    #[test]
+    fn test_write_results_explain() {
+        let explained = |port, state, detail: &str| ScanResult {
+            detail: Some(detail.to_string()),
+            ..ScanResult::new("10.0.0.1".parse().unwrap(), port, state)
+        };
+        let report = Report {
+            results: vec![
+                explained(22, PortState::Closed, "connection refused after 3ms"),
+                explained(80, PortState::Filtered, "timed out after 1000ms"),
+            ],
+            ..Report::default()
+        };
+        let options = OutputOptions {
+            explain: true,
+            ..OutputOptions::default()
+        };
+
+        let mut buf = Vec::new();
+        write_results(&mut buf, &report, &options).unwrap();
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "10.0.0.1:22 closed -- connection refused after 3ms\n\
+             10.0.0.1:80 filtered -- timed out after 1000ms\n"
+        );
+
+        let mut buf = Vec::new();
+        let options = OutputOptions {
+            explain: false,
+            show_time: false,
+            group_services: false,
+            ..options
+        };
+        write_results(&mut buf, &report, &options).unwrap();
+        assert!(buf.is_empty());
+    }
+}
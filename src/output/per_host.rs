@@ -0,0 +1,129 @@
+use crate::{
+    output::{
+        summary::Summary, write_results, writer::OutputWriter, OutputFormat, OutputOptions, Report,
+    },
+    scan::aggregate::group_by_host,
+};
+use std::{
+    fs, io,
+    net::IpAddr,
+    path::{Path, PathBuf},
+};
+
+/// Writes one report per host of `report` into `dir`, creating it if missing.
+///
+/// Each file is named `<address>.<ext>` after the host and `options.format`, holds only that
+/// host's results and unscanned ports, and gets a summary of its own when `report` has one.
+/// With `compress`, files are gzip-compressed and end in `.gz`. Returns the paths written,
+/// sorted by address.
+pub fn write_per_host(
+    dir: &Path,
+    report: &Report,
+    options: &OutputOptions,
+    compress: bool,
+) -> io::Result<Vec<PathBuf>> {
+    fs::create_dir_all(dir)?;
+
+    let mut written = Vec::new();
+    for host in group_by_host(report.results.clone()) {
+        let host_report = Report {
+            cycle: report.cycle,
+            started_at: report.started_at.clone(),
+            summary: report
+                .summary
+                .as_ref()
+                .map(|_| Summary::from_results(&host.ports)),
+            not_scanned: report
+                .not_scanned
+                .iter()
+                .filter(|gap| gap.address == host.address)
+                .cloned()
+                .collect(),
+            drift: report
+                .drift
+                .as_ref()
+                .map(|drift| drift.for_host(host.address)),
+            results: host.ports,
+            errors: Vec::new(),
+        };
+
+        let mut name = format!("{}.{}", file_stem(host.address), extension(options.format));
+        if compress {
+            name.push_str(".gz");
+        }
+        let path = dir.join(name);
+        let mut writer = OutputWriter::open(Some(&path), compress)?;
+        write_results(&mut writer, &host_report, options)?;
+        writer.finish()?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+/// Returns a file name for `address` that is valid on every platform: IPv6 colons become
+/// underscores, so `fd00::1` is written as `fd00__1`.
+pub fn file_stem(address: IpAddr) -> String {
+    address.to_string().replace(':', "_")
+}
+
+fn extension(format: OutputFormat) -> &'static str {
+    match format {
+        OutputFormat::Text => "txt",
+        OutputFormat::Json => "json",
+        OutputFormat::Jsonl => "jsonl",
+        OutputFormat::Sarif => "sarif",
+        OutputFormat::Targets => "txt",
+    }
+}
+
+// Unit tests >------------------------------------------------------------<
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scan::result::{PortState, ScanResult};
+
+    #[test]
+    fn test_write_per_host_one_file_per_host() {
+        let dir = std::env::temp_dir()
+            .join(format!("port_hawk_hosts_{}", std::process::id()))
+            .join("nested");
+        let report = Report {
+            results: vec![
+                ScanResult::new("10.0.0.1".parse().unwrap(), 22, PortState::Open),
+                ScanResult::new("fd00::1".parse().unwrap(), 443, PortState::Open),
+                ScanResult::new("10.0.0.1".parse().unwrap(), 80, PortState::Closed),
+            ],
+            ..Report::default()
+        };
+        let options = OutputOptions {
+            format: OutputFormat::Json,
+            ..OutputOptions::default()
+        };
+
+        let written = write_per_host(&dir, &report, &options, false).unwrap();
+        let read = |name: &str| -> Report {
+            serde_json::from_slice(&fs::read(dir.join(name)).unwrap()).unwrap()
+        };
+        assert_eq!(
+            written,
+            vec![dir.join("10.0.0.1.json"), dir.join("fd00__1.json")]
+        );
+        let ports: Vec<u16> = read("10.0.0.1.json")
+            .results
+            .iter()
+            .map(|r| r.port)
+            .collect();
+        assert_eq!(ports, vec![22, 80]);
+        assert_eq!(read("fd00__1.json").results.len(), 1);
+        fs::remove_dir_all(dir.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_file_stem() {
+        assert_eq!(file_stem("192.168.1.10".parse().unwrap()), "192.168.1.10");
+        assert_eq!(
+            file_stem("2001:db8::ff00:42".parse().unwrap()),
+            "2001_db8__ff00_42"
+        );
+    }
+}
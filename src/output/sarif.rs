@@ -0,0 +1,178 @@
+use crate::{
+    output::Report,
+    scan::{result::PortState, services::service_name},
+};
+use serde::Serialize;
+use std::{
+    io::{self, Write},
+    net::SocketAddr,
+};
+
+const SARIF_VERSION: &str = "2.1.0";
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// The rule every open-port finding is reported under.
+const OPEN_PORT_RULE: &str = "open-port";
+
+#[derive(Debug, Serialize)]
+struct SarifLog {
+    #[serde(rename = "$schema")]
+    schema: &'static str,
+    version: &'static str,
+    runs: Vec<Run>,
+}
+
+#[derive(Debug, Serialize)]
+struct Run {
+    tool: Tool,
+    results: Vec<SarifResult>,
+}
+
+#[derive(Debug, Serialize)]
+struct Tool {
+    driver: Driver,
+}
+
+#[derive(Debug, Serialize)]
+struct Driver {
+    name: &'static str,
+    version: &'static str,
+    rules: Vec<Rule>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Rule {
+    id: &'static str,
+    short_description: Message,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifResult {
+    rule_id: &'static str,
+    level: &'static str,
+    message: Message,
+    locations: Vec<Location>,
+}
+
+#[derive(Debug, Serialize)]
+struct Message {
+    text: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Location {
+    physical_location: PhysicalLocation,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PhysicalLocation {
+    artifact_location: ArtifactLocation,
+}
+
+#[derive(Debug, Serialize)]
+struct ArtifactLocation {
+    uri: String,
+}
+
+/// Writes the open ports of `report` as a minimal SARIF 2.1.0 log with a single run.
+///
+/// Each open port becomes a `note`-level `open-port` result whose location URI is
+/// `tcp://address:port`, so code-scanning dashboards can list and de-duplicate findings.
+pub fn write_sarif<W: Write>(writer: &mut W, report: &Report, pretty: bool) -> io::Result<()> {
+    let results = report
+        .results
+        .iter()
+        .filter(|result| result.state == PortState::Open)
+        .map(|result| {
+            let addr = SocketAddr::new(result.address, result.port);
+            let text = match service_name(result.port) {
+                Some(service) => format!("Open port {} ({})", addr, service),
+                None => format!("Open port {}", addr),
+            };
+            SarifResult {
+                rule_id: OPEN_PORT_RULE,
+                level: "note",
+                message: Message { text },
+                locations: vec![Location {
+                    physical_location: PhysicalLocation {
+                        artifact_location: ArtifactLocation {
+                            uri: format!("tcp://{}", addr),
+                        },
+                    },
+                }],
+            }
+        })
+        .collect();
+
+    let log = SarifLog {
+        schema: SARIF_SCHEMA,
+        version: SARIF_VERSION,
+        runs: vec![Run {
+            tool: Tool {
+                driver: Driver {
+                    name: env!("CARGO_PKG_NAME"),
+                    version: env!("CARGO_PKG_VERSION"),
+                    rules: vec![Rule {
+                        id: OPEN_PORT_RULE,
+                        short_description: Message {
+                            text: "A TCP port accepted connections".to_string(),
+                        },
+                    }],
+                },
+            },
+            results,
+        }],
+    };
+
+    if pretty {
+        serde_json::to_writer_pretty(&mut *writer, &log)?;
+    } else {
+        serde_json::to_writer(&mut *writer, &log)?;
+    }
+    writeln!(writer)
+}
+
+// Unit tests >------------------------------------------------------------<
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scan::result::ScanResult;
+    use serde_json::Value;
+
+    #[test]
+    fn test_write_sarif_structure() {
+        let report = Report {
+            results: vec![
+                ScanResult::new("10.0.0.1".parse().unwrap(), 22, PortState::Open),
+                ScanResult::new("10.0.0.1".parse().unwrap(), 23, PortState::Closed),
+                ScanResult::new("fd00::1".parse().unwrap(), 8443, PortState::Open),
+            ],
+            ..Report::default()
+        };
+
+        let mut buf = Vec::new();
+        write_sarif(&mut buf, &report, false).unwrap();
+        let log: Value = serde_json::from_slice(&buf).unwrap();
+
+        assert_eq!(log["version"], "2.1.0");
+        assert_eq!(log["runs"].as_array().unwrap().len(), 1);
+        let run = &log["runs"][0];
+        assert_eq!(run["tool"]["driver"]["name"], "port_hawk");
+        assert_eq!(run["tool"]["driver"]["rules"][0]["id"], "open-port");
+
+        let results = run["results"].as_array().unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0]["ruleId"], "open-port");
+        assert_eq!(results[0]["level"], "note");
+        assert_eq!(results[0]["message"]["text"], "Open port 10.0.0.1:22 (ssh)");
+        let uri = |result: &Value| {
+            result["locations"][0]["physicalLocation"]["artifactLocation"]["uri"].clone()
+        };
+        assert_eq!(uri(&results[0]), "tcp://10.0.0.1:22");
+        assert_eq!(uri(&results[1]), "tcp://[fd00::1]:8443");
+    }
+}
@@ -0,0 +1,155 @@
+use crate::scan::result::ScanResult;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::{cmp::Reverse, fmt};
+
+/// Represents how risky it is to expose a service, from `Info` (no known concern) up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self {
+            Severity::Info => "info",
+            Severity::Low => "low",
+            Severity::Medium => "medium",
+            Severity::High => "high",
+            Severity::Critical => "critical",
+        };
+        write!(f, "{}", severity)
+    }
+}
+
+/// Represents the order sorted results are written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SortOrder {
+    /// By address, then port
+    Address,
+    /// Most severe open services first, then by address and port
+    Severity,
+}
+
+/// Services that are risky to expose, sorted by port, each with its name as service detection
+/// and the well-known port table spell it.
+///
+/// Cleartext remote shells are critical; file sharing, remote desktops and databases that are
+/// routinely exposed by mistake follow.
+const RISKY_SERVICES: &[(u16, &str, Severity)] = &[
+    (21, "ftp", Severity::Medium),      // cleartext credentials
+    (23, "telnet", Severity::Critical), // cleartext remote shell
+    (111, "rpcbind", Severity::Low),
+    (135, "msrpc", Severity::Medium),
+    (139, "netbios-ssn", Severity::Medium),
+    (445, "microsoft-ds", Severity::High), // SMB, including SMBv1
+    (512, "exec", Severity::Critical),     // rexec
+    (513, "login", Severity::Critical),    // rlogin
+    (514, "shell", Severity::Critical),    // rsh
+    (1433, "ms-sql-s", Severity::Medium),  // mssql
+    (2375, "docker", Severity::Critical),  // unauthenticated API
+    (3306, "mysql", Severity::Medium),
+    (3389, "ms-wbt-server", Severity::High), // rdp
+    (5432, "postgresql", Severity::Medium),
+    (5900, "vnc", Severity::High),
+    (6379, "redis", Severity::High), // no authentication by default
+    (9200, "elasticsearch", Severity::High),
+    (11211, "memcache", Severity::High), // memcached
+    (27017, "mongodb", Severity::High),
+];
+
+/// Returns the severity of exposing `port`; ports not in the table are `Info`.
+pub fn severity(port: u16) -> Severity {
+    RISKY_SERVICES
+        .binary_search_by_key(&port, |(risky, _, _)| *risky)
+        .map(|index| RISKY_SERVICES[index].2)
+        .unwrap_or(Severity::Info)
+}
+
+/// Returns the severity of exposing `result`'s service: the detected service's, if it is a
+/// risky one, so telnet on port 2323 is as critical as on 23, and otherwise its port's.
+pub fn service_severity(result: &ScanResult) -> Severity {
+    result
+        .service
+        .as_deref()
+        .and_then(|service| RISKY_SERVICES.iter().find(|(_, name, _)| *name == service))
+        .map_or_else(|| severity(result.port), |(_, _, severity)| *severity)
+}
+
+/// Sorts results by descending severity, keeping the existing order among equals.
+///
+/// Only open ports count as exposed; every other result sorts as `Info`.
+pub fn sort_by_severity(results: &mut [ScanResult]) {
+    results.sort_by_key(|result| Reverse(result.severity.unwrap_or(Severity::Info)));
+}
+
+// Unit tests >------------------------------------------------------------<
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scan::result::PortState;
+
+    #[test]
+    fn test_severity_table() {
+        assert!(RISKY_SERVICES.windows(2).all(|w| w[0].0 < w[1].0));
+        assert_eq!(severity(23), Severity::Critical);
+        assert_eq!(severity(513), Severity::Critical);
+        assert_eq!(severity(445), Severity::High);
+        assert_eq!(severity(3389), Severity::High);
+        assert_eq!(severity(21), Severity::Medium);
+        assert_eq!(severity(443), Severity::Info);
+        assert_eq!(severity(31337), Severity::Info);
+    }
+
+    #[test]
+    fn test_service_severity_prefers_detected_service() {
+        let open = |port, service: Option<&str>| ScanResult {
+            service: service.map(str::to_string),
+            ..ScanResult::new("10.0.0.1".parse().unwrap(), port, PortState::Open)
+        };
+        assert_eq!(
+            service_severity(&open(2323, Some("telnet"))),
+            Severity::Critical
+        );
+        assert_eq!(service_severity(&open(8080, Some("redis"))), Severity::High);
+        // Services without a known risk fall back to the port.
+        assert_eq!(service_severity(&open(23, Some("tls"))), Severity::Critical);
+        assert_eq!(service_severity(&open(2323, Some("http"))), Severity::Info);
+        assert_eq!(service_severity(&open(445, None)), Severity::High);
+    }
+
+    #[test]
+    fn test_sort_by_severity() {
+        let result = |port, state| ScanResult {
+            severity: (state == PortState::Open).then(|| severity(port)),
+            ..ScanResult::new("10.0.0.1".parse().unwrap(), port, state)
+        };
+        let mut results = vec![
+            result(22, PortState::Open),
+            result(23, PortState::Closed),
+            result(80, PortState::Open),
+            result(445, PortState::Open),
+            result(3306, PortState::Open),
+            result(23, PortState::Open),
+        ];
+        sort_by_severity(&mut results);
+
+        let ports: Vec<(u16, PortState)> = results.iter().map(|r| (r.port, r.state)).collect();
+        assert_eq!(
+            ports,
+            vec![
+                (23, PortState::Open),
+                (445, PortState::Open),
+                (3306, PortState::Open),
+                (22, PortState::Open),
+                (23, PortState::Closed),
+                (80, PortState::Open),
+            ]
+        );
+    }
+}
@@ -0,0 +1,158 @@
+use crate::{
+    output::{json, summary, targets, text, write_results, OutputFormat, OutputOptions, Report},
+    scan::{aggregate, result::ScanResult},
+};
+use std::{
+    io::{self, Write},
+    sync::mpsc::Receiver,
+    thread::{self, JoinHandle},
+};
+
+/// Writes results on a dedicated thread as they arrive over `results`, so formatting and I/O
+/// never hold up the scan workers.
+///
+/// With `options.sorted` (or a format that needs the whole scan, like JSON), results are
+/// buffered until the channel closes, then deduplicated, sorted and written at once. Otherwise
+/// text, JSONL and targets lines are written in completion order as they arrive, and
+/// overlapping targets may repeat a port. Either way, `finish` builds the final report from the
+/// deduplicated results once the channel closes; its summary is written after streamed text
+/// lines. The thread returns the writer and that report.
+pub fn spawn_writer<W, F>(
+    mut writer: W,
+    results: Receiver<ScanResult>,
+    options: OutputOptions,
+    finish: F,
+) -> JoinHandle<io::Result<(W, Report)>>
+where
+    W: Write + Send + 'static,
+    F: FnOnce(Vec<ScanResult>) -> Report + Send + 'static,
+{
+    thread::spawn(move || {
+        // Hiding unresponsive hosts from text, or grouping their ports, needs every result of a
+        // host first.
+        let streamable = match options.format {
+            OutputFormat::Text => {
+                !options.only_responsive && !options.group_services && !options.tree
+            }
+            OutputFormat::Jsonl | OutputFormat::Targets => true,
+            _ => false,
+        };
+        let streamed =
+            !options.sorted && !options.summary_only && !options.count_only && streamable;
+
+        let mut received = Vec::new();
+        for result in results {
+            if streamed {
+                match options.format {
+                    OutputFormat::Jsonl => json::write_jsonl(&mut writer, &[result.clone()])?,
+                    OutputFormat::Targets => {
+                        targets::write_targets(&mut writer, &[result.clone()])?
+                    }
+                    _ => text::write_result(
+                        &mut writer,
+                        &result,
+                        options.template.as_ref(),
+                        options.explain,
+                        options.show_time,
+                    )?,
+                }
+            }
+            received.push(result);
+        }
+
+        let report = finish(aggregate::dedup(received));
+        if !streamed {
+            write_results(&mut writer, &report, &options)?;
+        } else if options.format == OutputFormat::Text {
+            if let Some(summary) = &report.summary {
+                summary::write(&mut writer, summary, options.theme)?;
+            }
+            if let Some(drift) = &report.drift {
+                write!(writer, "{}", drift)?;
+            }
+        }
+        Ok((writer, report))
+    })
+}
+
+// Unit tests >------------------------------------------------------------<
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scan::result::PortState;
+    use std::sync::mpsc;
+
+    fn options(format: OutputFormat, sorted: bool) -> OutputOptions {
+        OutputOptions {
+            format,
+            sorted,
+            ..OutputOptions::default()
+        }
+    }
+
+    fn open(port: u16) -> ScanResult {
+        ScanResult::new("10.0.0.1".parse().unwrap(), port, PortState::Open)
+    }
+
+    #[test]
+    fn test_writer_thread_drains_all_results() {
+        let (tx, rx) = mpsc::channel();
+        let writer = spawn_writer(
+            Vec::new(),
+            rx,
+            options(OutputFormat::Jsonl, false),
+            |results| Report {
+                results,
+                ..Report::default()
+            },
+        );
+
+        for port in [443, 22, 80, 22] {
+            tx.send(open(port)).unwrap();
+        }
+        drop(tx);
+
+        let (buf, report) = writer.join().unwrap().unwrap();
+        let ports: Vec<u16> = String::from_utf8(buf)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str::<ScanResult>(line).unwrap().port)
+            .collect();
+        assert_eq!(ports, vec![443, 22, 80, 22]);
+        assert_eq!(report.results, vec![open(22), open(80), open(443)]);
+    }
+
+    #[test]
+    fn test_writer_thread_sorts_at_finish() {
+        let (tx, rx) = mpsc::channel();
+        let writer = spawn_writer(
+            Vec::new(),
+            rx,
+            options(OutputFormat::Text, true),
+            |results| Report {
+                results,
+                ..Report::default()
+            },
+        );
+
+        for port in [443, 22, 80] {
+            tx.send(open(port)).unwrap();
+        }
+        drop(tx);
+
+        let (buf, _) = writer.join().unwrap().unwrap();
+        let lines: Vec<String> = String::from_utf8(buf)
+            .unwrap()
+            .lines()
+            .map(str::to_string)
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                open(22).to_string(),
+                open(80).to_string(),
+                open(443).to_string()
+            ]
+        );
+    }
+}
@@ -0,0 +1,419 @@
+use crate::{
+    output::theme::{paint, ColorTheme, Palette},
+    scan::{
+        aggregate::{group_by_host, HostState},
+        coverage::CoverageGap,
+        result::{PortState, ScanResult},
+        services::service_name,
+    },
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fmt,
+    io::{self, Write},
+    time::Duration,
+};
+
+/// How many services the summary ranks.
+pub const TOP_SERVICES: usize = 5;
+
+/// Aggregate counts and statistics over a whole scan.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Summary {
+    pub hosts: usize,
+    pub hosts_up: usize,
+    pub open: usize,
+    pub closed: usize,
+    pub filtered: usize,
+    /// Silent ports of NULL, FIN and Xmas scans; only shown when non-zero.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub open_filtered: usize,
+    pub errors: usize,
+    /// Planned ports that were never probed; only shown when non-zero.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub not_scanned: usize,
+    /// Connect latency distribution over open ports, if any port was open.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency: Option<LatencyPercentiles>,
+    /// The well-known services found open most often, most common first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub top_services: Vec<ServiceCount>,
+    /// Wall-clock time the scan took, when the caller measured it.
+    #[serde(
+        default,
+        rename = "elapsed_ms",
+        with = "crate::scan::result::latency_ms",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub elapsed: Option<Duration>,
+}
+
+/// The number of open ports running one well-known service.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServiceCount {
+    pub service: String,
+    pub open: usize,
+}
+
+/// Connect latency percentiles, in fractional milliseconds.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LatencyPercentiles {
+    pub p50_ms: f64,
+    pub p90_ms: f64,
+    pub p99_ms: f64,
+}
+
+impl Summary {
+    /// Computes the summary of a result list, leaving `elapsed` unset.
+    pub fn from_results(results: &[ScanResult]) -> Self {
+        let count = |state| results.iter().filter(|r| r.state == state).count();
+        let hosts = group_by_host(results.to_vec());
+
+        Self {
+            hosts: hosts.len(),
+            hosts_up: hosts
+                .iter()
+                .filter(|host| host.host_state == HostState::Up)
+                .count(),
+            open: count(PortState::Open),
+            closed: count(PortState::Closed),
+            filtered: count(PortState::Filtered),
+            open_filtered: count(PortState::OpenFiltered),
+            errors: count(PortState::Error),
+            not_scanned: 0,
+            latency: latency_percentiles(results),
+            top_services: top_services(results),
+            elapsed: None,
+        }
+    }
+
+    /// Records the planned ports the scan never probed.
+    pub fn with_gaps(self, gaps: &[CoverageGap]) -> Self {
+        Self {
+            not_scanned: gaps.iter().map(CoverageGap::count).sum(),
+            ..self
+        }
+    }
+
+    /// Records how long the scan took.
+    pub fn with_elapsed(self, elapsed: Duration) -> Self {
+        Self {
+            elapsed: Some(elapsed),
+            ..self
+        }
+    }
+
+    /// Renders the summary line, coloring each port state count with `palette` if given.
+    pub fn render(&self, palette: Option<Palette>) -> String {
+        let count = |count: usize, label: &str, code: fn(&Palette) -> &'static str| {
+            let text = format!("{} {}", count, label);
+            match palette {
+                Some(palette) => paint(&text, code(&palette)),
+                None => text,
+            }
+        };
+
+        let mut line = format!(
+            "{} hosts ({} up), {}, {}, {}",
+            self.hosts,
+            self.hosts_up,
+            count(self.open, "open", |p| p.open),
+            count(self.closed, "closed", |p| p.closed),
+            count(self.filtered, "filtered", |p| p.filtered)
+        );
+        if self.open_filtered > 0 {
+            line += &format!(
+                ", {}",
+                count(self.open_filtered, "open|filtered", |p| p.filtered)
+            );
+        }
+        line += &format!(", {}", count(self.errors, "errors", |p| p.errors));
+        if self.not_scanned > 0 {
+            line += &format!(", {} not scanned", self.not_scanned);
+        }
+        if let Some(latency) = self.latency {
+            line += &format!(
+                "; connect latency p50 {:.1}ms, p90 {:.1}ms, p99 {:.1}ms",
+                latency.p50_ms, latency.p90_ms, latency.p99_ms
+            );
+        }
+        if let Some(elapsed) = self.elapsed {
+            line += &format!("; elapsed {}", format_duration(elapsed));
+        }
+        line
+    }
+
+    /// Renders the summary as one `SCAN_COMPLETE key=value ...` line for log collectors.
+    ///
+    /// Unlike [`Summary::render`] the keys and their order never change, and `elapsed` is
+    /// always in seconds with a tenth of a second, or `0.0s` when it was not measured.
+    pub fn log_line(&self) -> String {
+        format!(
+            "SCAN_COMPLETE hosts={} open={} closed={} filtered={} elapsed={:.1}s",
+            self.hosts,
+            self.open,
+            self.closed,
+            self.filtered,
+            self.elapsed.unwrap_or_default().as_secs_f64()
+        )
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.render(None))
+    }
+}
+
+/// Formats a duration for people: `450ms`, `12.3s`, `1m 23s` or `2h 5m 0s`.
+///
+/// Sub-second durations are whole milliseconds and durations under a minute keep a tenth of a
+/// second; longer ones are truncated to whole seconds.
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match secs {
+        0 => format!("{}ms", duration.as_millis()),
+        1..=59 => format!("{:.1}s", duration.as_secs_f64()),
+        60..=3599 => format!("{}m {}s", secs / 60, secs % 60),
+        _ => format!("{}h {}m {}s", secs / 3600, secs / 60 % 60, secs % 60),
+    }
+}
+
+/// Computes latency percentiles over successful connects (open ports) only.
+///
+/// Closed ports are left out: an RST usually comes back faster than a full handshake and would
+/// skew the distribution used to size timeouts.
+pub fn latency_percentiles(results: &[ScanResult]) -> Option<LatencyPercentiles> {
+    let mut samples: Vec<Duration> = results
+        .iter()
+        .filter(|r| r.state == PortState::Open)
+        .filter_map(|r| r.latency)
+        .collect();
+    if samples.is_empty() {
+        return None;
+    }
+    samples.sort_unstable();
+
+    let ms = |p| percentile(&samples, p).as_secs_f64() * 1000.0;
+    Some(LatencyPercentiles {
+        p50_ms: ms(50),
+        p90_ms: ms(90),
+        p99_ms: ms(99),
+    })
+}
+
+/// Ranks the services of open ports by how often they were found, ties broken by name.
+///
+/// Ports without a well-known service name are not counted.
+pub fn top_services(results: &[ScanResult]) -> Vec<ServiceCount> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for service in results
+        .iter()
+        .filter(|r| r.state == PortState::Open)
+        .filter_map(|r| service_name(r.port))
+    {
+        *counts.entry(service).or_default() += 1;
+    }
+
+    let mut ranked: Vec<ServiceCount> = counts
+        .into_iter()
+        .map(|(service, open)| ServiceCount {
+            service: service.to_string(),
+            open,
+        })
+        .collect();
+    ranked.sort_by(|a, b| b.open.cmp(&a.open).then_with(|| a.service.cmp(&b.service)));
+    ranked.truncate(TOP_SERVICES);
+    ranked
+}
+
+/// Returns the nearest-rank `p`th percentile of a sorted, non-empty sample.
+fn percentile(sorted: &[Duration], p: usize) -> Duration {
+    let rank = (p * sorted.len()).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
+
+fn is_zero(count: &usize) -> bool {
+    *count == 0
+}
+
+/// Writes the summary as `# `-prefixed comment lines, so it can follow text results.
+///
+/// The counts come first, colored by `theme`; the top services, if any port had a known
+/// service, follow on a line of their own.
+pub fn write<W: Write>(writer: &mut W, summary: &Summary, theme: ColorTheme) -> io::Result<()> {
+    writeln!(writer, "# {}", summary.render(theme.palette()))?;
+    if summary.top_services.is_empty() {
+        return Ok(());
+    }
+    let services: Vec<String> = summary
+        .top_services
+        .iter()
+        .map(|count| format!("{} ({})", count.service, count.open))
+        .collect();
+    writeln!(writer, "# top services: {}", services.join(", "))
+}
+
+// Unit tests >------------------------------------------------------------<
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary_log_line() {
+        let results = vec![
+            open_in(5),
+            ScanResult::new("10.0.0.1".parse().unwrap(), 81, PortState::Closed),
+            ScanResult::new("10.0.0.2".parse().unwrap(), 80, PortState::Filtered),
+            ScanResult::new("10.0.0.2".parse().unwrap(), 81, PortState::Filtered),
+        ];
+        let summary = Summary::from_results(&results).with_elapsed(Duration::from_millis(12_345));
+        assert_eq!(
+            summary.log_line(),
+            "SCAN_COMPLETE hosts=2 open=1 closed=1 filtered=2 elapsed=12.3s"
+        );
+        assert_eq!(
+            Summary::default().log_line(),
+            "SCAN_COMPLETE hosts=0 open=0 closed=0 filtered=0 elapsed=0.0s"
+        );
+    }
+
+    fn open_in(ms: u64) -> ScanResult {
+        ScanResult {
+            latency: Some(Duration::from_millis(ms)),
+            ..ScanResult::new("10.0.0.1".parse().unwrap(), 80, PortState::Open)
+        }
+    }
+
+    #[test]
+    fn test_latency_percentiles_known_sample() {
+        let results: Vec<ScanResult> = (1..=100).map(open_in).collect();
+        assert_eq!(
+            latency_percentiles(&results),
+            Some(LatencyPercentiles {
+                p50_ms: 50.0,
+                p90_ms: 90.0,
+                p99_ms: 99.0,
+            })
+        );
+
+        let results: Vec<ScanResult> = [7, 3, 5].into_iter().map(open_in).collect();
+        assert_eq!(
+            latency_percentiles(&results),
+            Some(LatencyPercentiles {
+                p50_ms: 5.0,
+                p90_ms: 7.0,
+                p99_ms: 7.0,
+            })
+        );
+    }
+
+    #[test]
+    fn test_latency_percentiles_only_counts_open_ports() {
+        let closed = ScanResult {
+            latency: Some(Duration::from_millis(1)),
+            ..ScanResult::new("10.0.0.1".parse().unwrap(), 81, PortState::Closed)
+        };
+        assert_eq!(latency_percentiles(&[closed.clone()]), None);
+
+        let percentiles = latency_percentiles(&[closed, open_in(20)]).unwrap();
+        assert_eq!(percentiles.p50_ms, 20.0);
+    }
+
+    #[test]
+    fn test_top_services() {
+        let open = |port| ScanResult::new("10.0.0.1".parse().unwrap(), port, PortState::Open);
+        let results = vec![
+            open(443),
+            open(22),
+            open(80),
+            open(22),
+            open(31337),
+            ScanResult::new("10.0.0.2".parse().unwrap(), 80, PortState::Closed),
+        ];
+        let ranked: Vec<(String, usize)> = top_services(&results)
+            .into_iter()
+            .map(|count| (count.service, count.open))
+            .collect();
+        assert_eq!(
+            ranked,
+            vec![
+                ("ssh".to_string(), 2),
+                ("http".to_string(), 1),
+                ("https".to_string(), 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(Duration::from_micros(450_700)), "450ms");
+        assert_eq!(format_duration(Duration::ZERO), "0ms");
+        assert_eq!(format_duration(Duration::from_millis(12_340)), "12.3s");
+        assert_eq!(format_duration(Duration::from_secs(83)), "1m 23s");
+        assert_eq!(format_duration(Duration::from_millis(3_599_999)), "59m 59s");
+        assert_eq!(format_duration(Duration::from_secs(7_500)), "2h 5m 0s");
+        assert_eq!(format_duration(Duration::from_secs(90_061)), "25h 1m 1s");
+    }
+
+    #[test]
+    fn test_summary_from_results() {
+        let results = vec![
+            open_in(4),
+            ScanResult::new("10.0.0.1".parse().unwrap(), 81, PortState::Closed),
+            ScanResult::new("10.0.0.2".parse().unwrap(), 80, PortState::Filtered),
+        ];
+        let summary = Summary::from_results(&results);
+        assert_eq!(
+            summary.to_string(),
+            "2 hosts (1 up), 1 open, 1 closed, 1 filtered, 0 errors; connect latency p50 4.0ms, p90 4.0ms, p99 4.0ms"
+        );
+        let gaps = [CoverageGap {
+            address: "10.0.0.2".parse().unwrap(),
+            ports: vec![(81, 90)],
+        }];
+        assert!(summary
+            .clone()
+            .with_gaps(&gaps)
+            .to_string()
+            .contains(", 0 errors, 10 not scanned;"));
+        assert!(summary
+            .with_elapsed(Duration::from_secs(83))
+            .to_string()
+            .ends_with("; elapsed 1m 23s"));
+    }
+
+    #[test]
+    fn test_write_colors_counts_by_theme() {
+        let summary = Summary {
+            hosts: 1,
+            hosts_up: 1,
+            open: 2,
+            closed: 3,
+            filtered: 4,
+            errors: 1,
+            ..Summary::default()
+        };
+        let written = |theme| {
+            let mut buf = Vec::new();
+            write(&mut buf, &summary, theme).unwrap();
+            String::from_utf8(buf).unwrap()
+        };
+
+        assert_eq!(
+            written(ColorTheme::Dark),
+            "# 1 hosts (1 up), \x1b[92m2 open\x1b[0m, \x1b[91m3 closed\x1b[0m, \
+             \x1b[93m4 filtered\x1b[0m, \x1b[95m1 errors\x1b[0m\n"
+        );
+        assert_eq!(
+            written(ColorTheme::Light),
+            "# 1 hosts (1 up), \x1b[32m2 open\x1b[0m, \x1b[31m3 closed\x1b[0m, \
+             \x1b[33m4 filtered\x1b[0m, \x1b[35m1 errors\x1b[0m\n"
+        );
+        assert_eq!(
+            written(ColorTheme::None),
+            "# 1 hosts (1 up), 2 open, 3 closed, 4 filtered, 1 errors\n"
+        );
+    }
+}
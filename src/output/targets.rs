@@ -0,0 +1,44 @@
+use crate::scan::result::{PortState, ScanResult};
+use std::{
+    io::{self, Write},
+    net::SocketAddr,
+};
+
+/// Writes one `address:port` line per open port and nothing else, for piping into another
+/// tool. IPv6 addresses are bracketed, e.g. `[::1]:22`, so every line parses as a socket
+/// address.
+///
+/// Only ports confirmed open are written; open|filtered ones never answered.
+pub fn write_targets<W: Write>(writer: &mut W, results: &[ScanResult]) -> io::Result<()> {
+    for result in results.iter().filter(|r| r.state == PortState::Open) {
+        writeln!(writer, "{}", SocketAddr::new(result.address, result.port))?;
+    }
+    Ok(())
+}
+
+// Unit tests >------------------------------------------------------------<
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_targets_lists_open_ports_only() {
+        let result =
+            |address: &str, port, state| ScanResult::new(address.parse().unwrap(), port, state);
+        let results = vec![
+            ScanResult {
+                banner: Some("SSH-2.0-OpenSSH_9.6".to_string()),
+                service: Some("ssh".to_string()),
+                ..result("10.0.0.1", 22, PortState::Open)
+            },
+            result("10.0.0.1", 23, PortState::Closed),
+            result("10.0.0.2", 80, PortState::Filtered),
+            result("10.0.0.2", 81, PortState::OpenFiltered),
+            result("::1", 443, PortState::Open),
+        ];
+
+        let mut buf = Vec::new();
+        write_targets(&mut buf, &results).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), "10.0.0.1:22\n[::1]:443\n");
+    }
+}
@@ -0,0 +1,217 @@
+use crate::scan::{result::ScanResult, services::service_name};
+use std::fmt::Write;
+
+/// Represents a field that can be substituted into a line template.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Addr,
+    Hostname,
+    Port,
+    State,
+    Latency,
+    Service,
+    Banner,
+    Source,
+    Detail,
+}
+
+impl Field {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "addr" => Some(Field::Addr),
+            "hostname" => Some(Field::Hostname),
+            "port" => Some(Field::Port),
+            "state" => Some(Field::State),
+            "latency" => Some(Field::Latency),
+            "service" => Some(Field::Service),
+            "banner" => Some(Field::Banner),
+            "source" => Some(Field::Source),
+            "detail" => Some(Field::Detail),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    Field(Field),
+}
+
+/// A parsed `--template` used to render one line per result.
+///
+/// Fields are written as `{name}`; `{{` and `}}` produce literal braces. Fields with no value
+/// for a result (no latency, no banner) render as `-`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    segments: Vec<Segment>,
+}
+
+impl Template {
+    /// Renders the template for a single result.
+    pub fn render(&self, result: &ScanResult) -> String {
+        let mut line = String::new();
+
+        for segment in &self.segments {
+            match segment {
+                Segment::Literal(text) => line.push_str(text),
+                Segment::Field(field) => {
+                    let _ = match field {
+                        Field::Addr => write!(line, "{}", result.address),
+                        Field::Hostname => {
+                            write!(line, "{}", result.hostname.as_deref().unwrap_or("-"))
+                        }
+                        Field::Port => write!(line, "{}", result.port),
+                        Field::State => write!(line, "{}", result.state),
+                        Field::Latency => match result.latency {
+                            Some(latency) => write!(line, "{}", latency.as_millis()),
+                            None => write!(line, "-"),
+                        },
+                        Field::Service => {
+                            let service = result.service.as_deref();
+                            write!(
+                                line,
+                                "{}",
+                                service.or(service_name(result.port)).unwrap_or("-")
+                            )
+                        }
+                        Field::Banner => match result.printable_banner() {
+                            Some(banner) => write!(line, "{}", banner),
+                            None => write!(line, "-"),
+                        },
+                        Field::Source => match result.source_addr {
+                            Some(source) => write!(line, "{}", source),
+                            None => write!(line, "-"),
+                        },
+                        Field::Detail => {
+                            write!(line, "{}", result.detail.as_deref().unwrap_or("-"))
+                        }
+                    };
+                }
+            }
+        }
+
+        line
+    }
+}
+
+/// Parses a line template such as `"{addr}:{port} {state} {latency}ms {service}"`.
+///
+/// # Arguments
+///
+/// * `template` - A string containing literal text and `{field}` placeholders.
+///
+/// # Returns
+///
+/// * `Ok(Template)` - If every placeholder names a known field.
+/// * `Err(String)` - If a placeholder is unknown or a brace is unbalanced.
+pub fn parse_template(template: &str) -> Result<Template, String> {
+    let mut segments = Vec::new();
+    let mut literal = String::new();
+    let mut chars = template.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                literal.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                literal.push('}');
+            }
+            '{' => {
+                let mut name = String::new();
+                let mut closed = false;
+                for c in chars.by_ref() {
+                    if c == '}' {
+                        closed = true;
+                        break;
+                    }
+                    name.push(c);
+                }
+                if !closed {
+                    return Err(format!("Unclosed template field: {{{}", name));
+                }
+                let field = Field::from_name(&name)
+                    .ok_or_else(|| format!("Unknown template field: {{{}}}", name))?;
+
+                if !literal.is_empty() {
+                    segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                }
+                segments.push(Segment::Field(field));
+            }
+            '}' => return Err(format!("Unmatched '}}' in template: {}", template)),
+            c => literal.push(c),
+        }
+    }
+
+    if !literal.is_empty() {
+        segments.push(Segment::Literal(literal));
+    }
+    Ok(Template { segments })
+}
+
+// Unit tests >------------------------------------------------------------<
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scan::result::PortState;
+    use std::time::Duration;
+
+    fn open_ssh() -> ScanResult {
+        ScanResult {
+            latency: Some(Duration::from_millis(12)),
+            banner: Some("SSH-2.0-OpenSSH_9.6\r\n".to_string()),
+            ..ScanResult::new("10.0.0.5".parse().unwrap(), 22, PortState::Open)
+        }
+    }
+
+    #[test]
+    fn test_render_default_style_template() {
+        let template = parse_template("{addr}:{port} {state} {latency}ms {service}").unwrap();
+        assert_eq!(template.render(&open_ssh()), "10.0.0.5:22 open 12ms ssh");
+    }
+
+    #[test]
+    fn test_render_banner_and_missing_values() {
+        let template = parse_template("{port}\t{banner}\t{source}").unwrap();
+        assert_eq!(template.render(&open_ssh()), "22\tSSH-2.0-OpenSSH_9.6\t-");
+
+        let closed = ScanResult::new("10.0.0.5".parse().unwrap(), 4, PortState::Closed);
+        let template = parse_template("{port} {service} {latency}").unwrap();
+        assert_eq!(template.render(&closed), "4 - -");
+
+        let named = ScanResult {
+            hostname: Some("bastion.lab".to_string()),
+            ..open_ssh()
+        };
+        let template = parse_template("{hostname} {addr}").unwrap();
+        assert_eq!(template.render(&named), "bastion.lab 10.0.0.5");
+        assert_eq!(template.render(&open_ssh()), "- 10.0.0.5");
+    }
+
+    #[test]
+    fn test_render_escaped_braces() {
+        let template = parse_template("{{{port}}}").unwrap();
+        assert_eq!(template.render(&open_ssh()), "{22}");
+    }
+
+    #[test]
+    fn test_parse_template_unknown_field() {
+        let result = parse_template("{addr} {os}");
+        assert_eq!(result, Err("Unknown template field: {os}".to_string()));
+    }
+
+    #[test]
+    fn test_parse_template_unbalanced_braces() {
+        assert_eq!(
+            parse_template("{addr"),
+            Err("Unclosed template field: {addr".to_string())
+        );
+        assert_eq!(
+            parse_template("addr}"),
+            Err("Unmatched '}' in template: addr}".to_string())
+        );
+    }
+}
@@ -0,0 +1,79 @@
+use crate::{
+    output::{summary, template::Template, theme::ColorTheme, write_each, OutputOptions, Report},
+    scan::{
+        aggregate::group_services,
+        result::{PortState, ScanResult},
+    },
+};
+use std::io::{self, Write};
+
+/// Writes one human-readable line per open (or, for NULL/FIN/Xmas scans, open|filtered) port,
+/// using the options' template when one is given. With `explain`, every probed port gets a
+/// line, followed by how its state was decided; with `show_time`, lines start with the time
+/// the probe completed. The lines are formatted on `options.threads` threads.
+///
+/// Watch cycles are preceded by a `# cycle N started at ...` header line, and a requested
+/// summary follows the results as a `# ...` line colored by the options' theme.
+pub fn write<W: Write>(writer: &mut W, report: &Report, options: &OutputOptions) -> io::Result<()> {
+    if let (Some(cycle), Some(started_at)) = (report.cycle, &report.started_at) {
+        writeln!(writer, "# cycle {} started at {}", cycle, started_at)?;
+    }
+
+    let template = options.template.as_ref();
+    write_each(writer, &report.results, options.threads, |mut w, result| {
+        write_result(&mut w, result, template, options.explain, options.show_time)
+    })?;
+    if let Some(summary) = &report.summary {
+        summary::write(writer, summary, options.theme)?;
+    }
+    Ok(())
+}
+
+/// Writes the report like `write`, but with one line per group of a host's open (or
+/// open|filtered) ports that share a banner and service, e.g.
+/// `10.0.0.1 ports [8000-8010] open service=http`.
+pub fn write_grouped<W: Write>(
+    writer: &mut W,
+    report: &Report,
+    theme: ColorTheme,
+) -> io::Result<()> {
+    if let (Some(cycle), Some(started_at)) = (report.cycle, &report.started_at) {
+        writeln!(writer, "# cycle {} started at {}", cycle, started_at)?;
+    }
+
+    let shown: Vec<ScanResult> = report
+        .results
+        .iter()
+        .filter(|r| matches!(r.state, PortState::Open | PortState::OpenFiltered))
+        .cloned()
+        .collect();
+    for group in group_services(&shown) {
+        writeln!(writer, "{}", group)?;
+    }
+    if let Some(summary) = &report.summary {
+        summary::write(writer, summary, theme)?;
+    }
+    Ok(())
+}
+
+/// Writes the line for a single result, or nothing if its port is not open and `explain` is
+/// not set.
+pub fn write_result<W: Write>(
+    writer: &mut W,
+    result: &ScanResult,
+    template: Option<&Template>,
+    explain: bool,
+    show_time: bool,
+) -> io::Result<()> {
+    if !explain && !matches!(result.state, PortState::Open | PortState::OpenFiltered) {
+        return Ok(());
+    }
+    if let (true, Some(timestamp)) = (show_time, &result.timestamp) {
+        write!(writer, "{} ", timestamp)?;
+    }
+    match (template, &result.detail) {
+        (Some(template), _) => writeln!(writer, "{}", template.render(result)),
+        (None, Some(detail)) if explain => writeln!(writer, "{} -- {}", result, detail),
+        (None, _) => writeln!(writer, "{}", result),
+    }
+}
@@ -0,0 +1,96 @@
+use clap::ValueEnum;
+use std::ffi::OsStr;
+
+/// Represents the colors used for summary counts, picked to suit the terminal background.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ColorTheme {
+    /// Bright colors for dark backgrounds
+    Dark,
+    /// Deeper colors for light backgrounds
+    Light,
+    /// No colors
+    None,
+}
+
+/// ANSI SGR codes for each port state a summary counts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Palette {
+    pub open: &'static str,
+    pub closed: &'static str,
+    pub filtered: &'static str,
+    pub errors: &'static str,
+}
+
+const DARK: Palette = Palette {
+    open: "92",
+    closed: "91",
+    filtered: "93",
+    errors: "95",
+};
+
+const LIGHT: Palette = Palette {
+    open: "32",
+    closed: "31",
+    filtered: "33",
+    errors: "35",
+};
+
+impl ColorTheme {
+    /// Returns the theme's colors, or `None` for the colorless theme.
+    pub fn palette(self) -> Option<Palette> {
+        match self {
+            ColorTheme::Dark => Some(DARK),
+            ColorTheme::Light => Some(LIGHT),
+            ColorTheme::None => None,
+        }
+    }
+}
+
+/// Returns the theme to use: `requested`, unless the `NO_COLOR` convention turns colors off.
+///
+/// Any non-empty `NO_COLOR` value disables colors, whatever theme was asked for.
+pub fn resolve_theme(requested: ColorTheme, no_color: Option<&OsStr>) -> ColorTheme {
+    match no_color {
+        Some(value) if !value.is_empty() => ColorTheme::None,
+        _ => requested,
+    }
+}
+
+/// Wraps `text` in the SGR `code` and a reset.
+pub fn paint(text: &str, code: &str) -> String {
+    format!("\x1b[{}m{}\x1b[0m", code, text)
+}
+
+// Unit tests >------------------------------------------------------------<
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_theme_palettes() {
+        let dark = ColorTheme::Dark.palette().unwrap();
+        assert_eq!(
+            (dark.open, dark.closed, dark.filtered, dark.errors),
+            ("92", "91", "93", "95")
+        );
+        let light = ColorTheme::Light.palette().unwrap();
+        assert_eq!(
+            (light.open, light.closed, light.filtered, light.errors),
+            ("32", "31", "33", "35")
+        );
+        assert_eq!(ColorTheme::None.palette(), None);
+        assert_eq!(paint("3 open", dark.open), "\x1b[92m3 open\x1b[0m");
+    }
+
+    #[test]
+    fn test_resolve_theme_respects_no_color() {
+        let set = Some(OsStr::new("1"));
+        assert_eq!(resolve_theme(ColorTheme::Dark, set), ColorTheme::None);
+        assert_eq!(resolve_theme(ColorTheme::Light, set), ColorTheme::None);
+        assert_eq!(
+            resolve_theme(ColorTheme::Dark, Some(OsStr::new(""))),
+            ColorTheme::Dark
+        );
+        assert_eq!(resolve_theme(ColorTheme::Light, None), ColorTheme::Light);
+    }
+}
@@ -0,0 +1,125 @@
+use crate::{
+    output::{summary, theme::ColorTheme, Report},
+    scan::{
+        aggregate::{group_by_host, HostReport},
+        result::{PortState, ScanResult},
+    },
+};
+use std::io::{self, Write};
+
+/// Writes the report as a tree: a header line per host with an open (or open|filtered) port,
+/// then one indented line per such port with its service and latency, e.g.
+///
+/// ```text
+/// 10.0.0.1 (gateway.lan)
+///   22 open [ssh] (3ms)
+///   80 open [http] (1ms)
+/// ```
+///
+/// Hosts with nothing open are collapsed into a single `# N hosts without open ports` line.
+/// Watch cycles are preceded by a `# cycle N started at ...` header line, and a requested
+/// summary follows the hosts as `# ...` lines colored by `theme`.
+pub fn write<W: Write>(writer: &mut W, report: &Report, theme: ColorTheme) -> io::Result<()> {
+    if let (Some(cycle), Some(started_at)) = (report.cycle, &report.started_at) {
+        writeln!(writer, "# cycle {} started at {}", cycle, started_at)?;
+    }
+
+    let mut collapsed = 0;
+    for host in group_by_host(report.results.clone()) {
+        let shown: Vec<&ScanResult> = host
+            .ports
+            .iter()
+            .filter(|r| matches!(r.state, PortState::Open | PortState::OpenFiltered))
+            .collect();
+        if shown.is_empty() {
+            collapsed += 1;
+            continue;
+        }
+        write_header(writer, &host)?;
+        for result in shown {
+            write_port(writer, result)?;
+        }
+    }
+    if collapsed > 0 {
+        let noun = if collapsed == 1 { "host" } else { "hosts" };
+        writeln!(writer, "# {} {} without open ports", collapsed, noun)?;
+    }
+    if let Some(summary) = &report.summary {
+        summary::write(writer, summary, theme)?;
+    }
+    Ok(())
+}
+
+/// Writes the host's address, followed by its reverse DNS name when one was resolved.
+fn write_header<W: Write>(writer: &mut W, host: &HostReport) -> io::Result<()> {
+    match host.ports.iter().find_map(|r| r.hostname.as_ref()) {
+        Some(hostname) => writeln!(writer, "{} ({})", host.address, hostname),
+        None => writeln!(writer, "{}", host.address),
+    }
+}
+
+/// Writes an indented `<port> <state> [service] (latency)` line.
+fn write_port<W: Write>(writer: &mut W, result: &ScanResult) -> io::Result<()> {
+    write!(writer, "  {}", result.port)?;
+    if let Some(interface) = &result.interface {
+        write!(writer, "%{}", interface)?;
+    }
+    write!(writer, " {}", result.state)?;
+    if let Some(service) = &result.service {
+        write!(writer, " [{}]", service)?;
+    }
+    if let Some(latency) = result.latency {
+        write!(writer, " ({}ms)", latency.as_millis())?;
+    }
+    writeln!(writer)
+}
+
+// Unit tests >------------------------------------------------------------<
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn result(address: &str, port: u16, state: PortState) -> ScanResult {
+        ScanResult::new(address.parse().unwrap(), port, state)
+    }
+
+    #[test]
+    fn test_write_tree_indents_ports_under_hosts() {
+        let report = Report {
+            results: vec![
+                ScanResult {
+                    service: Some("http".to_string()),
+                    latency: Some(Duration::from_millis(1)),
+                    ..result("10.0.0.2", 80, PortState::Open)
+                },
+                ScanResult {
+                    hostname: Some("gateway.lan".to_string()),
+                    service: Some("ssh".to_string()),
+                    latency: Some(Duration::from_millis(3)),
+                    ..result("10.0.0.1", 22, PortState::Open)
+                },
+                result("10.0.0.1", 23, PortState::Closed),
+                ScanResult {
+                    hostname: Some("gateway.lan".to_string()),
+                    ..result("10.0.0.1", 443, PortState::Open)
+                },
+                result("10.0.0.3", 22, PortState::Filtered),
+                result("10.0.0.4", 22, PortState::Closed),
+            ],
+            ..Report::default()
+        };
+
+        let mut buf = Vec::new();
+        write(&mut buf, &report, ColorTheme::None).unwrap();
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "10.0.0.1 (gateway.lan)\n\
+             \x20 22 open [ssh] (3ms)\n\
+             \x20 443 open\n\
+             10.0.0.2\n\
+             \x20 80 open [http] (1ms)\n\
+             # 2 hosts without open ports\n"
+        );
+    }
+}
@@ -0,0 +1,239 @@
+use crate::output::checksum::{Checksum, ChecksumWriter};
+use flate2::{write::GzEncoder, Compression};
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+/// The destination scan results are written to, optionally gzip-compressed.
+pub enum OutputWriter {
+    Plain(Box<dyn Write + Send>),
+    Gzip(GzEncoder<Box<dyn Write + Send>>),
+}
+
+impl OutputWriter {
+    /// Opens the results destination: `path` if given, stdout otherwise.
+    ///
+    /// Output is gzip-compressed when `compress` is set or `path` ends in `.gz`.
+    pub fn open(path: Option<&Path>, compress: bool) -> io::Result<Self> {
+        let inner: Box<dyn Write + Send> = match path {
+            Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+            None => Box::new(io::stdout()),
+        };
+
+        Ok(Self::wrap(
+            inner,
+            compress || path.is_some_and(is_gzip_path),
+        ))
+    }
+
+    /// Opens the results file `path` like `open`, along with a running SHA-256 of the bytes
+    /// that reach the file, compressed ones included.
+    pub fn open_checksummed(path: &Path, compress: bool) -> io::Result<(Self, Checksum)> {
+        let checksum = Checksum::default();
+        let file = BufWriter::new(File::create(path)?);
+        let inner: Box<dyn Write + Send> = Box::new(ChecksumWriter::new(file, checksum.clone()));
+        Ok((Self::wrap(inner, compress || is_gzip_path(path)), checksum))
+    }
+
+    /// Opens file descriptor `fd`, inherited from the caller, as the results destination.
+    ///
+    /// Output is gzip-compressed when `compress` is set.
+    pub fn open_fd(fd: i32, compress: bool) -> io::Result<Self> {
+        let inner: Box<dyn Write + Send> = Box::new(BufWriter::new(open_fd(fd)?));
+        Ok(Self::wrap(inner, compress))
+    }
+
+    fn wrap(inner: Box<dyn Write + Send>, gzip: bool) -> Self {
+        if gzip {
+            OutputWriter::Gzip(GzEncoder::new(inner, Compression::default()))
+        } else {
+            OutputWriter::Plain(inner)
+        }
+    }
+
+    /// Flushes everything and, for gzip output, writes the trailer.
+    pub fn finish(self) -> io::Result<()> {
+        match self {
+            OutputWriter::Plain(mut inner) => inner.flush(),
+            OutputWriter::Gzip(encoder) => encoder.finish()?.flush(),
+        }
+    }
+}
+
+impl Write for OutputWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            OutputWriter::Plain(inner) => inner.write(buf),
+            OutputWriter::Gzip(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            OutputWriter::Plain(inner) => inner.flush(),
+            OutputWriter::Gzip(encoder) => encoder.flush(),
+        }
+    }
+}
+
+/// Opens an inherited file descriptor for writing, e.g. one end of a pipe set up by a parent.
+///
+/// The descriptor is duplicated, so dropping the returned file leaves `fd` itself open.
+#[cfg(unix)]
+pub fn open_fd(fd: i32) -> io::Result<File> {
+    use std::os::fd::FromRawFd;
+
+    // SAFETY: F_DUPFD_CLOEXEC only reads `fd` and fails cleanly if it is not open.
+    let duplicate = unsafe { libc::fcntl(fd, libc::F_DUPFD_CLOEXEC, 0) };
+    if duplicate < 0 {
+        let e = io::Error::last_os_error();
+        return Err(io::Error::new(
+            e.kind(),
+            format!("Invalid file descriptor {}: {}", fd, e),
+        ));
+    }
+    // SAFETY: `duplicate` is a fresh descriptor nothing else owns.
+    Ok(unsafe { File::from_raw_fd(duplicate) })
+}
+
+#[cfg(not(unix))]
+pub fn open_fd(fd: i32) -> io::Result<File> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!(
+            "Writing to file descriptor {} is only supported on Unix",
+            fd
+        ),
+    ))
+}
+
+fn is_gzip_path(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "gz")
+}
+
+// Unit tests >------------------------------------------------------------<
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        output::{json::write_json, Report},
+        scan::result::{PortState, ScanResult},
+    };
+    use flate2::read::GzDecoder;
+    use std::{fs, io::Read};
+
+    #[test]
+    fn test_gzip_output_round_trip() {
+        let report = Report {
+            results: vec![ScanResult::new(
+                "127.0.0.1".parse().unwrap(),
+                443,
+                PortState::Open,
+            )],
+            ..Report::default()
+        };
+        let path = std::env::temp_dir().join(format!("port_hawk_{}.json.gz", std::process::id()));
+
+        let mut writer = OutputWriter::open(Some(&path), false).unwrap();
+        write_json(&mut writer, &report, false).unwrap();
+        writer.finish().unwrap();
+
+        let mut decompressed = Vec::new();
+        GzDecoder::new(File::open(&path).unwrap())
+            .read_to_end(&mut decompressed)
+            .unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let mut uncompressed = Vec::new();
+        write_json(&mut uncompressed, &report, false).unwrap();
+        assert_eq!(decompressed, uncompressed);
+
+        let parsed: Report = serde_json::from_slice(&decompressed).unwrap();
+        assert_eq!(parsed, report);
+    }
+
+    /// Returns the (read, write) ends of a new pipe.
+    fn pipe() -> (File, i32) {
+        use std::os::fd::FromRawFd;
+
+        let mut fds = [0; 2];
+        assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+        (unsafe { File::from_raw_fd(fds[0]) }, fds[1])
+    }
+
+    #[test]
+    fn test_results_and_progress_fds_are_separate_streams() {
+        let (mut results_read, results_fd) = pipe();
+        let (mut progress_read, progress_fd) = pipe();
+
+        let mut results = OutputWriter::open_fd(results_fd, false).unwrap();
+        let mut progress = open_fd(progress_fd).unwrap();
+        writeln!(results, "127.0.0.1:22 open").unwrap();
+        writeln!(progress, "Warning: something").unwrap();
+        results.finish().unwrap();
+        drop(progress);
+        // The writers held duplicates; the originals stay open until closed here.
+        unsafe {
+            libc::close(results_fd);
+            libc::close(progress_fd);
+        }
+
+        let (mut out, mut err) = (String::new(), String::new());
+        results_read.read_to_string(&mut out).unwrap();
+        progress_read.read_to_string(&mut err).unwrap();
+        assert_eq!(out, "127.0.0.1:22 open\n");
+        assert_eq!(err, "Warning: something\n");
+    }
+
+    #[test]
+    fn test_open_fd_rejects_unopened_descriptor() {
+        let error = open_fd(987_654).unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("Invalid file descriptor 987654:"));
+    }
+
+    #[test]
+    fn test_checksum_matches_written_file() {
+        use crate::output::checksum::{sha256_hex, sidecar_path, write_sidecar};
+
+        let report = Report {
+            results: vec![ScanResult::new(
+                "127.0.0.1".parse().unwrap(),
+                22,
+                PortState::Open,
+            )],
+            ..Report::default()
+        };
+        for name in ["checksum.json", "checksum.json.gz"] {
+            let path =
+                std::env::temp_dir().join(format!("port_hawk_{}_{}", std::process::id(), name));
+            let (mut writer, checksum) = OutputWriter::open_checksummed(&path, false).unwrap();
+            write_json(&mut writer, &report, true).unwrap();
+            writer.finish().unwrap();
+            write_sidecar(&path, &checksum).unwrap();
+
+            let written = fs::read(&path).unwrap();
+            let sidecar = fs::read_to_string(sidecar_path(&path)).unwrap();
+            assert_eq!(
+                sidecar,
+                format!(
+                    "{}  port_hawk_{}_{}\n",
+                    sha256_hex(&written),
+                    std::process::id(),
+                    name
+                )
+            );
+            fs::remove_file(sidecar_path(&path)).unwrap();
+            fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_is_gzip_path() {
+        assert!(is_gzip_path(Path::new("results.json.gz")));
+        assert!(!is_gzip_path(Path::new("results.json")));
+    }
+}
@@ -0,0 +1,358 @@
+use crate::scan::result::{PortState, ScanResult};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    net::IpAddr,
+};
+
+/// Represents whether a host answered on any of its scanned ports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HostState {
+    /// At least one port was `Open` or `Closed`, so something on the host responded.
+    Up,
+    /// Every port was `Filtered` or `Error`.
+    Down,
+}
+
+/// The results for a single host.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HostReport {
+    pub address: IpAddr,
+    pub host_state: HostState,
+    /// The host's results, sorted by port.
+    pub ports: Vec<ScanResult>,
+}
+
+/// Collapses the results to exactly one per (host, port, protocol, interface), sorted by
+/// address, port and protocol.
+///
+/// When the same port was probed several times (overlapping targets, retries), the most
+/// conclusive outcome wins: `Open` over `Closed` over `OpenFiltered` over `Filtered` over `Error`.
+/// Among equally conclusive results the one that arrived last is kept.
+pub fn dedup(results: Vec<ScanResult>) -> Vec<ScanResult> {
+    let mut best: HashMap<_, ScanResult> = HashMap::with_capacity(results.len());
+
+    for result in results {
+        let key = (
+            result.address,
+            result.port,
+            result.protocol,
+            result.interface.clone(),
+        );
+        match best.get(&key) {
+            Some(current) if precedence(current.state) > precedence(result.state) => {}
+            _ => {
+                best.insert(key, result);
+            }
+        }
+    }
+
+    let mut results: Vec<ScanResult> = best.into_values().collect();
+    results.sort_by(|a, b| {
+        (a.address, a.port, a.protocol, &a.interface).cmp(&(
+            b.address,
+            b.port,
+            b.protocol,
+            &b.interface,
+        ))
+    });
+    results
+}
+
+/// Groups a flat result list into one `HostReport` per address, sorted by address.
+pub fn group_by_host(results: Vec<ScanResult>) -> Vec<HostReport> {
+    let mut hosts: HashMap<IpAddr, Vec<ScanResult>> = HashMap::new();
+    for result in results {
+        hosts.entry(result.address).or_default().push(result);
+    }
+
+    let mut reports: Vec<HostReport> = hosts
+        .into_iter()
+        .map(|(address, mut ports)| {
+            ports.sort_by_key(|r| r.port);
+            let responded = ports
+                .iter()
+                .any(|r| matches!(r.state, PortState::Open | PortState::Closed));
+            HostReport {
+                address,
+                host_state: if responded {
+                    HostState::Up
+                } else {
+                    HostState::Down
+                },
+                ports,
+            }
+        })
+        .collect();
+    reports.sort_by_key(|report| report.address);
+    reports
+}
+
+/// Ports of one host that answered identically: in the same state, with the same banner and
+/// identified service.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServiceGroup {
+    pub address: IpAddr,
+    pub state: PortState,
+    /// The ports, as sorted inclusive ranges of consecutive ports.
+    pub ports: Vec<(u16, u16)>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub banner: Option<String>,
+}
+
+impl fmt::Display for ServiceGroup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ports: Vec<String> = self
+            .ports
+            .iter()
+            .map(|&(start, end)| {
+                if start == end {
+                    start.to_string()
+                } else {
+                    format!("{}-{}", start, end)
+                }
+            })
+            .collect();
+        write!(
+            f,
+            "{} ports [{}] {} service={}",
+            self.address,
+            ports.join(","),
+            self.state,
+            self.service.as_deref().unwrap_or("-")
+        )?;
+        if let Some(banner) = &self.banner {
+            write!(f, " banner={}", banner.escape_debug())?;
+        }
+        Ok(())
+    }
+}
+
+/// Groups the results of each host by (state, banner, service), so a service that answers
+/// the same on a whole range of ports takes one entry. Banners are compared trimmed.
+///
+/// Groups are sorted by address, then by their lowest port.
+pub fn group_services(results: &[ScanResult]) -> Vec<ServiceGroup> {
+    let mut groups: BTreeMap<_, (PortState, Vec<u16>)> = BTreeMap::new();
+    for result in results {
+        let banner = result.banner.as_deref().map(str::trim);
+        let key = (
+            result.address,
+            precedence(result.state),
+            result.service.as_deref(),
+            banner,
+        );
+        groups
+            .entry(key)
+            .or_insert_with(|| (result.state, Vec::new()))
+            .1
+            .push(result.port);
+    }
+
+    let mut groups: Vec<ServiceGroup> = groups
+        .into_iter()
+        .map(|((address, _, service, banner), (state, mut ports))| {
+            ports.sort_unstable();
+            ports.dedup();
+            ServiceGroup {
+                address,
+                state,
+                ports: coalesce(&ports),
+                service: service.map(str::to_string),
+                banner: banner.map(str::to_string),
+            }
+        })
+        .collect();
+    groups.sort_by_key(|group| (group.address, group.ports[0].0));
+    groups
+}
+
+/// Turns sorted, distinct ports into inclusive ranges of consecutive ports.
+fn coalesce(ports: &[u16]) -> Vec<(u16, u16)> {
+    let mut ranges: Vec<(u16, u16)> = Vec::new();
+    for &port in ports {
+        match ranges.last_mut() {
+            Some((_, end)) if *end as u32 + 1 == port as u32 => *end = port,
+            _ => ranges.push((port, port)),
+        }
+    }
+    ranges
+}
+
+fn precedence(state: PortState) -> u8 {
+    match state {
+        PortState::Open => 4,
+        PortState::Closed => 3,
+        PortState::OpenFiltered => 2,
+        PortState::Filtered => 1,
+        PortState::Error => 0,
+    }
+}
+
+// Unit tests >------------------------------------------------------------<
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::args::parser::Protocol;
+    use std::time::Duration;
+
+    fn result(port: u16, state: PortState) -> ScanResult {
+        ScanResult::new("10.0.0.1".parse().unwrap(), port, state)
+    }
+
+    #[test]
+    fn test_dedup_prefers_open() {
+        let results = dedup(vec![
+            result(22, PortState::Filtered),
+            result(22, PortState::Open),
+            result(22, PortState::Closed),
+        ]);
+        assert_eq!(results, vec![result(22, PortState::Open)]);
+    }
+
+    #[test]
+    fn test_dedup_prefers_closed_over_filtered() {
+        let results = dedup(vec![
+            result(80, PortState::Closed),
+            result(80, PortState::Filtered),
+        ]);
+        assert_eq!(results, vec![result(80, PortState::Closed)]);
+    }
+
+    #[test]
+    fn test_dedup_keeps_last_of_equal_precedence() {
+        let last = ScanResult {
+            latency: Some(Duration::from_millis(5)),
+            ..result(443, PortState::Open)
+        };
+        let results = dedup(vec![result(443, PortState::Open), last.clone()]);
+        assert_eq!(results, vec![last]);
+    }
+
+    #[test]
+    fn test_dedup_keeps_distinct_ports_sorted() {
+        let results = dedup(vec![
+            result(443, PortState::Open),
+            result(22, PortState::Closed),
+            result(443, PortState::Filtered),
+        ]);
+        assert_eq!(
+            results,
+            vec![result(22, PortState::Closed), result(443, PortState::Open)]
+        );
+    }
+
+    #[test]
+    fn test_dedup_keeps_each_protocol_of_a_port() {
+        let udp = |state| ScanResult {
+            protocol: Protocol::Udp,
+            ..result(53, state)
+        };
+        let results = dedup(vec![
+            udp(PortState::OpenFiltered),
+            result(53, PortState::Closed),
+            udp(PortState::Open),
+            result(53, PortState::Open),
+        ]);
+        assert_eq!(
+            results,
+            vec![result(53, PortState::Open), udp(PortState::Open)]
+        );
+    }
+
+    #[test]
+    fn test_group_by_host() {
+        let on =
+            |address: &str, port, state| ScanResult::new(address.parse().unwrap(), port, state);
+        let reports = group_by_host(vec![
+            on("10.0.0.2", 80, PortState::Filtered),
+            on("10.0.0.1", 443, PortState::Open),
+            on("10.0.0.3", 22, PortState::Closed),
+            on("10.0.0.1", 22, PortState::Closed),
+            on("10.0.0.2", 22, PortState::Error),
+        ]);
+
+        assert_eq!(
+            reports,
+            vec![
+                HostReport {
+                    address: "10.0.0.1".parse().unwrap(),
+                    host_state: HostState::Up,
+                    ports: vec![
+                        on("10.0.0.1", 22, PortState::Closed),
+                        on("10.0.0.1", 443, PortState::Open),
+                    ],
+                },
+                HostReport {
+                    address: "10.0.0.2".parse().unwrap(),
+                    host_state: HostState::Down,
+                    ports: vec![
+                        on("10.0.0.2", 22, PortState::Error),
+                        on("10.0.0.2", 80, PortState::Filtered),
+                    ],
+                },
+                HostReport {
+                    address: "10.0.0.3".parse().unwrap(),
+                    host_state: HostState::Up,
+                    ports: vec![on("10.0.0.3", 22, PortState::Closed)],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_group_services_merges_identical_banners() {
+        let web = |port| ScanResult {
+            banner: Some("HTTP/1.1 200 OK\r\nServer: lb\r\n".to_string()),
+            service: Some("http".to_string()),
+            ..result(port, PortState::Open)
+        };
+        let mut results: Vec<ScanResult> = (8000..=8010).map(web).collect();
+        results.push(ScanResult {
+            banner: Some("SSH-2.0-OpenSSH_9.6".to_string()),
+            service: Some("ssh".to_string()),
+            ..result(22, PortState::Open)
+        });
+        results.push(web(9000));
+        results.push(ScanResult {
+            banner: Some("HTTP/1.1 404 Not Found".to_string()),
+            service: Some("http".to_string()),
+            ..result(8011, PortState::Open)
+        });
+        results.push(ScanResult {
+            address: "10.0.0.2".parse().unwrap(),
+            ..web(8000)
+        });
+
+        let groups = group_services(&results);
+        let lines: Vec<String> = groups.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            lines,
+            vec![
+                "10.0.0.1 ports [22] open service=ssh banner=SSH-2.0-OpenSSH_9.6",
+                "10.0.0.1 ports [8000-8010,9000] open service=http banner=HTTP/1.1 200 OK\\r\\nServer: lb",
+                "10.0.0.1 ports [8011] open service=http banner=HTTP/1.1 404 Not Found",
+                "10.0.0.2 ports [8000] open service=http banner=HTTP/1.1 200 OK\\r\\nServer: lb",
+            ]
+        );
+        assert_eq!(groups[1].ports, vec![(8000, 8010), (9000, 9000)]);
+    }
+
+    #[test]
+    fn test_group_services_keeps_states_apart() {
+        let groups = group_services(&[
+            result(1, PortState::OpenFiltered),
+            result(2, PortState::Open),
+            result(3, PortState::OpenFiltered),
+        ]);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].state, PortState::OpenFiltered);
+        assert_eq!(groups[0].ports, vec![(1, 1), (3, 3)]);
+        assert_eq!(groups[1].state, PortState::Open);
+        assert_eq!(groups[1].to_string(), "10.0.0.1 ports [2] open service=-");
+    }
+}
@@ -0,0 +1,423 @@
+use crate::scan::result::OpenSubState;
+use clap::ValueEnum;
+use std::{
+    io::{ErrorKind, Read},
+    iter::Peekable,
+    net::TcpStream,
+    str::Chars,
+    time::{Duration, Instant},
+};
+
+/// Default maximum number of bytes read from a banner.
+pub const DEFAULT_BANNER_BYTES: usize = 512;
+
+/// Share of control bytes above which a banner is treated as binary rather than text.
+const BINARY_CONTROL_SHARE: f64 = 0.1;
+
+/// Represents how banners that are not valid UTF-8 are decoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum BannerCharset {
+    /// Replace invalid sequences with U+FFFD
+    #[default]
+    Utf8,
+    /// Read every byte as a Latin-1 character, losing nothing
+    Latin1,
+}
+
+/// Limits applied when reading a service banner.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BannerOptions {
+    /// Maximum number of bytes kept.
+    pub bytes: usize,
+    /// When set, keep reading until this many newlines have arrived instead of stopping after
+    /// the first read.
+    pub lines: Option<usize>,
+    /// How to decode banners that are not valid UTF-8.
+    pub charset: BannerCharset,
+}
+
+impl Default for BannerOptions {
+    fn default() -> Self {
+        Self {
+            bytes: DEFAULT_BANNER_BYTES,
+            lines: None,
+            charset: BannerCharset::Utf8,
+        }
+    }
+}
+
+/// A banner decoded for output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Banner {
+    pub text: String,
+    /// The banner was binary, so `text` escapes every byte outside printable ASCII as `\xNN`.
+    pub binary: bool,
+}
+
+/// Decodes raw banner bytes for output.
+///
+/// Valid UTF-8 is kept as is; other text is decoded with `charset`. Banners that are clearly
+/// binary, with a NUL byte or more than a tenth of control bytes besides tabs and line breaks,
+/// are hex-escaped instead, since no charset would make them readable.
+pub fn decode_banner(bytes: &[u8], charset: BannerCharset) -> Banner {
+    let controls = bytes
+        .iter()
+        .filter(|b| b.is_ascii_control() && !matches!(b, b'\t' | b'\n' | b'\r'))
+        .count();
+    if bytes.contains(&0) || controls as f64 > bytes.len() as f64 * BINARY_CONTROL_SHARE {
+        return Banner {
+            text: hex_escape(bytes),
+            binary: true,
+        };
+    }
+    let text = match (std::str::from_utf8(bytes), charset) {
+        (Ok(text), _) => text.to_string(),
+        (Err(_), BannerCharset::Utf8) => String::from_utf8_lossy(bytes).into_owned(),
+        (Err(_), BannerCharset::Latin1) => bytes.iter().map(|b| char::from(*b)).collect(),
+    };
+    Banner {
+        text,
+        binary: false,
+    }
+}
+
+/// Writes printable ASCII as is, a backslash as `\\`, and every other byte as `\xNN`.
+fn hex_escape(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|b| match b {
+            b'\\' => "\\\\".to_string(),
+            b' '..=b'~' => char::from(*b).to_string(),
+            _ => format!("\\x{:02x}", b),
+        })
+        .collect()
+}
+
+/// Strips terminal control sequences from a decoded banner, for `--quiet-banner`.
+///
+/// ANSI escape sequences (CSI like `ESC [31m`, OSC like a window title set by `ESC ]0;...BEL`,
+/// and the other `ESC` forms) are removed whole, as are all other control characters. Each run
+/// of tabs and line breaks becomes a single space, so a multi-line banner stays on one line.
+pub fn sanitize_banner(text: &str) -> String {
+    let mut sanitized = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\u{1b}' => match chars.next() {
+                Some('[') => skip_csi(&mut chars),
+                Some(']' | 'P' | 'X' | '^' | '_') => skip_string(&mut chars),
+                // Intermediate bytes, then the final one: `ESC ( B`, `ESC 7`.
+                Some(' '..='/') => {
+                    while chars.next_if(|c| matches!(c, ' '..='/')).is_some() {}
+                    chars.next();
+                }
+                _ => {}
+            },
+            '\u{9b}' => skip_csi(&mut chars),
+            '\u{9d}' | '\u{90}' | '\u{98}' | '\u{9e}' | '\u{9f}' => skip_string(&mut chars),
+            '\t' | '\r' | '\n' => {
+                while chars.next_if(|c| matches!(c, '\t' | '\r' | '\n')).is_some() {}
+                sanitized.push(' ');
+            }
+            c if c.is_control() => {}
+            c => sanitized.push(c),
+        }
+    }
+    sanitized
+}
+
+/// Skips the rest of a CSI sequence: parameter and intermediate bytes, then its final byte.
+fn skip_csi(chars: &mut Peekable<Chars<'_>>) {
+    while chars
+        .next_if(|c| matches!(c, '0'..='?' | ' '..='/'))
+        .is_some()
+    {}
+    chars.next_if(|c| matches!(c, '@'..='~'));
+}
+
+/// Skips the rest of an OSC (or DCS, SOS, PM, APC) string, up to and including the BEL or
+/// string terminator (`ESC \`) that ends it.
+fn skip_string(chars: &mut Peekable<Chars<'_>>) {
+    while let Some(c) = chars.next() {
+        match c {
+            '\u{7}' | '\u{9c}' => return,
+            '\u{1b}' if chars.next_if_eq(&'\\').is_some() => return,
+            _ => {}
+        }
+    }
+}
+
+/// Reads a banner from a freshly connected stream, giving up once `timeout` has elapsed.
+///
+/// Without `lines`, whatever the first read returns (up to `bytes`) is the banner. With `lines`,
+/// reads continue until that many newlines arrive, `bytes` is reached, the peer closes, or the
+/// timeout expires, whichever comes first. Returns the raw bytes, for `decode_banner`, or `None`
+/// if nothing was received.
+pub fn read_banner(
+    stream: &mut TcpStream,
+    options: &BannerOptions,
+    timeout: Duration,
+) -> Option<Vec<u8>> {
+    read_banner_state(stream, options, timeout).0
+}
+
+/// Reads a banner like `read_banner` and also reports how the port behaved while it did.
+///
+/// A port that sent anything is `Banner`, even if it reset afterwards. One that reset the
+/// connection before sending a byte is `OpenButReset`, which is typical of tarpits and of
+/// services that crash on accept; one that stayed silent or closed cleanly is `NoData`.
+pub fn read_banner_state(
+    stream: &mut TcpStream,
+    options: &BannerOptions,
+    timeout: Duration,
+) -> (Option<Vec<u8>>, OpenSubState) {
+    let deadline = Instant::now() + timeout;
+    let mut reset = false;
+    let mut banner = Vec::new();
+    let mut chunk = vec![0u8; options.bytes.min(4096)];
+
+    while banner.len() < options.bytes {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() || stream.set_read_timeout(Some(remaining)).is_err() {
+            break;
+        }
+
+        let want = (options.bytes - banner.len()).min(chunk.len());
+        match stream.read(&mut chunk[..want]) {
+            Ok(0) => break,
+            Err(e) => {
+                reset = matches!(
+                    e.kind(),
+                    ErrorKind::ConnectionReset | ErrorKind::ConnectionAborted
+                );
+                break;
+            }
+            Ok(n) => banner.extend_from_slice(&chunk[..n]),
+        }
+
+        match options.lines {
+            None => break,
+            Some(lines) => {
+                if let Some(end) = nth_line_end(&banner, lines) {
+                    banner.truncate(end);
+                    break;
+                }
+            }
+        }
+    }
+
+    match (banner.is_empty(), reset) {
+        (true, true) => (None, OpenSubState::OpenButReset),
+        (true, false) => (None, OpenSubState::NoData),
+        (false, _) => (Some(banner), OpenSubState::Banner),
+    }
+}
+
+/// Returns the length of `buf` up to and including its `n`-th newline, if it has that many.
+fn nth_line_end(buf: &[u8], n: usize) -> Option<usize> {
+    buf.iter()
+        .enumerate()
+        .filter(|(_, b)| **b == b'\n')
+        .nth(n.checked_sub(1)?)
+        .map(|(i, _)| i + 1)
+}
+
+// Unit tests >------------------------------------------------------------<
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{io::Write, net::TcpListener, thread};
+
+    const GREETING: [&str; 3] = [
+        "220-mail.example.com ESMTP\r\n",
+        "220-No UCE\r\n",
+        "220 Ready\r\n",
+    ];
+
+    /// Accepts one connection and sends the SMTP-style greeting one line at a time.
+    fn greeting_listener() -> (TcpStream, thread::JoinHandle<()>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            for line in GREETING {
+                stream.write_all(line.as_bytes()).unwrap();
+                thread::sleep(Duration::from_millis(30));
+            }
+        });
+        (TcpStream::connect(addr).unwrap(), server)
+    }
+
+    #[test]
+    fn test_read_banner_first_read_only() {
+        let (mut stream, server) = greeting_listener();
+        let banner = read_banner(
+            &mut stream,
+            &BannerOptions::default(),
+            Duration::from_secs(2),
+        );
+        server.join().unwrap();
+
+        assert_eq!(banner, Some(GREETING[0].as_bytes().to_vec()));
+    }
+
+    #[test]
+    fn test_read_banner_multi_line() {
+        let (mut stream, server) = greeting_listener();
+        let options = BannerOptions {
+            lines: Some(3),
+            ..BannerOptions::default()
+        };
+        let banner = read_banner(&mut stream, &options, Duration::from_secs(2));
+        server.join().unwrap();
+
+        assert_eq!(banner, Some(GREETING.concat().into_bytes()));
+    }
+
+    #[test]
+    fn test_read_banner_byte_limit() {
+        let (mut stream, server) = greeting_listener();
+        let options = BannerOptions {
+            bytes: 8,
+            lines: Some(3),
+            ..BannerOptions::default()
+        };
+        let banner = read_banner(&mut stream, &options, Duration::from_secs(2));
+        server.join().unwrap();
+
+        assert_eq!(banner, Some(b"220-mail".to_vec()));
+    }
+
+    #[test]
+    fn test_read_banner_timeout() {
+        let (mut stream, server) = greeting_listener();
+        let options = BannerOptions {
+            lines: Some(10),
+            ..BannerOptions::default()
+        };
+        let start = Instant::now();
+        let banner = read_banner(&mut stream, &options, Duration::from_millis(40));
+        let elapsed = start.elapsed();
+
+        assert!(elapsed < Duration::from_millis(500));
+        assert!(banner.unwrap().starts_with(GREETING[0].as_bytes()));
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_read_banner_state_reset_after_accept() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            // A zero linger turns the close into an RST.
+            socket2::SockRef::from(&stream)
+                .set_linger(Some(Duration::ZERO))
+                .unwrap();
+        });
+        let mut stream = TcpStream::connect(addr).unwrap();
+        server.join().unwrap();
+
+        let state = read_banner_state(
+            &mut stream,
+            &BannerOptions::default(),
+            Duration::from_secs(2),
+        );
+        assert_eq!(state, (None, OpenSubState::OpenButReset));
+    }
+
+    #[test]
+    fn test_read_banner_state_silent_and_talking() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut silent = TcpStream::connect(addr).unwrap();
+        let (_held, _) = listener.accept().unwrap();
+        let state = read_banner_state(
+            &mut silent,
+            &BannerOptions::default(),
+            Duration::from_millis(50),
+        );
+        assert_eq!(state, (None, OpenSubState::NoData));
+
+        let (mut stream, server) = greeting_listener();
+        let (banner, state) = read_banner_state(
+            &mut stream,
+            &BannerOptions::default(),
+            Duration::from_secs(2),
+        );
+        server.join().unwrap();
+        assert_eq!(banner, Some(GREETING[0].as_bytes().to_vec()));
+        assert_eq!(state, OpenSubState::Banner);
+    }
+
+    #[test]
+    fn test_nth_line_end() {
+        assert_eq!(nth_line_end(b"a\nb\nc", 2), Some(4));
+        assert_eq!(nth_line_end(b"a\nb", 2), None);
+        assert_eq!(nth_line_end(b"a\n", 0), None);
+    }
+
+    #[test]
+    fn test_decode_banner_utf8_latin1_and_binary() {
+        let utf8 = "220 café ready\r\n".as_bytes();
+        for charset in [BannerCharset::Utf8, BannerCharset::Latin1] {
+            assert_eq!(
+                decode_banner(utf8, charset),
+                Banner {
+                    text: "220 café ready\r\n".to_string(),
+                    binary: false
+                }
+            );
+        }
+
+        let latin1 = b"220 caf\xe9 ready\r\n";
+        assert_eq!(
+            decode_banner(latin1, BannerCharset::Utf8).text,
+            "220 caf\u{fffd} ready\r\n"
+        );
+        assert_eq!(
+            decode_banner(latin1, BannerCharset::Latin1),
+            Banner {
+                text: "220 café ready\r\n".to_string(),
+                binary: false
+            }
+        );
+
+        let blob = b"\x00\x00\x00\x1cJ\\\x01\xff";
+        assert_eq!(
+            decode_banner(blob, BannerCharset::Utf8),
+            Banner {
+                text: "\\x00\\x00\\x00\\x1cJ\\\\\\x01\\xff".to_string(),
+                binary: true
+            }
+        );
+    }
+
+    #[test]
+    fn test_sanitize_banner_strips_ansi_sequences() {
+        assert_eq!(
+            sanitize_banner("\x1b[1;31mSSH-2.0\x1b[0m-OpenSSH_9.6"),
+            "SSH-2.0-OpenSSH_9.6"
+        );
+        // A window title, then a hyperlink terminated by ST instead of BEL.
+        assert_eq!(
+            sanitize_banner("\x1b]0;owned\x07220 \x1b]8;;http://x\x1b\\ready\x1b]8;;\x1b\\"),
+            "220 ready"
+        );
+        assert_eq!(sanitize_banner("\x1b(Bcafé\x1bc \u{9b}2Jok\x1b"), "café ok");
+        assert_eq!(sanitize_banner("plain banner"), "plain banner");
+    }
+
+    #[test]
+    fn test_sanitize_banner_strips_control_characters() {
+        assert_eq!(
+            sanitize_banner("220 mail ready\r\n250\tok\r\n"),
+            "220 mail ready 250 ok "
+        );
+        assert_eq!(
+            sanitize_banner("root\x08\x08\x08\x08user\x07\x7f"),
+            "rootuser"
+        );
+        assert_eq!(sanitize_banner("a\u{85}b\u{9d}title\u{9c}c"), "abc");
+    }
+}
@@ -0,0 +1,803 @@
+use crate::scan::{
+    banner::{decode_banner, read_banner_state, BannerOptions},
+    detect,
+    jobs::Job,
+    result::{FilterReason, OpenSubState, PortState, ScanResult, TcpResponse},
+};
+use socket2::{Domain, Protocol, Socket, Type};
+use std::{
+    io::{self, ErrorKind},
+    net::{IpAddr, SocketAddr, TcpStream},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// What the connect engine does with a successful connection once the port is classified.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PostConnect {
+    /// Close right away, after the banner read if one was asked for.
+    #[default]
+    Close,
+    /// Hold the connection open this long before closing, for manual interaction.
+    KeepOpen(Duration),
+    /// Always read a banner before closing, with default limits if none were given.
+    GrabThenClose,
+}
+
+/// Infers the target's TCP response from the outcome of a connect attempt.
+pub fn tcp_response<T>(outcome: &io::Result<T>) -> TcpResponse {
+    match outcome {
+        Ok(_) => TcpResponse::SynAck,
+        Err(e) if e.kind() == ErrorKind::ConnectionRefused => TcpResponse::Rst,
+        Err(e) if matches!(e.kind(), ErrorKind::TimedOut | ErrorKind::WouldBlock) => {
+            TcpResponse::NoResponse
+        }
+        Err(_) => TcpResponse::Unreachable,
+    }
+}
+
+/// Explains a connect that did not complete, e.g. "connection refused after 3ms".
+pub fn failure_detail(error: &io::Error, elapsed: Duration) -> String {
+    let ms = elapsed.as_millis();
+    match error.kind() {
+        ErrorKind::ConnectionRefused => format!("connection refused after {}ms", ms),
+        ErrorKind::TimedOut | ErrorKind::WouldBlock => format!("timed out after {}ms", ms),
+        _ => format!("{} after {}ms", error, ms),
+    }
+}
+
+/// Explains an open port: how long the handshake took and, if it was read, what the banner
+/// read found, e.g. "connected after 2ms, banner read 21 bytes".
+fn open_detail(
+    latency: Duration,
+    banner: Option<&[u8]>,
+    sub_state: Option<OpenSubState>,
+) -> String {
+    let connected = format!("connected after {}ms", latency.as_millis());
+    match sub_state {
+        Some(OpenSubState::Banner) => format!(
+            "{}, banner read {} bytes",
+            connected,
+            banner.map_or(0, <[u8]>::len)
+        ),
+        Some(OpenSubState::NoData) => format!("{}, no banner", connected),
+        Some(OpenSubState::OpenButReset) => format!("{}, reset after accept", connected),
+        None => connected,
+    }
+}
+
+/// Returns whether a connect failed because this host ran out of file descriptors or local
+/// ports, rather than because of anything the target did.
+pub fn is_local_exhaustion(error: &io::Error) -> bool {
+    matches!(
+        error.raw_os_error(),
+        Some(libc::EMFILE | libc::ENFILE | libc::EADDRNOTAVAIL)
+    )
+}
+
+/// Infers why a connect attempt that got no TCP answer failed, if the error says.
+///
+/// The kernel reports ICMP unreachables as `EHOSTUNREACH` or `ENETUNREACH`; Linux folds
+/// "administratively prohibited" into `EHOSTUNREACH`, so only a local firewall rejecting the
+/// SYN (`EACCES`, `EPERM`) shows up as `AdminProhibited`.
+pub fn filter_reason(error: &io::Error) -> Option<FilterReason> {
+    if matches!(error.kind(), ErrorKind::TimedOut | ErrorKind::WouldBlock) {
+        return Some(FilterReason::Timeout);
+    }
+    match error.raw_os_error()? {
+        libc::EHOSTUNREACH | libc::EHOSTDOWN => Some(FilterReason::HostUnreachable),
+        libc::ENETUNREACH | libc::ENETDOWN => Some(FilterReason::NetUnreachable),
+        libc::EACCES | libc::EPERM => Some(FilterReason::AdminProhibited),
+        _ => None,
+    }
+}
+
+/// Opens a TCP connection to `addr`, requesting TCP Fast Open when `fast_open` is set.
+///
+/// Fast Open only saves a round trip when data is sent with the SYN, so it pays off for probes
+/// that write a payload. If the kernel deferred the handshake to the first write (it does once
+/// it holds a cookie for the host), an empty `send(2)` is issued to complete it, so a returned
+/// stream is always a finished handshake and a refused one an error. Platforms without Fast
+/// Open connect normally.
+pub fn connect(addr: &SocketAddr, timeout: Duration, fast_open: bool) -> io::Result<TcpStream> {
+    if !fast_open {
+        return TcpStream::connect_timeout(addr, timeout);
+    }
+    finish_connect(new_socket(addr)?, addr, timeout, fast_open)
+}
+
+fn new_socket(addr: &SocketAddr) -> io::Result<Socket> {
+    Socket::new(
+        Domain::for_address(*addr),
+        Type::STREAM,
+        Some(Protocol::TCP),
+    )
+}
+
+/// Connects an unconnected `socket` to `addr`, as `connect` describes.
+fn finish_connect(
+    socket: Socket,
+    addr: &SocketAddr,
+    timeout: Duration,
+    fast_open: bool,
+) -> io::Result<TcpStream> {
+    let requested = fast_open && request_fast_open(&socket);
+    socket.connect_timeout(&(*addr).into(), timeout)?;
+    if requested {
+        // `write_all(&[])` would return without a syscall; a real empty send(2) is what makes
+        // the kernel send a deferred SYN, and blocks until the handshake finishes or fails.
+        socket.set_write_timeout(Some(timeout))?;
+        socket.send(&[])?;
+        socket.set_write_timeout(None)?;
+    }
+    Ok(socket.into())
+}
+
+/// Asks the kernel to use TCP Fast Open on the next connect; returns whether it agreed.
+#[cfg(target_os = "linux")]
+fn request_fast_open(socket: &Socket) -> bool {
+    use std::os::fd::AsRawFd;
+
+    let enable: libc::c_int = 1;
+    // SAFETY: `enable` is a valid c_int for the length passed.
+    let status = unsafe {
+        libc::setsockopt(
+            socket.as_raw_fd(),
+            libc::IPPROTO_TCP,
+            libc::TCP_FASTOPEN_CONNECT,
+            (&enable as *const libc::c_int).cast(),
+            std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+        )
+    };
+    status == 0
+}
+
+#[cfg(not(target_os = "linux"))]
+fn request_fast_open(_socket: &Socket) -> bool {
+    false
+}
+
+/// How many times a retry rebinds before accepting a previously used source port.
+const MAX_REBINDS: usize = 16;
+
+/// How long `--retry-on-reset` waits before retrying a refused connect.
+const RESET_RETRY_DELAY: Duration = Duration::from_millis(50);
+
+/// Settings for connect probes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConnectOptions {
+    /// Bound on each connect attempt and on the banner read.
+    pub timeout: Duration,
+    /// Read a banner from open ports with these limits.
+    pub banner: Option<BannerOptions>,
+    /// Identify the service of open ports on the banner connection, waiting this long for the
+    /// answer when the probe has to be sent. Only used along with `banner`.
+    pub detect: Option<Duration>,
+    pub fast_open: bool,
+    pub post_connect: PostConnect,
+    /// Extra attempts for ports that did not answer, each from a fresh source port.
+    pub retries: u32,
+    /// Spend `retries` on refused attempts too, not only unanswered ones.
+    pub retry_on_reset: bool,
+    /// Local address to connect from, instead of the one the route picks.
+    pub source: Option<IpAddr>,
+}
+
+impl Default for ConnectOptions {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_millis(1000),
+            banner: None,
+            detect: None,
+            fast_open: false,
+            post_connect: PostConnect::Close,
+            retries: 0,
+            retry_on_reset: false,
+            source: None,
+        }
+    }
+}
+
+/// The last connect attempt made for a probe.
+struct Attempt {
+    outcome: io::Result<TcpStream>,
+    started: Instant,
+    /// Source port of every attempt, when the attempts were bound explicitly.
+    #[cfg_attr(not(test), allow(dead_code))]
+    source_ports: Vec<u16>,
+}
+
+/// Probes a single TCP port with a full connect and classifies the outcome.
+///
+/// A completed handshake is `Open`, an explicit refusal (RST) is `Closed`, and anything else
+/// (timeouts, unreachable networks) is `Filtered`. Filtered ports are retried up to
+/// `options.retries` times. When `options.banner` is set, open ports also get a banner read on
+/// the same connection, bounded by the timeout, and record how the port behaved during it
+/// (see `OpenSubState`). With `options.detect` as well, the service is identified from the
+/// banner, or from the answer to the detection probe sent right after it, still on the same
+/// connection. `options.post_connect` decides how the connection is torn down afterwards.
+pub fn probe(job: &Job, options: &ConnectOptions) -> ScanResult {
+    let addr = job.socket_addr();
+    let Attempt {
+        outcome, started, ..
+    } = connect_with_retries(&addr, options);
+    let response = tcp_response(&outcome);
+    let result = |state| ScanResult {
+        response: Some(response),
+        ..ScanResult::new(job.address, job.port, state)
+    };
+
+    match (outcome, response) {
+        (Ok(mut stream), _) => {
+            let latency = started.elapsed();
+            let limits = match options.post_connect {
+                PostConnect::GrabThenClose => Some(options.banner.unwrap_or_default()),
+                _ => options.banner,
+            };
+            let (raw, sub_state) = match limits {
+                Some(limits) => {
+                    let (raw, state) = read_banner_state(&mut stream, &limits, options.timeout);
+                    (raw, Some(state))
+                }
+                None => (None, None),
+            };
+            let service = match (options.detect, sub_state) {
+                (Some(probe_timeout), Some(OpenSubState::Banner | OpenSubState::NoData)) => {
+                    let _ = stream.set_write_timeout(Some(options.timeout));
+                    detect::identify_after(&mut stream, raw.as_deref(), probe_timeout)
+                }
+                _ => None,
+            };
+            let charset = limits.unwrap_or_default().charset;
+            let banner = raw.as_deref().map(|raw| decode_banner(raw, charset));
+            let result = ScanResult {
+                latency: Some(latency),
+                detail: Some(open_detail(latency, raw.as_deref(), sub_state)),
+                source_addr: stream.local_addr().ok().map(|local| local.ip()),
+                banner_binary: banner.as_ref().is_some_and(|banner| banner.binary),
+                banner: banner.map(|banner| banner.text),
+                sub_state,
+                service: service.map(str::to_string),
+                ..result(PortState::Open)
+            };
+            if let PostConnect::KeepOpen(hold) = options.post_connect {
+                thread::sleep(hold);
+            }
+            result
+        }
+        (Err(e), TcpResponse::Rst) => {
+            let elapsed = started.elapsed();
+            ScanResult {
+                latency: Some(elapsed),
+                detail: Some(failure_detail(&e, elapsed)),
+                ..result(PortState::Closed)
+            }
+        }
+        // The probe never left this host, so nothing is known about the port.
+        (Err(e), _) if is_local_exhaustion(&e) => ScanResult {
+            error: Some(e.to_string()),
+            ..ScanResult::new(job.address, job.port, PortState::Error)
+        },
+        (Err(e), _) => ScanResult {
+            filter_reason: filter_reason(&e),
+            detail: Some(failure_detail(&e, started.elapsed())),
+            ..result(PortState::Filtered)
+        },
+    }
+}
+
+/// Connects, retrying unanswered attempts with a new 4-tuple each time.
+///
+/// A stateful middlebox that dropped one SYN tends to drop every SYN of the same 4-tuple, so
+/// with retries enabled each attempt binds a source port no earlier attempt used. With
+/// `retry_on_reset`, a refused attempt is retried too, after `RESET_RETRY_DELAY`, so a
+/// service that is restarting gets a moment to listen again before its port is called closed.
+fn connect_with_retries(addr: &SocketAddr, options: &ConnectOptions) -> Attempt {
+    if options.retries == 0 {
+        let started = Instant::now();
+        let outcome = match options.source {
+            Some(source) => bound_socket(addr, source).and_then(|socket| {
+                finish_connect(socket, addr, options.timeout, options.fast_open)
+            }),
+            None => connect(addr, options.timeout, options.fast_open),
+        };
+        return Attempt {
+            started,
+            outcome,
+            source_ports: Vec::new(),
+        };
+    }
+
+    let mut source_ports = Vec::new();
+    loop {
+        let started = Instant::now();
+        let outcome = match bind_fresh_port(addr, options.source, &source_ports) {
+            Ok((socket, port)) => {
+                source_ports.push(port);
+                finish_connect(socket, addr, options.timeout, options.fast_open)
+            }
+            Err(e) => Err(e),
+        };
+
+        let response = tcp_response(&outcome);
+        let retry = match response {
+            TcpResponse::NoResponse | TcpResponse::Unreachable => true,
+            TcpResponse::Rst => options.retry_on_reset,
+            TcpResponse::SynAck => false,
+        };
+        if !retry || source_ports.len() > options.retries as usize {
+            return Attempt {
+                outcome,
+                started,
+                source_ports,
+            };
+        }
+        if response == TcpResponse::Rst {
+            thread::sleep(RESET_RETRY_DELAY);
+        }
+    }
+}
+
+/// Opens a socket for `addr`'s family bound to an ephemeral port of `source`.
+fn bound_socket(addr: &SocketAddr, source: IpAddr) -> io::Result<Socket> {
+    let socket = new_socket(addr)?;
+    socket.bind(&SocketAddr::new(source, 0).into())?;
+    Ok(socket)
+}
+
+/// Binds a socket for `addr`'s family to an ephemeral port not in `used`, when the kernel
+/// hands one out within `MAX_REBINDS` tries. The socket is bound to `source` if given, or to
+/// the unspecified address otherwise.
+fn bind_fresh_port(
+    addr: &SocketAddr,
+    source: Option<IpAddr>,
+    used: &[u16],
+) -> io::Result<(Socket, u16)> {
+    let source = source.unwrap_or(match addr {
+        SocketAddr::V4(_) => IpAddr::V4(std::net::Ipv4Addr::UNSPECIFIED),
+        SocketAddr::V6(_) => IpAddr::V6(std::net::Ipv6Addr::UNSPECIFIED),
+    });
+    let mut tries = 0;
+    loop {
+        let socket = bound_socket(addr, source)?;
+        let port = socket
+            .local_addr()?
+            .as_socket()
+            .map(|local| local.port())
+            .unwrap_or_default();
+        tries += 1;
+        if !used.contains(&port) || tries >= MAX_REBINDS {
+            return Ok((socket, port));
+        }
+    }
+}
+
+// Unit tests >------------------------------------------------------------<
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{io::Write, net::TcpListener, thread};
+
+    #[test]
+    fn test_probe_open_and_closed() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let job = Job {
+            address: "127.0.0.1".parse().unwrap(),
+            port,
+            scope_id: 0,
+            protocol: None,
+        };
+
+        let result = probe(&job, &ConnectOptions::default());
+        assert_eq!(result.state, PortState::Open);
+        assert_eq!(result.response, Some(TcpResponse::SynAck));
+        assert_eq!(result.source_addr, Some("127.0.0.1".parse().unwrap()));
+        assert!(result.detail.unwrap().starts_with("connected after "));
+
+        drop(listener);
+        let result = probe(&job, &ConnectOptions::default());
+        assert_eq!(result.state, PortState::Closed);
+        assert_eq!(result.response, Some(TcpResponse::Rst));
+        assert_eq!(result.source_addr, None);
+        let detail = result.detail.unwrap();
+        assert!(
+            detail.starts_with("connection refused after "),
+            "{}",
+            detail
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_fast_open_is_requested() {
+        use std::os::fd::AsRawFd;
+
+        let socket = Socket::new(Domain::IPV4, Type::STREAM, Some(Protocol::TCP)).unwrap();
+        assert!(request_fast_open(&socket));
+
+        let mut value: libc::c_int = 0;
+        let mut len = std::mem::size_of::<libc::c_int>() as libc::socklen_t;
+        // SAFETY: `value` and `len` are valid for getsockopt to write.
+        let status = unsafe {
+            libc::getsockopt(
+                socket.as_raw_fd(),
+                libc::IPPROTO_TCP,
+                libc::TCP_FASTOPEN_CONNECT,
+                (&mut value as *mut libc::c_int).cast(),
+                &mut len,
+            )
+        };
+        assert_eq!(status, 0);
+        assert_eq!(value, 1);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_fast_open_deferred_connect_still_tells_open_from_closed() {
+        use std::os::fd::AsRawFd;
+
+        // Without a cookie the kernel only defers the handshake for a host it has seen Fast
+        // Open from; skipping the cookie makes it defer every time, as for such a host.
+        let deferred_connect = |addr: &SocketAddr| {
+            let socket = new_socket(addr).unwrap();
+            let enable: libc::c_int = 1;
+            // SAFETY: `enable` is a valid c_int for the length passed.
+            let status = unsafe {
+                libc::setsockopt(
+                    socket.as_raw_fd(),
+                    libc::IPPROTO_TCP,
+                    libc::TCP_FASTOPEN_NO_COOKIE,
+                    (&enable as *const libc::c_int).cast(),
+                    std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+                )
+            };
+            assert_eq!(status, 0);
+            finish_connect(socket, addr, Duration::from_millis(1000), true)
+        };
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        assert_eq!(tcp_response(&deferred_connect(&addr)), TcpResponse::SynAck);
+
+        drop(listener);
+        assert_eq!(tcp_response(&deferred_connect(&addr)), TcpResponse::Rst);
+    }
+
+    #[test]
+    fn test_fast_open_probe_classifies_like_connect() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let job = Job {
+            address: "127.0.0.1".parse().unwrap(),
+            port: listener.local_addr().unwrap().port(),
+            scope_id: 0,
+            protocol: None,
+        };
+
+        let result = probe(
+            &job,
+            &ConnectOptions {
+                fast_open: true,
+                ..ConnectOptions::default()
+            },
+        );
+        assert_eq!(result.state, PortState::Open);
+
+        drop(listener);
+        let result = probe(
+            &job,
+            &ConnectOptions {
+                fast_open: true,
+                ..ConnectOptions::default()
+            },
+        );
+        assert_eq!(result.state, PortState::Closed);
+    }
+
+    #[test]
+    fn test_post_connect_modes_and_banner_reads() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let job = Job {
+            address: "127.0.0.1".parse().unwrap(),
+            port: listener.local_addr().unwrap().port(),
+            scope_id: 0,
+            protocol: None,
+        };
+        let server = thread::spawn(move || {
+            for stream in listener.incoming().take(4) {
+                let _ = stream.unwrap().write_all(b"SSH-2.0-OpenSSH_9.6\r\n");
+            }
+        });
+        let banner = "SSH-2.0-OpenSSH_9.6\r\n".to_string();
+        let probe_with = |banner: Option<BannerOptions>, post_connect| {
+            let options = ConnectOptions {
+                banner,
+                post_connect,
+                ..ConnectOptions::default()
+            };
+            probe(&job, &options)
+        };
+
+        let result = probe_with(None, PostConnect::Close);
+        assert_eq!(result.banner, None);
+
+        let result = probe_with(None, PostConnect::GrabThenClose);
+        assert_eq!(result.banner, Some(banner.clone()));
+
+        let hold = Duration::from_millis(50);
+        let start = Instant::now();
+        let result = probe_with(None, PostConnect::KeepOpen(hold));
+        assert!(start.elapsed() >= hold);
+        assert_eq!(result.banner, None);
+
+        let result = probe_with(Some(BannerOptions::default()), PostConnect::KeepOpen(hold));
+        assert_eq!(result.banner, Some(banner));
+        server.join().unwrap();
+    }
+
+    /// Returns a listener whose accept queue is full, so new SYNs to it go unanswered.
+    fn saturated_listener() -> (Socket, SocketAddr, Vec<TcpStream>) {
+        let listener = Socket::new(Domain::IPV4, Type::STREAM, Some(Protocol::TCP)).unwrap();
+        listener
+            .bind(&"127.0.0.1:0".parse::<SocketAddr>().unwrap().into())
+            .unwrap();
+        listener.listen(0).unwrap();
+        let addr = listener.local_addr().unwrap().as_socket().unwrap();
+
+        let mut queued = Vec::new();
+        while let Ok(stream) = TcpStream::connect_timeout(&addr, Duration::from_millis(50)) {
+            queued.push(stream);
+            assert!(queued.len() < 16, "accept queue never filled");
+        }
+        (listener, addr, queued)
+    }
+
+    #[test]
+    fn test_retries_rotate_source_port() {
+        let (_listener, addr, _queued) = saturated_listener();
+        let options = ConnectOptions {
+            timeout: Duration::from_millis(50),
+            retries: 3,
+            ..ConnectOptions::default()
+        };
+
+        let attempt = connect_with_retries(&addr, &options);
+        assert!(attempt.outcome.is_err());
+        let mut ports = attempt.source_ports.clone();
+        assert_eq!(ports.len(), 4);
+        ports.sort_unstable();
+        ports.dedup();
+        assert_eq!(ports.len(), 4, "ports reused: {:?}", attempt.source_ports);
+    }
+
+    #[test]
+    fn test_retries_stop_at_first_answer() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let options = ConnectOptions {
+            retries: 3,
+            ..ConnectOptions::default()
+        };
+
+        let attempt = connect_with_retries(&addr, &options);
+        assert!(attempt.outcome.is_ok());
+        assert_eq!(attempt.source_ports.len(), 1);
+
+        drop(listener);
+        let attempt = connect_with_retries(&addr, &options);
+        assert_eq!(tcp_response(&attempt.outcome), TcpResponse::Rst);
+        assert_eq!(attempt.source_ports.len(), 1);
+    }
+
+    #[test]
+    fn test_retry_on_reset_promotes_to_open() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+
+        let options = ConnectOptions {
+            retries: 20,
+            retry_on_reset: true,
+            ..ConnectOptions::default()
+        };
+        // The service comes back while the probe is still retrying its resets.
+        let restart = thread::spawn(move || {
+            thread::sleep(RESET_RETRY_DELAY * 2);
+            TcpListener::bind(addr).unwrap()
+        });
+        let attempt = connect_with_retries(&addr, &options);
+        let _listener = restart.join().unwrap();
+
+        assert!(attempt.outcome.is_ok());
+        assert!(attempt.source_ports.len() > 1);
+    }
+
+    #[test]
+    fn test_tcp_response_from_connect_outcome() {
+        let outcome = |kind| io::Result::<()>::Err(io::Error::from(kind));
+        assert_eq!(tcp_response(&Ok(())), TcpResponse::SynAck);
+        assert_eq!(
+            tcp_response(&outcome(ErrorKind::ConnectionRefused)),
+            TcpResponse::Rst
+        );
+        assert_eq!(
+            tcp_response(&outcome(ErrorKind::TimedOut)),
+            TcpResponse::NoResponse
+        );
+        assert_eq!(
+            tcp_response(&outcome(ErrorKind::WouldBlock)),
+            TcpResponse::NoResponse
+        );
+        assert_eq!(
+            tcp_response(&outcome(ErrorKind::AddrNotAvailable)),
+            TcpResponse::Unreachable
+        );
+    }
+
+    #[test]
+    fn test_filter_reason_from_connect_error() {
+        let os = io::Error::from_raw_os_error;
+        assert_eq!(
+            filter_reason(&io::Error::from(ErrorKind::TimedOut)),
+            Some(FilterReason::Timeout)
+        );
+        assert_eq!(
+            filter_reason(&os(libc::EHOSTUNREACH)),
+            Some(FilterReason::HostUnreachable)
+        );
+        assert_eq!(
+            filter_reason(&os(libc::ENETUNREACH)),
+            Some(FilterReason::NetUnreachable)
+        );
+        assert_eq!(
+            filter_reason(&os(libc::EACCES)),
+            Some(FilterReason::AdminProhibited)
+        );
+        assert_eq!(filter_reason(&os(libc::EADDRNOTAVAIL)), None);
+    }
+
+    #[test]
+    fn test_failure_detail_tells_refused_from_timed_out() {
+        assert_eq!(
+            failure_detail(
+                &io::Error::from(ErrorKind::ConnectionRefused),
+                Duration::from_millis(3)
+            ),
+            "connection refused after 3ms"
+        );
+        assert_eq!(
+            failure_detail(
+                &io::Error::from(ErrorKind::TimedOut),
+                Duration::from_millis(1000)
+            ),
+            "timed out after 1000ms"
+        );
+        assert_eq!(
+            failure_detail(
+                &io::Error::from(ErrorKind::WouldBlock),
+                Duration::from_millis(250)
+            ),
+            "timed out after 250ms"
+        );
+    }
+
+    #[test]
+    fn test_probe_reads_banner() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            stream.write_all(b"SSH-2.0-OpenSSH_9.6\r\n").unwrap();
+        });
+        let job = Job {
+            address: "127.0.0.1".parse().unwrap(),
+            port,
+            scope_id: 0,
+            protocol: None,
+        };
+
+        let result = probe(
+            &job,
+            &ConnectOptions {
+                banner: Some(BannerOptions::default()),
+                ..ConnectOptions::default()
+            },
+        );
+        server.join().unwrap();
+
+        assert_eq!(result.state, PortState::Open);
+        assert_eq!(result.banner, Some("SSH-2.0-OpenSSH_9.6\r\n".to_string()));
+        assert_eq!(result.sub_state, Some(OpenSubState::Banner));
+    }
+
+    #[test]
+    fn test_probe_flags_reset_after_accept() {
+        use std::sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        };
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        // Every connection is reset, so a stray connect from a parallel test can't take the
+        // accept meant for the probe.
+        let done = Arc::new(AtomicBool::new(false));
+        let server_done = Arc::clone(&done);
+        let server = thread::spawn(move || {
+            for stream in listener.incoming() {
+                if server_done.load(Ordering::Relaxed) {
+                    break;
+                }
+                socket2::SockRef::from(&stream.unwrap())
+                    .set_linger(Some(Duration::ZERO))
+                    .unwrap();
+            }
+        });
+        let job = Job {
+            address: "127.0.0.1".parse().unwrap(),
+            port,
+            scope_id: 0,
+            protocol: None,
+        };
+        let banner_options = ConnectOptions {
+            banner: Some(BannerOptions::default()),
+            ..ConnectOptions::default()
+        };
+
+        let result = probe(&job, &banner_options);
+        assert_eq!(result.state, PortState::Open);
+        assert_eq!(result.sub_state, Some(OpenSubState::OpenButReset));
+        assert!(result.to_string().contains(" open (reset after accept) ("));
+
+        // Without a banner read there is nothing to tell a reset from a quiet service.
+        assert_eq!(probe(&job, &ConnectOptions::default()).sub_state, None);
+
+        done.store(true, Ordering::Relaxed);
+        let _ = TcpStream::connect(("127.0.0.1", port));
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_probe_banner_and_service_share_one_connection() {
+        use std::io::Read;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        listener.set_nonblocking(true).unwrap();
+        // A silent HTTP server that counts every connection it gets.
+        let server = thread::spawn(move || {
+            let mut accepted = 0;
+            let deadline = Instant::now() + Duration::from_millis(500);
+            while Instant::now() < deadline {
+                match listener.accept() {
+                    Ok((mut stream, _)) => {
+                        accepted += 1;
+                        stream.set_nonblocking(false).unwrap();
+                        let _ = stream.read(&mut [0u8; 64]);
+                        let _ = stream.write_all(b"HTTP/1.0 200 OK\r\n\r\n");
+                    }
+                    Err(_) => thread::sleep(Duration::from_millis(5)),
+                }
+            }
+            accepted
+        });
+        let job = Job {
+            address: "127.0.0.1".parse().unwrap(),
+            port,
+            scope_id: 0,
+            protocol: None,
+        };
+        let options = ConnectOptions {
+            timeout: Duration::from_millis(100),
+            banner: Some(BannerOptions::default()),
+            detect: Some(Duration::from_millis(300)),
+            ..ConnectOptions::default()
+        };
+
+        let result = probe(&job, &options);
+        assert_eq!(result.state, PortState::Open);
+        assert_eq!(result.sub_state, Some(OpenSubState::NoData));
+        assert_eq!(result.service.as_deref(), Some("http"));
+        assert_eq!(server.join().unwrap(), 1);
+    }
+}
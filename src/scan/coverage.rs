@@ -0,0 +1,133 @@
+use crate::{
+    args::parser::Protocol,
+    scan::{jobs::Job, result::ScanResult},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashSet},
+    net::IpAddr,
+};
+
+/// The ports of one host that were planned but never probed, e.g. because the scan was
+/// cancelled.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoverageGap {
+    pub address: IpAddr,
+    /// Inclusive `(start, end)` port ranges, sorted and coalesced.
+    pub ports: Vec<(u16, u16)>,
+}
+
+impl CoverageGap {
+    /// Returns the number of unscanned ports on the host.
+    pub fn count(&self) -> usize {
+        self.ports
+            .iter()
+            .map(|(start, end)| (end - start) as usize + 1)
+            .sum()
+    }
+}
+
+/// Returns the planned jobs that have no result, grouped by host and sorted by address.
+///
+/// `planned` is consumed lazily, so only the results and the gaps themselves are held in
+/// memory.
+pub fn not_scanned<I>(planned: I, results: &[ScanResult]) -> Vec<CoverageGap>
+where
+    I: Iterator<Item = Job>,
+{
+    let scanned: HashSet<(IpAddr, u16, Protocol)> = results
+        .iter()
+        .map(|r| (r.address, r.port, r.protocol))
+        .collect();
+
+    let mut missing: BTreeMap<IpAddr, Vec<u16>> = BTreeMap::new();
+    for job in planned
+        .filter(|job| !scanned.contains(&(job.address, job.port, job.protocol.unwrap_or_default())))
+    {
+        missing.entry(job.address).or_default().push(job.port);
+    }
+
+    missing
+        .into_iter()
+        .map(|(address, ports)| CoverageGap {
+            address,
+            ports: coalesce(ports),
+        })
+        .collect()
+}
+
+/// Sorts `ports` and merges consecutive ones into inclusive ranges.
+fn coalesce(mut ports: Vec<u16>) -> Vec<(u16, u16)> {
+    ports.sort_unstable();
+    ports.dedup();
+
+    let mut ranges: Vec<(u16, u16)> = Vec::new();
+    for port in ports {
+        match ranges.last_mut() {
+            Some((_, end)) if *end as u32 + 1 == port as u32 => *end = port,
+            _ => ranges.push((port, port)),
+        }
+    }
+    ranges
+}
+
+// Unit tests >------------------------------------------------------------<
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        args::{parser::PortRange, target::parse_targets},
+        scan::{jobs::jobs, result::PortState},
+    };
+
+    #[test]
+    fn test_not_scanned_after_partial_scan() {
+        let targets = parse_targets("10.0.0.1,10.0.0.2,10.0.0.3".to_string()).unwrap();
+        let ports = PortRange::Range(vec![(20, 25), (80, 80)]);
+        let done = |address: &str, port| {
+            ScanResult::new(address.parse().unwrap(), port, PortState::Closed)
+        };
+        // Host 1 finished except port 22, host 2 got as far as port 21, host 3 never started.
+        let mut results: Vec<ScanResult> = [20, 21, 23, 24, 25, 80]
+            .into_iter()
+            .map(|port| done("10.0.0.1", port))
+            .collect();
+        results.extend([done("10.0.0.2", 20), done("10.0.0.2", 21)]);
+
+        let gaps = not_scanned(jobs(&targets, &ports, &[]), &results);
+        assert_eq!(
+            gaps,
+            vec![
+                CoverageGap {
+                    address: "10.0.0.1".parse().unwrap(),
+                    ports: vec![(22, 22)],
+                },
+                CoverageGap {
+                    address: "10.0.0.2".parse().unwrap(),
+                    ports: vec![(22, 25), (80, 80)],
+                },
+                CoverageGap {
+                    address: "10.0.0.3".parse().unwrap(),
+                    ports: vec![(20, 25), (80, 80)],
+                },
+            ]
+        );
+        assert_eq!(gaps.iter().map(CoverageGap::count).sum::<usize>(), 13);
+        assert_eq!(not_scanned(jobs(&targets, &ports, &[]), &[]).len(), 3);
+    }
+
+    #[test]
+    fn test_not_scanned_complete_scan_has_no_gaps() {
+        let targets = parse_targets("10.0.0.1".to_string()).unwrap();
+        let ports = PortRange::Range(vec![(65534, 65535)]);
+        let results: Vec<ScanResult> = [65535, 65534]
+            .into_iter()
+            .map(|port| ScanResult::new("10.0.0.1".parse().unwrap(), port, PortState::Open))
+            .collect();
+        assert_eq!(not_scanned(jobs(&targets, &ports, &[]), &results), vec![]);
+        assert_eq!(
+            coalesce(vec![65535, 1, 65534, 2, 2]),
+            vec![(1, 2), (65534, 65535)]
+        );
+    }
+}
@@ -0,0 +1,147 @@
+use crate::scan::{
+    banner::{read_banner, BannerOptions},
+    proxy::{connect_chain, Proxy},
+};
+use std::{
+    io::Write,
+    net::{SocketAddr, TcpStream},
+    time::Duration,
+};
+
+/// Sent to services that stay silent after the handshake; most line-based services answer it
+/// with something recognisable, even if only an error.
+const PROBE: &[u8] = b"GET / HTTP/1.0\r\n\r\n";
+
+/// Connects to `addr`, directly or through `proxies`, and identifies the service there.
+///
+/// `connect_timeout` bounds the connection; `probe_timeout` bounds each wait for the service to
+/// speak, first for a greeting and then for the answer to `PROBE`. Returns `None` if the service
+/// could not be reached or was not recognised.
+pub fn detect(
+    addr: SocketAddr,
+    proxies: &[Proxy],
+    connect_timeout: Duration,
+    probe_timeout: Duration,
+) -> Option<String> {
+    let mut stream = if proxies.is_empty() {
+        TcpStream::connect_timeout(&addr, connect_timeout).ok()?
+    } else {
+        connect_chain(proxies, addr, connect_timeout).ok()?
+    };
+    stream.set_write_timeout(Some(connect_timeout)).ok()?;
+    identify(&mut stream, probe_timeout).map(str::to_string)
+}
+
+/// Waits up to `probe_timeout` for a greeting, sends `PROBE` if none came, and names the
+/// service from what it said.
+pub fn identify(stream: &mut TcpStream, probe_timeout: Duration) -> Option<&'static str> {
+    let greeting = read_banner(stream, &BannerOptions::default(), probe_timeout);
+    identify_after(stream, greeting.as_deref(), probe_timeout)
+}
+
+/// Names the service from the `greeting` already read off `stream`, or, if it stayed silent,
+/// from its answer to `PROBE`, waiting up to `probe_timeout` for it.
+pub fn identify_after(
+    stream: &mut TcpStream,
+    greeting: Option<&[u8]>,
+    probe_timeout: Duration,
+) -> Option<&'static str> {
+    match greeting {
+        Some(greeting) => classify(greeting),
+        None => {
+            stream.write_all(PROBE).ok()?;
+            let response = read_banner(stream, &BannerOptions::default(), probe_timeout)?;
+            classify(&response)
+        }
+    }
+}
+
+/// Names the service a greeting or probe response came from.
+fn classify(response: &[u8]) -> Option<&'static str> {
+    let text = String::from_utf8_lossy(response).to_ascii_lowercase();
+    let service = match response {
+        r if r.starts_with(b"SSH-") => "ssh",
+        r if r.starts_with(b"HTTP/") => "http",
+        r if r.starts_with(b"RFB ") => "vnc",
+        r if r.starts_with(b"+OK") => "pop3",
+        r if r.starts_with(b"* OK") => "imap",
+        r if r.starts_with(b"-ERR") || r.starts_with(b"-NOAUTH") => "redis",
+        // IAC WILL/WONT/DO/DONT: a telnet server negotiating options.
+        [0xff, 0xfb..=0xfe, ..] => "telnet",
+        // A TLS alert answering the plaintext probe.
+        [0x15, 0x03, ..] => "tls",
+        r if r.starts_with(b"220") && text.contains("ftp") => "ftp",
+        r if r.starts_with(b"220") => "smtp",
+        _ => return None,
+    };
+    Some(service)
+}
+
+// Unit tests >------------------------------------------------------------<
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{io::Read, net::TcpListener, thread, time::Instant};
+
+    /// Accepts one connection and answers the probe with an HTTP response after `delay`.
+    fn slow_http_server(delay: Duration) -> (SocketAddr, thread::JoinHandle<()>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0u8; 64];
+            let _ = stream.read(&mut request);
+            thread::sleep(delay);
+            let _ = stream.write_all(b"HTTP/1.0 200 OK\r\n\r\n");
+        });
+        (addr, server)
+    }
+
+    #[test]
+    fn test_detect_waits_for_probe_timeout() {
+        let (addr, server) = slow_http_server(Duration::from_millis(150));
+        // Connecting is instant, so only the probe timeout can make room for the slow answer.
+        assert_eq!(
+            detect(
+                addr,
+                &[],
+                Duration::from_millis(50),
+                Duration::from_millis(500)
+            ),
+            Some("http".to_string())
+        );
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_detect_gives_up_after_probe_timeout() {
+        let (addr, server) = slow_http_server(Duration::from_millis(600));
+        let started = Instant::now();
+        assert_eq!(
+            detect(
+                addr,
+                &[],
+                Duration::from_secs(2),
+                Duration::from_millis(100)
+            ),
+            None
+        );
+        // One wait for a greeting and one for the probe's answer, not the connect timeout.
+        assert!(started.elapsed() < Duration::from_millis(500));
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(classify(b"SSH-2.0-OpenSSH_9.6\r\n"), Some("ssh"));
+        assert_eq!(classify(b"220 ProFTPD Server ready\r\n"), Some("ftp"));
+        assert_eq!(classify(b"220 mail.example ESMTP\r\n"), Some("smtp"));
+        assert_eq!(classify(b"HTTP/1.1 400 Bad Request\r\n"), Some("http"));
+        assert_eq!(classify(&[0x15, 0x03, 0x01, 0x00, 0x02]), Some("tls"));
+        assert_eq!(
+            classify(&[0xff, 0xfd, 0x18, 0xff, 0xfd, 0x20]),
+            Some("telnet")
+        );
+        assert_eq!(classify(b"\x00\x01garbage"), None);
+    }
+}
@@ -0,0 +1,355 @@
+use crate::{
+    output::Report,
+    scan::result::{PortState, ScanResult},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    net::{IpAddr, SocketAddr},
+};
+
+/// A port whose state differs between two scans.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PortChange {
+    pub address: IpAddr,
+    pub port: u16,
+    /// The state in the earlier scan; `None` if the port was not scanned then.
+    pub before: Option<PortState>,
+    /// The state in the later scan; `None` if the port was not scanned again.
+    pub after: Option<PortState>,
+}
+
+/// Compares two scans port by port, returning every change sorted by address and port.
+///
+/// Ports scanned in only one of the two count as changed. Duplicate results for a port are
+/// resolved by keeping the last one, so callers wanting `dedup` precedence should dedup first.
+pub fn diff(previous: &[ScanResult], current: &[ScanResult]) -> Vec<PortChange> {
+    let before = states(previous);
+    let after = states(current);
+
+    let keys: BTreeSet<_> = before.keys().chain(after.keys()).copied().collect();
+    keys.into_iter()
+        .filter_map(|(address, port)| {
+            let change = PortChange {
+                address,
+                port,
+                before: before.get(&(address, port)).copied(),
+                after: after.get(&(address, port)).copied(),
+            };
+            (change.before != change.after).then_some(change)
+        })
+        .collect()
+}
+
+/// Returns the `current` results of every host with at least one port change since `previous`.
+///
+/// Hosts are reported whole, so a changed host shows all of its ports, not just the ones that
+/// changed.
+pub fn changed_hosts(previous: &[ScanResult], current: &[ScanResult]) -> Vec<ScanResult> {
+    let changed: BTreeSet<IpAddr> = diff(previous, current)
+        .into_iter()
+        .map(|change| change.address)
+        .collect();
+    current
+        .iter()
+        .filter(|result| changed.contains(&result.address))
+        .cloned()
+        .collect()
+}
+
+/// An earlier scan's results, under the name it is shown as in a `DriftMatrix`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Baseline {
+    /// Usually the file name the report was read from.
+    pub name: String,
+    pub results: Vec<ScanResult>,
+}
+
+/// The state of every (host, port) that was open at some point, across several earlier scans
+/// and the current one.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DriftMatrix {
+    /// Column names, oldest first; the last is the current scan.
+    pub scans: Vec<String>,
+    pub rows: Vec<DriftRow>,
+}
+
+/// One (host, port) of a `DriftMatrix`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DriftRow {
+    pub address: IpAddr,
+    pub port: u16,
+    /// The port's state in each scan, in column order; `None` where it was not scanned.
+    pub states: Vec<Option<PortState>>,
+}
+
+/// Name of the current scan's column in a `DriftMatrix`.
+pub const CURRENT_SCAN: &str = "current";
+
+/// Builds the matrix of `baselines`, oldest first, followed by the `current` results.
+///
+/// Only ports found open by at least one scan get a row, so hosts that never exposed anything
+/// don't drown out the ones that drifted. Rows are sorted by address and port.
+pub fn drift_matrix(baselines: &[Baseline], current: &[ScanResult]) -> DriftMatrix {
+    let scans: Vec<BTreeMap<(IpAddr, u16), PortState>> = baselines
+        .iter()
+        .map(|baseline| states(&baseline.results))
+        .chain([states(current)])
+        .collect();
+    let opened: BTreeSet<(IpAddr, u16)> = scans
+        .iter()
+        .flat_map(|states| states.iter())
+        .filter(|(_, state)| **state == PortState::Open)
+        .map(|(key, _)| *key)
+        .collect();
+
+    DriftMatrix {
+        scans: baselines
+            .iter()
+            .map(|baseline| baseline.name.clone())
+            .chain([CURRENT_SCAN.to_string()])
+            .collect(),
+        rows: opened
+            .into_iter()
+            .map(|(address, port)| DriftRow {
+                address,
+                port,
+                states: scans
+                    .iter()
+                    .map(|states| states.get(&(address, port)).copied())
+                    .collect(),
+            })
+            .collect(),
+    }
+}
+
+impl DriftMatrix {
+    /// Returns the matrix restricted to the rows of `address`.
+    pub fn for_host(&self, address: IpAddr) -> DriftMatrix {
+        DriftMatrix {
+            scans: self.scans.clone(),
+            rows: self
+                .rows
+                .iter()
+                .filter(|row| row.address == address)
+                .cloned()
+                .collect(),
+        }
+    }
+}
+
+impl fmt::Display for DriftMatrix {
+    /// Formats the matrix as a table with a header line, one line per row, padded columns and
+    /// `-` for ports a scan did not cover.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let cells: Vec<Vec<String>> = self
+            .rows
+            .iter()
+            .map(|row| {
+                [SocketAddr::new(row.address, row.port).to_string()]
+                    .into_iter()
+                    .chain(row.states.iter().map(|state| {
+                        state.map_or_else(|| "-".to_string(), |state| state.to_string())
+                    }))
+                    .collect()
+            })
+            .collect();
+        let header: Vec<String> = ["port".to_string()]
+            .into_iter()
+            .chain(self.scans.iter().cloned())
+            .collect();
+        let widths: Vec<usize> = (0..header.len())
+            .map(|column| {
+                [&header]
+                    .into_iter()
+                    .chain(&cells)
+                    .map(|line| line[column].len())
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+
+        for line in [&header].into_iter().chain(&cells) {
+            let padded: Vec<String> = line
+                .iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+                .collect();
+            writeln!(f, "{}", padded.join("  ").trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses a baseline: a report previously written with `--format json`.
+///
+/// # Returns
+///
+/// * `Ok(Vec<ScanResult>)` - If the report parses, returns its results.
+/// * `Err(String)` - If it is not a JSON report, returns an error message.
+pub fn parse_baseline(contents: &str) -> Result<Vec<ScanResult>, String> {
+    serde_json::from_str::<Report>(contents)
+        .map(|report| report.results)
+        .map_err(|e| format!("Invalid baseline report: {}", e))
+}
+
+fn states(results: &[ScanResult]) -> BTreeMap<(IpAddr, u16), PortState> {
+    results
+        .iter()
+        .map(|result| ((result.address, result.port), result.state))
+        .collect()
+}
+
+// Unit tests >------------------------------------------------------------<
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(address: &str, port: u16, state: PortState) -> ScanResult {
+        ScanResult::new(address.parse().unwrap(), port, state)
+    }
+
+    #[test]
+    fn test_diff_reports_changed_added_and_removed_ports() {
+        let previous = vec![
+            result("10.0.0.1", 22, PortState::Open),
+            result("10.0.0.1", 80, PortState::Closed),
+            result("10.0.0.2", 443, PortState::Open),
+        ];
+        let current = vec![
+            result("10.0.0.1", 22, PortState::Open),
+            result("10.0.0.1", 80, PortState::Open),
+            result("10.0.0.3", 8080, PortState::Filtered),
+        ];
+
+        let changes = diff(&previous, &current);
+        let summary: Vec<_> = changes
+            .iter()
+            .map(|c| (c.address.to_string(), c.port, c.before, c.after))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    "10.0.0.1".to_string(),
+                    80,
+                    Some(PortState::Closed),
+                    Some(PortState::Open)
+                ),
+                ("10.0.0.2".to_string(), 443, Some(PortState::Open), None),
+                (
+                    "10.0.0.3".to_string(),
+                    8080,
+                    None,
+                    Some(PortState::Filtered)
+                ),
+            ]
+        );
+        assert!(diff(&current, &current).is_empty());
+    }
+
+    #[test]
+    fn test_parse_baseline_reads_json_report() {
+        let baseline = parse_baseline(
+            r#"{"results":[{"address":"10.0.0.1","port":22,"state":"open","latency_ms":null}],"errors":[]}"#,
+        )
+        .unwrap();
+        assert_eq!(baseline, vec![result("10.0.0.1", 22, PortState::Open)]);
+        assert!(parse_baseline("10.0.0.1:22").is_err());
+    }
+
+    #[test]
+    fn test_changed_hosts_across_two_cycles() {
+        let first = vec![
+            result("10.0.0.1", 22, PortState::Open),
+            result("10.0.0.1", 80, PortState::Closed),
+            result("10.0.0.2", 22, PortState::Open),
+        ];
+        let second = vec![
+            result("10.0.0.1", 22, PortState::Open),
+            result("10.0.0.1", 80, PortState::Open),
+            result("10.0.0.2", 22, PortState::Open),
+        ];
+
+        let emitted = changed_hosts(&first, &second);
+        assert_eq!(
+            emitted,
+            vec![
+                result("10.0.0.1", 22, PortState::Open),
+                result("10.0.0.1", 80, PortState::Open),
+            ]
+        );
+        assert!(changed_hosts(&second, &second).is_empty());
+    }
+
+    #[test]
+    fn test_drift_matrix_across_three_scans() {
+        let baseline = |name: &str, results| Baseline {
+            name: name.to_string(),
+            results,
+        };
+        let baselines = vec![
+            baseline(
+                "monday.json",
+                vec![
+                    result("10.0.0.1", 22, PortState::Open),
+                    result("10.0.0.1", 80, PortState::Closed),
+                    result("10.0.0.2", 443, PortState::Filtered),
+                ],
+            ),
+            baseline(
+                "tuesday.json",
+                vec![
+                    result("10.0.0.1", 22, PortState::Open),
+                    result("10.0.0.1", 80, PortState::Open),
+                    result("10.0.0.2", 443, PortState::Filtered),
+                ],
+            ),
+        ];
+        let current = vec![
+            result("10.0.0.1", 22, PortState::Closed),
+            result("10.0.0.1", 80, PortState::Open),
+            result("10.0.0.2", 443, PortState::Filtered),
+            result("10.0.0.3", 8080, PortState::Open),
+        ];
+
+        let matrix = drift_matrix(&baselines, &current);
+        assert_eq!(matrix.scans, vec!["monday.json", "tuesday.json", "current"]);
+        let rows: Vec<_> = matrix
+            .rows
+            .iter()
+            .map(|row| (row.port, row.states.clone()))
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                (
+                    22,
+                    vec![
+                        Some(PortState::Open),
+                        Some(PortState::Open),
+                        Some(PortState::Closed)
+                    ]
+                ),
+                (
+                    80,
+                    vec![
+                        Some(PortState::Closed),
+                        Some(PortState::Open),
+                        Some(PortState::Open)
+                    ]
+                ),
+                (8080, vec![None, None, Some(PortState::Open)]),
+            ]
+        );
+        assert_eq!(
+            matrix.to_string(),
+            "port           monday.json  tuesday.json  current\n\
+             10.0.0.1:22    open         open          closed\n\
+             10.0.0.1:80    closed       open          open\n\
+             10.0.0.3:8080  -            -             open\n"
+        );
+        assert_eq!(matrix.for_host("10.0.0.3".parse().unwrap()).rows.len(), 1);
+    }
+}
@@ -0,0 +1,99 @@
+use crate::scan::raw::sockaddr;
+use std::{
+    collections::HashMap,
+    ffi::CStr,
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, Mutex, OnceLock},
+};
+
+/// Looks up the PTR name of `address` through the system resolver.
+///
+/// Returns `None` when there is no PTR record or the lookup fails.
+pub fn reverse_lookup(address: IpAddr) -> Option<String> {
+    let (storage, len) = sockaddr(SocketAddr::new(address, 0));
+    let mut host = [0 as libc::c_char; libc::NI_MAXHOST as usize];
+
+    // SAFETY: `storage` is a valid sockaddr of `len` bytes and `host` a writable buffer of the
+    // size passed; no service buffer is requested.
+    let status = unsafe {
+        libc::getnameinfo(
+            (&storage as *const libc::sockaddr_storage).cast(),
+            len,
+            host.as_mut_ptr(),
+            host.len() as libc::socklen_t,
+            std::ptr::null_mut(),
+            0,
+            libc::NI_NAMEREQD,
+        )
+    };
+    if status != 0 {
+        return None;
+    }
+
+    // SAFETY: on success getnameinfo wrote a NUL-terminated string into `host`.
+    let name = unsafe { CStr::from_ptr(host.as_ptr()) };
+    name.to_str().ok().map(str::to_string)
+}
+
+/// A per-host cache in front of a reverse lookup, shared by every worker of a scan.
+///
+/// Each address is looked up at most once. Workers asking for the same address while its
+/// lookup is running wait for that result; lookups of different addresses run in parallel.
+pub struct ReverseDnsCache<F> {
+    lookup: F,
+    names: Mutex<HashMap<IpAddr, Arc<OnceLock<Option<String>>>>>,
+}
+
+impl<F: Fn(IpAddr) -> Option<String>> ReverseDnsCache<F> {
+    pub fn new(lookup: F) -> Self {
+        Self {
+            lookup,
+            names: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the cached name of `address`, looking it up on first use.
+    pub fn get(&self, address: IpAddr) -> Option<String> {
+        let entry = Arc::clone(self.names.lock().unwrap().entry(address).or_default());
+        entry.get_or_init(|| (self.lookup)(address)).clone()
+    }
+}
+
+// Unit tests >------------------------------------------------------------<
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        thread,
+    };
+
+    #[test]
+    fn test_reverse_dns_cache_looks_up_each_host_once() {
+        let lookups = AtomicUsize::new(0);
+        let cache = ReverseDnsCache::new(|address: IpAddr| {
+            lookups.fetch_add(1, Ordering::SeqCst);
+            (address == IpAddr::from([10, 0, 0, 1])).then(|| "gateway.lab".to_string())
+        });
+
+        thread::scope(|s| {
+            for _ in 0..8 {
+                s.spawn(|| {
+                    for i in 0..10u8 {
+                        let host = IpAddr::from([10, 0, 0, 1 + i % 2]);
+                        cache.get(host);
+                    }
+                });
+            }
+        });
+
+        assert_eq!(lookups.load(Ordering::SeqCst), 2);
+        assert_eq!(
+            cache.get(IpAddr::from([10, 0, 0, 1])),
+            Some("gateway.lab".to_string())
+        );
+        // Failed lookups are cached too, and leave the name unset.
+        assert_eq!(cache.get(IpAddr::from([10, 0, 0, 2])), None);
+        assert_eq!(lookups.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,126 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// TCP options raw probes carry when fingerprinting: MSS 1460, two NOPs, then a timestamp
+/// (TSval 1, TSecr 0). Stacks differ in which of them they echo and how they size the window
+/// in answer.
+pub const PROBE_OPTIONS: [u8; 16] = [
+    2, 4, 0x05, 0xb4, // MSS
+    1, 1, // NOP, NOP
+    8, 10, 0, 0, 0, 1, 0, 0, 0, 0, // Timestamps
+];
+
+/// The operating system family a reply's initial TTL suggests.
+///
+/// This is a coarse hint: the initial TTL is a per-stack default that administrators can
+/// change, and middleboxes that answer for a host (firewalls sending RSTs) show their own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OsGuess {
+    /// Initial TTL 64: Linux, macOS, the BSDs and most embedded Unix-likes.
+    Unix,
+    /// Initial TTL 128: Windows.
+    Windows,
+    /// Initial TTL 255: routers, switches and Solaris.
+    NetworkDevice,
+}
+
+impl fmt::Display for OsGuess {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            OsGuess::Unix => "unix",
+            OsGuess::Windows => "windows",
+            OsGuess::NetworkDevice => "network device",
+        })
+    }
+}
+
+/// What a target's reply to a raw probe reveals about its TCP/IP stack.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Fingerprint {
+    /// TTL the reply arrived with; IPv6 raw sockets don't expose the hop limit, so it is only
+    /// known for IPv4.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<u8>,
+    /// Window size the reply advertised.
+    pub window: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub os: Option<OsGuess>,
+}
+
+impl Fingerprint {
+    /// Fingerprints a reply that arrived with `ttl` and advertised `window`.
+    pub fn new(ttl: Option<u8>, window: u16) -> Self {
+        Self {
+            ttl,
+            window,
+            os: ttl.map(guess_os),
+        }
+    }
+}
+
+/// Returns the initial TTL a reply seen with `observed` most likely left its sender with: the
+/// smallest common default (64, 128 or 255) at or above it.
+pub fn initial_ttl(observed: u8) -> u8 {
+    match observed {
+        0..=64 => 64,
+        65..=128 => 128,
+        _ => 255,
+    }
+}
+
+/// Guesses the sender's operating system family from the TTL a reply arrived with.
+pub fn guess_os(observed: u8) -> OsGuess {
+    match initial_ttl(observed) {
+        64 => OsGuess::Unix,
+        128 => OsGuess::Windows,
+        _ => OsGuess::NetworkDevice,
+    }
+}
+
+// Unit tests >------------------------------------------------------------<
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_guess_os_from_ttl_buckets() {
+        for (observed, initial, os) in [
+            (64, 64, OsGuess::Unix),
+            (52, 64, OsGuess::Unix),
+            (1, 64, OsGuess::Unix),
+            (128, 128, OsGuess::Windows),
+            (117, 128, OsGuess::Windows),
+            (65, 128, OsGuess::Windows),
+            (255, 255, OsGuess::NetworkDevice),
+            (240, 255, OsGuess::NetworkDevice),
+            (129, 255, OsGuess::NetworkDevice),
+        ] {
+            assert_eq!(initial_ttl(observed), initial, "ttl {}", observed);
+            assert_eq!(guess_os(observed), os, "ttl {}", observed);
+        }
+    }
+
+    #[test]
+    fn test_fingerprint_without_ttl_has_no_guess() {
+        assert_eq!(
+            Fingerprint::new(Some(57), 29200),
+            Fingerprint {
+                ttl: Some(57),
+                window: 29200,
+                os: Some(OsGuess::Unix),
+            }
+        );
+        assert_eq!(Fingerprint::new(None, 0).os, None);
+    }
+
+    #[test]
+    fn test_probe_options_fill_whole_words() {
+        assert_eq!(PROBE_OPTIONS.len() % 4, 0);
+        assert_eq!(PROBE_OPTIONS[0], 2);
+        assert_eq!(
+            u16::from_be_bytes([PROBE_OPTIONS[2], PROBE_OPTIONS[3]]),
+            1460
+        );
+    }
+}
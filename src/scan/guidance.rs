@@ -0,0 +1,85 @@
+use crate::scan::result::ScanResult;
+use std::collections::BTreeSet;
+
+/// What to do when raw sockets, or anything else a scan needs, are not permitted.
+pub const PRIVILEGES_GUIDANCE: &str =
+    "run with sudo, or grant the binary CAP_NET_RAW (sudo setcap cap_net_raw+ep <path>)";
+
+/// What to do when the scan runs out of file descriptors.
+pub const FD_LIMIT_GUIDANCE: &str = "raise the open file limit (ulimit -n) or lower --concurrency";
+
+/// What to do when every local port is taken by connections still closing.
+pub const LOCAL_PORTS_GUIDANCE: &str = "lower --concurrency, or widen net.ipv4.ip_local_port_range";
+
+/// Returns an actionable hint for the failures scans commonly hit, recognized from the OS
+/// error an error message ends with, or `None` when there is nothing specific to suggest.
+///
+/// Messages come from `std::io::Error`, so they end in e.g. `(os error 24)` whatever the
+/// locale.
+pub fn explain_error(error: &str) -> Option<&'static str> {
+    let os_error = error
+        .rsplit_once("(os error ")
+        .and_then(|(_, code)| code.strip_suffix(')'))
+        .and_then(|code| code.parse::<i32>().ok());
+    match os_error? {
+        libc::EPERM | libc::EACCES => Some(PRIVILEGES_GUIDANCE),
+        libc::EMFILE | libc::ENFILE => Some(FD_LIMIT_GUIDANCE),
+        libc::EADDRNOTAVAIL => Some(LOCAL_PORTS_GUIDANCE),
+        _ => None,
+    }
+}
+
+/// Returns the distinct hints for the errors among `results`, so a scan that failed on many
+/// ports for the same reason gets one line of guidance.
+pub fn result_guidance(results: &[ScanResult]) -> BTreeSet<&'static str> {
+    results
+        .iter()
+        .filter_map(|result| result.error.as_deref())
+        .filter_map(explain_error)
+        .collect()
+}
+
+// Unit tests >------------------------------------------------------------<
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scan::result::PortState;
+    use std::io;
+
+    #[test]
+    fn test_explain_error_maps_common_failures() {
+        let os = |code| io::Error::from_raw_os_error(code).to_string();
+        assert_eq!(
+            explain_error(&format!(
+                "--scan-type fin needs raw socket privileges: {}",
+                os(libc::EPERM)
+            )),
+            Some(PRIVILEGES_GUIDANCE)
+        );
+        assert_eq!(explain_error(&os(libc::EMFILE)), Some(FD_LIMIT_GUIDANCE));
+        assert_eq!(explain_error(&os(libc::ENFILE)), Some(FD_LIMIT_GUIDANCE));
+        assert_eq!(
+            explain_error(&os(libc::EADDRNOTAVAIL)),
+            Some(LOCAL_PORTS_GUIDANCE)
+        );
+        assert_eq!(explain_error(&os(libc::ECONNRESET)), None);
+        assert_eq!(explain_error("Interface lo has no IPv6 address"), None);
+    }
+
+    #[test]
+    fn test_result_guidance_is_given_once() {
+        let failed = |port| ScanResult {
+            error: Some(io::Error::from_raw_os_error(libc::EMFILE).to_string()),
+            ..ScanResult::new("10.0.0.1".parse().unwrap(), port, PortState::Error)
+        };
+        let results = vec![
+            failed(22),
+            failed(80),
+            ScanResult::new("10.0.0.1".parse().unwrap(), 443, PortState::Open),
+        ];
+        assert_eq!(
+            result_guidance(&results).into_iter().collect::<Vec<_>>(),
+            vec![FD_LIMIT_GUIDANCE]
+        );
+    }
+}
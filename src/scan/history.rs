@@ -0,0 +1,98 @@
+use crate::output::Report;
+use std::{
+    collections::{BTreeSet, HashMap},
+    net::IpAddr,
+    time::{Duration, SystemTime},
+};
+
+/// Returns the hosts that a scan history shows were scanned within `within` of `now`.
+///
+/// The history is one or more JSON reports, back to back, as `--watch --format json` writes
+/// them; each report's `started_at` dates every host it has a result for, and a host's latest
+/// report counts. Hosts the history never mentions are not returned, so they are always
+/// scanned.
+///
+/// # Returns
+///
+/// * `Ok(BTreeSet<IpAddr>)` - If every report parses and is dated, returns the recent hosts.
+/// * `Err(String)` - If a report is malformed or has no `started_at`, returns an error message.
+pub fn recently_scanned(
+    contents: &str,
+    within: Duration,
+    now: SystemTime,
+) -> Result<BTreeSet<IpAddr>, String> {
+    let mut last_scanned: HashMap<IpAddr, SystemTime> = HashMap::new();
+
+    for report in serde_json::Deserializer::from_str(contents).into_iter::<Report>() {
+        let report = report.map_err(|e| format!("Invalid scan history: {}", e))?;
+        let started_at = report
+            .started_at
+            .as_deref()
+            .ok_or("Invalid scan history: a report has no started_at time")?;
+        let started_at = humantime::parse_rfc3339_weak(started_at)
+            .map_err(|e| format!("Invalid scan history time {}: {}", started_at, e))?;
+        for result in &report.results {
+            let last = last_scanned.entry(result.address).or_insert(started_at);
+            *last = (*last).max(started_at);
+        }
+    }
+
+    Ok(last_scanned
+        .into_iter()
+        .filter(|(_, at)| now.duration_since(*at).unwrap_or_default() < within)
+        .map(|(address, _)| address)
+        .collect())
+}
+
+// Unit tests >------------------------------------------------------------<
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scan::result::{PortState, ScanResult};
+
+    fn report(started_at: &str, hosts: &[&str]) -> String {
+        let report = Report {
+            started_at: Some(started_at.to_string()),
+            results: hosts
+                .iter()
+                .map(|host| ScanResult::new(host.parse().unwrap(), 22, PortState::Open))
+                .collect(),
+            ..Report::default()
+        };
+        serde_json::to_string_pretty(&report).unwrap()
+    }
+
+    #[test]
+    fn test_recently_scanned_mixes_recent_and_stale_hosts() {
+        let now = humantime::parse_rfc3339("2024-05-01T12:00:00Z").unwrap();
+        let history = [
+            report("2024-05-01T08:00:00Z", &["10.0.0.1", "10.0.0.2"]),
+            report("2024-05-01T11:30:00Z", &["10.0.0.2", "10.0.0.3"]),
+            report("2024-04-30T12:00:00Z", &["10.0.0.4"]),
+        ]
+        .join("\n");
+
+        let recent = recently_scanned(&history, Duration::from_secs(3600), now).unwrap();
+        let expected: BTreeSet<IpAddr> = ["10.0.0.2", "10.0.0.3"]
+            .iter()
+            .map(|host| host.parse().unwrap())
+            .collect();
+        assert_eq!(recent, expected);
+
+        let wider = recently_scanned(&history, Duration::from_secs(6 * 3600), now).unwrap();
+        assert_eq!(wider.len(), 3);
+        assert!(!wider.contains(&"10.0.0.4".parse::<IpAddr>().unwrap()));
+    }
+
+    #[test]
+    fn test_recently_scanned_requires_dated_reports() {
+        let undated = serde_json::to_string(&Report::default()).unwrap();
+        let now = SystemTime::now();
+        assert!(recently_scanned(&undated, Duration::from_secs(60), now).is_err());
+        assert!(recently_scanned("{", Duration::from_secs(60), now).is_err());
+        assert_eq!(
+            recently_scanned("", Duration::from_secs(60), now),
+            Ok(BTreeSet::new())
+        );
+    }
+}
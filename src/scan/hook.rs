@@ -0,0 +1,190 @@
+use crate::{
+    output::template::{parse_template, Template},
+    scan::result::{PortState, ScanResult},
+};
+use std::{
+    fmt,
+    process::{Child, Command, Stdio},
+    sync::{
+        mpsc::{self, Sender},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+};
+
+/// How many hook commands may run at once; further open ports wait for one to exit.
+pub const MAX_RUNNING_HOOKS: usize = 8;
+
+/// A parsed `--on-open` command: a program and its arguments, each a line template.
+///
+/// The command is split on whitespace once, when it is parsed, and run directly rather than
+/// through a shell, so a substituted value always stays a single argument: a banner containing
+/// `; rm -rf ~` reaches the program as text. The program itself still runs with the scanner's
+/// privileges, and a script that passes its arguments on to `sh -c` or `eval` reopens the
+/// injection the template avoids.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HookCommand {
+    words: Vec<Template>,
+}
+
+impl HookCommand {
+    /// Returns the program and arguments to run for `result`.
+    pub fn argv(&self, result: &ScanResult) -> Vec<String> {
+        self.words.iter().map(|word| word.render(result)).collect()
+    }
+}
+
+/// Parses an `--on-open` command such as `"notify.sh {addr} {port}"`.
+///
+/// Every whitespace-separated word is a line template with the fields of `--template`.
+///
+/// # Returns
+///
+/// * `Ok(HookCommand)` - If the command names a program and every placeholder is known.
+/// * `Err(String)` - If the command is empty or a word is not a valid template.
+pub fn parse_hook(command: &str) -> Result<HookCommand, String> {
+    let words = command
+        .split_whitespace()
+        .map(parse_template)
+        .collect::<Result<Vec<Template>, String>>()?;
+    if words.is_empty() {
+        return Err("Invalid --on-open command: no program given".to_string());
+    }
+    Ok(HookCommand { words })
+}
+
+/// Runs the hook command for every open result on a dedicated thread.
+///
+/// Scan workers only hand the arguments over, so a slow command never holds up the scan. At
+/// most `MAX_RUNNING_HOOKS` commands run at once; dropping the hook waits for the queued and
+/// running commands to finish.
+pub struct Hook {
+    command: HookCommand,
+    queue: Option<Sender<Vec<String>>>,
+    runner: Option<JoinHandle<()>>,
+}
+
+impl Hook {
+    /// Starts the thread that runs `command`.
+    pub fn start(command: HookCommand) -> Self {
+        let (queue, commands) = mpsc::channel::<Vec<String>>();
+        let runner = thread::spawn(move || {
+            let mut running: Vec<Child> = Vec::new();
+            for argv in commands {
+                running.retain_mut(|child| !matches!(child.try_wait(), Ok(Some(_))));
+                if running.len() >= MAX_RUNNING_HOOKS {
+                    let _ = running.remove(0).wait();
+                }
+                let spawned = Command::new(&argv[0])
+                    .args(&argv[1..])
+                    .stdin(Stdio::null())
+                    .spawn();
+                match spawned {
+                    Ok(child) => running.push(child),
+                    Err(e) => eprintln!("Failed to run --on-open command {}: {}", argv[0], e),
+                }
+            }
+            for mut child in running {
+                let _ = child.wait();
+            }
+        });
+        Self {
+            command,
+            queue: Some(queue),
+            runner: Some(runner),
+        }
+    }
+
+    /// Queues the command for `result` if its port is open.
+    pub fn notify(&self, result: &ScanResult) {
+        if result.state != PortState::Open {
+            return;
+        }
+        if let Some(queue) = &self.queue {
+            let _ = queue.send(self.command.argv(result));
+        }
+    }
+}
+
+impl Drop for Hook {
+    fn drop(&mut self) {
+        drop(self.queue.take());
+        if let Some(runner) = self.runner.take() {
+            let _ = runner.join();
+        }
+    }
+}
+
+/// Post-processes every result of a scan before it reaches the output, e.g. to tag results
+/// with what an inventory knows about the host.
+///
+/// Library users set one with `ArgsBuilder::result_hook`. It runs on the scan's worker
+/// threads, after the scanner's own annotations (hostname, severity) and before `--on-open`.
+/// Any `Fn(&mut ScanResult)` closure is a hook.
+pub trait ResultHook: Send + Sync {
+    fn process(&self, result: &mut ScanResult);
+}
+
+impl<F: Fn(&mut ScanResult) + Send + Sync> ResultHook for F {
+    fn process(&self, result: &mut ScanResult) {
+        self(result)
+    }
+}
+
+/// A `ResultHook` shared by every worker of a scan; clones are equal only to each other.
+#[derive(Clone)]
+pub struct SharedResultHook(Arc<dyn ResultHook>);
+
+impl SharedResultHook {
+    pub fn new(hook: impl ResultHook + 'static) -> Self {
+        Self(Arc::new(hook))
+    }
+
+    pub fn process(&self, result: &mut ScanResult) {
+        self.0.process(result)
+    }
+}
+
+impl fmt::Debug for SharedResultHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SharedResultHook")
+    }
+}
+
+impl PartialEq for SharedResultHook {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+// Unit tests >------------------------------------------------------------<
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hook_argv_substitutes_fields() {
+        let hook = parse_hook("notify.sh  --host {addr} {port}/{service}").unwrap();
+        let result = ScanResult::new("10.0.0.1".parse().unwrap(), 22, PortState::Open);
+        assert_eq!(
+            hook.argv(&result),
+            vec!["notify.sh", "--host", "10.0.0.1", "22/ssh"]
+        );
+    }
+
+    #[test]
+    fn test_hook_argv_keeps_values_single_arguments() {
+        let hook = parse_hook("notify.sh {banner}").unwrap();
+        let result = ScanResult {
+            banner: Some("SSH-2.0 x; rm -rf ~".to_string()),
+            ..ScanResult::new("10.0.0.1".parse().unwrap(), 22, PortState::Open)
+        };
+        assert_eq!(hook.argv(&result), vec!["notify.sh", "SSH-2.0 x; rm -rf ~"]);
+    }
+
+    #[test]
+    fn test_parse_hook_rejects_invalid_commands() {
+        assert!(parse_hook("   ").is_err());
+        assert!(parse_hook("notify.sh {nope}").is_err());
+    }
+}
@@ -0,0 +1,423 @@
+use crate::{
+    args::{
+        interfaces::is_link_local,
+        parser::{PortProtocols, PortRange, Protocol},
+        target::Target,
+    },
+    scan::{
+        random::Permutation,
+        result::{PortState, ScanResult},
+    },
+};
+use std::{
+    iter,
+    net::{IpAddr, SocketAddr, SocketAddrV6},
+    sync::Arc,
+};
+
+/// Represents a single unit of scan work: one port on one host, over one protocol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Job {
+    pub address: IpAddr,
+    pub port: u16,
+    /// Interface index an IPv6 link-local address is scoped to; 0 when unscoped.
+    pub scope_id: u32,
+    /// Protocol the port is probed over; `None` until `per_protocol` picks it from the port's
+    /// `/tcp` or `/udp` tag and `--scan-type`.
+    pub protocol: Option<Protocol>,
+}
+
+impl Job {
+    /// Returns the address to connect to, carrying the scope of link-local IPv6 jobs.
+    pub fn socket_addr(&self) -> SocketAddr {
+        match self.address {
+            IpAddr::V6(address) => SocketAddrV6::new(address, self.port, 0, self.scope_id).into(),
+            IpAddr::V4(_) => SocketAddr::new(self.address, self.port),
+        }
+    }
+}
+
+impl From<SocketAddr> for Job {
+    /// Converts a socket address into a job, keeping the scope of an IPv6 address.
+    fn from(addr: SocketAddr) -> Self {
+        let scope_id = match addr {
+            SocketAddr::V6(addr) => addr.scope_id(),
+            SocketAddr::V4(_) => 0,
+        };
+        Self {
+            address: addr.ip(),
+            port: addr.port(),
+            scope_id,
+            protocol: None,
+        }
+    }
+}
+
+/// Generates the job stream for the given targets and ports, host by host.
+///
+/// Jobs are produced lazily so that large CIDR blocks never need to be materialized.
+/// For each host, the `priority` ports that are part of `ports` come first (in the given order),
+/// followed by the remaining ports.
+pub fn jobs<'a>(
+    targets: &'a [Target],
+    ports: &'a PortRange,
+    priority: &[u16],
+) -> impl Iterator<Item = Job> + Send + 'a {
+    let mut front = Vec::new();
+    for &port in priority {
+        if ports.contains(port) && !front.contains(&port) {
+            front.push(port);
+        }
+    }
+    let front: Arc<[u16]> = front.into();
+
+    targets
+        .iter()
+        .flat_map(|target| target.hosts())
+        .flat_map(move |address| {
+            let head = Arc::clone(&front);
+            let rest = Arc::clone(&front);
+            (0..head.len())
+                .map(move |i| head[i])
+                .chain(ports.iter().filter(move |port| !rest.contains(port)))
+                .map(move |port| Job {
+                    address,
+                    port,
+                    scope_id: 0,
+                    protocol: None,
+                })
+        })
+}
+
+/// Selects one of `count` equal slices of a scan's jobs, for splitting it across machines.
+///
+/// Slices are taken by job index modulo `count`, so every shard of the same plan and job order
+/// gets a disjoint share, and together they cover every job exactly once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Shard {
+    /// 0-based index of this shard, below `count`.
+    pub index: u64,
+    pub count: u64,
+}
+
+impl Shard {
+    /// Returns how many of `total` jobs fall in this shard.
+    pub fn share(&self, total: u128) -> u128 {
+        let (whole, rest) = (total / self.count as u128, total % self.count as u128);
+        whole + u128::from((self.index as u128) < rest)
+    }
+}
+
+/// Parses a `--shard` value: `i/n`, for the i-th (1-based) of n shards.
+pub fn parse_shard(value: &str) -> Result<Shard, String> {
+    let invalid = || format!("Invalid shard: {} (expected i/n, e.g. 1/4)", value);
+    let (index, count) = value.split_once('/').ok_or_else(invalid)?;
+    let (index, count) = (
+        index.trim().parse::<u64>().map_err(|_| invalid())?,
+        count.trim().parse::<u64>().map_err(|_| invalid())?,
+    );
+    if index == 0 || index > count {
+        return Err(format!(
+            "Invalid shard: {} (i must be between 1 and n)",
+            value
+        ));
+    }
+    Ok(Shard {
+        index: index - 1,
+        count,
+    })
+}
+
+/// Keeps only the jobs of `shard`: those whose position in `jobs` is its index modulo its count.
+pub fn shard<I>(jobs: I, shard: Shard) -> impl Iterator<Item = Job> + Send
+where
+    I: Iterator<Item = Job> + Send,
+{
+    jobs.zip((0..shard.count).cycle())
+        .filter(move |(_, slot)| *slot == shard.index)
+        .map(|(job, _)| job)
+}
+
+/// Gives every job without a protocol one copy per protocol its port is tagged with in
+/// `port_protocols`, or `default` if the port is untagged.
+///
+/// A port tagged both `/tcp` and `/udp` is probed over each. Jobs that already have a protocol,
+/// like those of a `--plan`, pass through unchanged.
+pub fn per_protocol<'a, I>(
+    jobs: I,
+    port_protocols: &'a PortProtocols,
+    default: Protocol,
+) -> impl Iterator<Item = Job> + Send + 'a
+where
+    I: Iterator<Item = Job> + Send + 'a,
+{
+    jobs.flat_map(move |job| {
+        let mut protocols: Vec<Protocol> = match job.protocol {
+            Some(protocol) => vec![protocol],
+            None => port_protocols
+                .iter()
+                .filter(|((start, end), _)| (*start..=*end).contains(&job.port))
+                .map(|(_, protocol)| *protocol)
+                .collect(),
+        };
+        if protocols.is_empty() {
+            protocols.push(default);
+        }
+        protocols.sort_unstable();
+        protocols.dedup();
+        protocols.into_iter().map(move |protocol| Job {
+            protocol: Some(protocol),
+            ..job
+        })
+    })
+}
+
+/// Repeats every unscoped IPv6 link-local job once per interface index in `scopes`.
+///
+/// The same link-local address can be a different host on every link, so each copy is scoped
+/// to one interface. Other jobs pass through unchanged; with no `scopes`, link-local jobs are
+/// dropped, since nothing could reach them.
+pub fn per_interface<'a, I>(jobs: I, scopes: Arc<[u32]>) -> impl Iterator<Item = Job> + Send + 'a
+where
+    I: Iterator<Item = Job> + Send + 'a,
+{
+    jobs.flat_map(move |job| {
+        let scoped = job.scope_id == 0 && is_link_local(job.address);
+        let scopes = Arc::clone(&scopes);
+        let copies = if scoped { scopes.len() } else { 0 };
+        iter::once(job)
+            .filter(move |_| !scoped)
+            .chain((0..copies).map(move |i| Job {
+                scope_id: scopes[i],
+                ..job
+            }))
+    })
+}
+
+/// Generates jobs only for the ports a baseline scan found open, per host.
+///
+/// A baseline result is scheduled when its address is inside one of `targets` and its port is
+/// part of `ports`; everything else the targets and ports describe is skipped. Jobs come out
+/// sorted by address and port, once each.
+pub fn baseline_jobs(targets: &[Target], ports: &PortRange, baseline: &[ScanResult]) -> Vec<Job> {
+    let mut jobs: Vec<Job> = baseline
+        .iter()
+        .filter(|result| result.state == PortState::Open && ports.contains(result.port))
+        .filter(|result| {
+            let host = Target::Single(result.address);
+            targets.iter().any(|target| host.is_within(target))
+        })
+        .map(|result| Job {
+            address: result.address,
+            port: result.port,
+            scope_id: 0,
+            protocol: None,
+        })
+        .collect();
+    jobs.sort_by_key(|job| (job.address, job.port));
+    jobs.dedup();
+    jobs
+}
+
+/// Generates every (host, port) job exactly once, in a pseudo-random order fixed by `seed`.
+///
+/// Hosts and ports are interleaved across the whole scan rather than shuffled per host, so no
+/// single host sees a burst of consecutive probes. Like `jobs`, nothing is materialized.
+pub fn shuffled_jobs<'a>(
+    targets: &'a [Target],
+    ports: &'a PortRange,
+    seed: u64,
+) -> impl Iterator<Item = Job> + Send + 'a {
+    let port_count = ports.count() as u128;
+    let host_offsets: Vec<u128> = targets
+        .iter()
+        .scan(0u128, |offset, target| {
+            let start = *offset;
+            *offset = offset.saturating_add(target.count());
+            Some(start)
+        })
+        .collect();
+    let hosts = targets
+        .iter()
+        .fold(0u128, |hosts, target| hosts.saturating_add(target.count()));
+    let total = hosts.saturating_mul(port_count);
+    let permutation = Permutation::new(total, seed);
+
+    (0..total).map(move |i| {
+        let index = permutation.apply(i);
+        let (host, port) = (index / port_count, index % port_count);
+        let target = host_offsets.partition_point(|&offset| offset <= host) - 1;
+        Job {
+            address: targets[target]
+                .nth_host(host - host_offsets[target])
+                .expect("host index is within the target"),
+            port: ports
+                .nth(port as usize)
+                .expect("port index is within the range"),
+            scope_id: 0,
+            protocol: None,
+        }
+    })
+}
+
+// Unit tests >------------------------------------------------------------<
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::args::target::parse_targets;
+
+    #[test]
+    fn test_baseline_jobs_schedules_only_baseline_open_ports() {
+        let targets = parse_targets("10.0.0.0/30".to_string()).unwrap();
+        let ports = PortRange::Range(vec![(1, 1024)]);
+        let baseline = vec![
+            ScanResult::new("10.0.0.2".parse().unwrap(), 443, PortState::Open),
+            ScanResult::new("10.0.0.1".parse().unwrap(), 22, PortState::Open),
+            ScanResult::new("10.0.0.1".parse().unwrap(), 22, PortState::Open),
+            ScanResult::new("10.0.0.1".parse().unwrap(), 80, PortState::Closed),
+            ScanResult::new("10.0.0.1".parse().unwrap(), 8080, PortState::Open),
+            ScanResult::new("10.0.1.1".parse().unwrap(), 22, PortState::Open),
+        ];
+
+        let jobs: Vec<(String, u16)> = baseline_jobs(&targets, &ports, &baseline)
+            .into_iter()
+            .map(|job| (job.address.to_string(), job.port))
+            .collect();
+        assert_eq!(
+            jobs,
+            vec![("10.0.0.1".to_string(), 22), ("10.0.0.2".to_string(), 443)]
+        );
+    }
+
+    #[test]
+    fn test_jobs_host_major_order() {
+        let targets = parse_targets("10.0.0.0/31".to_string()).unwrap();
+        let ports = PortRange::Range(vec![(22, 23)]);
+        let jobs: Vec<(String, u16)> = jobs(&targets, &ports, &[])
+            .map(|job| (job.address.to_string(), job.port))
+            .collect();
+
+        assert_eq!(
+            jobs,
+            vec![
+                ("10.0.0.0".to_string(), 22),
+                ("10.0.0.0".to_string(), 23),
+                ("10.0.0.1".to_string(), 22),
+                ("10.0.0.1".to_string(), 23),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_jobs_priority_ports_first_per_host() {
+        let targets = parse_targets("10.0.0.0/31".to_string()).unwrap();
+        let ports = PortRange::Range(vec![(20, 25), (440, 445)]);
+        let jobs: Vec<(String, u16)> = jobs(&targets, &ports, &[443, 22, 3389, 22])
+            .map(|job| (job.address.to_string(), job.port))
+            .collect();
+
+        let expected_ports = [443, 22, 20, 21, 23, 24, 25, 440, 441, 442, 444, 445];
+        let expected: Vec<(String, u16)> = ["10.0.0.0", "10.0.0.1"]
+            .iter()
+            .flat_map(|host| expected_ports.iter().map(|port| (host.to_string(), *port)))
+            .collect();
+        assert_eq!(jobs, expected);
+    }
+
+    #[test]
+    fn test_shuffled_jobs_same_seed_same_order() {
+        let targets = parse_targets("10.0.0.0/30,10.0.1.5".to_string()).unwrap();
+        let ports = PortRange::Range(vec![(20, 25), (80, 80)]);
+
+        let first: Vec<Job> = shuffled_jobs(&targets, &ports, 1234).collect();
+        let second: Vec<Job> = shuffled_jobs(&targets, &ports, 1234).collect();
+        let other: Vec<Job> = shuffled_jobs(&targets, &ports, 4321).collect();
+        assert_eq!(first, second);
+        assert_ne!(first, other);
+
+        let mut sorted: Vec<(IpAddr, u16)> = first.iter().map(|j| (j.address, j.port)).collect();
+        sorted.sort_unstable();
+        let mut expected: Vec<(IpAddr, u16)> = jobs(&targets, &ports, &[])
+            .map(|j| (j.address, j.port))
+            .collect();
+        expected.sort_unstable();
+        assert_eq!(sorted, expected);
+    }
+
+    #[test]
+    fn test_per_interface_scopes_link_local_jobs() {
+        let targets = parse_targets("fe80::1,10.0.0.1".to_string()).unwrap();
+        let ports = PortRange::Range(vec![(22, 22)]);
+        let jobs: Vec<(String, u32)> =
+            per_interface(jobs(&targets, &ports, &[]), Arc::from([2, 5]))
+                .map(|job| (job.socket_addr().to_string(), job.scope_id))
+                .collect();
+
+        assert_eq!(
+            jobs,
+            vec![
+                ("[fe80::1%2]:22".to_string(), 2),
+                ("[fe80::1%5]:22".to_string(), 5),
+                ("10.0.0.1:22".to_string(), 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_per_interface_without_interfaces_drops_link_local_jobs() {
+        let targets = parse_targets("fe80::1,fd00::1".to_string()).unwrap();
+        let ports = PortRange::Range(vec![(22, 22)]);
+        let jobs: Vec<Job> = per_interface(jobs(&targets, &ports, &[]), Arc::from([])).collect();
+        assert_eq!(
+            jobs,
+            vec![Job {
+                address: "fd00::1".parse().unwrap(),
+                port: 22,
+                scope_id: 0,
+                protocol: None,
+            }]
+        );
+
+        let scoped = Job {
+            address: "fe80::1".parse().unwrap(),
+            port: 22,
+            scope_id: 7,
+            protocol: None,
+        };
+        let kept: Vec<Job> = per_interface(iter::once(scoped), Arc::from([2])).collect();
+        assert_eq!(kept, vec![scoped]);
+    }
+
+    #[test]
+    fn test_shards_partition_the_job_set() {
+        let targets = parse_targets("10.0.0.0/29,fd00::1".to_string()).unwrap();
+        let ports = PortRange::Range(vec![(20, 25), (80, 80)]);
+        let all: Vec<Job> = jobs(&targets, &ports, &[]).collect();
+        assert_eq!(all.len(), 9 * 7);
+
+        for count in [1, 2, 4, 10, 100] {
+            let mut covered = Vec::new();
+            for index in 0..count {
+                let slice = Shard { index, count };
+                let jobs: Vec<Job> = shard(all.iter().copied(), slice).collect();
+                assert_eq!(jobs.len() as u128, slice.share(all.len() as u128));
+                covered.extend(jobs);
+            }
+            // No job is in two shards and none is missing.
+            covered.sort_by_key(|job| (job.address, job.port));
+            let mut expected = all.clone();
+            expected.sort_by_key(|job| (job.address, job.port));
+            assert_eq!(covered, expected);
+        }
+    }
+
+    #[test]
+    fn test_parse_shard() {
+        assert_eq!(parse_shard("1/4"), Ok(Shard { index: 0, count: 4 }));
+        assert_eq!(parse_shard("4/4"), Ok(Shard { index: 3, count: 4 }));
+        assert!(parse_shard("0/4").is_err());
+        assert!(parse_shard("5/4").is_err());
+        assert!(parse_shard("2").is_err());
+        assert!(parse_shard("a/b").is_err());
+    }
+}
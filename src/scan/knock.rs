@@ -0,0 +1,78 @@
+use crate::scan::{jobs::Job, result::ScanResult};
+use std::{
+    net::{SocketAddr, TcpStream},
+    time::Duration,
+};
+
+/// Upper bound on how long a single knock waits; the SYN is what matters, not the reply.
+pub const KNOCK_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Touches a single knock port, ignoring the outcome.
+pub fn knock_port(addr: SocketAddr, timeout: Duration) {
+    let _ = TcpStream::connect_timeout(&addr, timeout.min(KNOCK_TIMEOUT));
+}
+
+/// Knocks every port of `sequence` on `address` in order, then runs `probe` for the job.
+///
+/// `knock` is called once per knock port; the scheduler passes `knock_port`.
+pub fn probe_after_knock<K, P>(job: &Job, sequence: &[u16], mut knock: K, probe: P) -> ScanResult
+where
+    K: FnMut(SocketAddr),
+    P: FnOnce(&Job) -> ScanResult,
+{
+    for &port in sequence {
+        knock(Job { port, ..*job }.socket_addr());
+    }
+    probe(job)
+}
+
+// Unit tests >------------------------------------------------------------<
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scan::result::PortState;
+    use std::cell::RefCell;
+
+    #[test]
+    fn test_probe_after_knock_order() {
+        let events = RefCell::new(Vec::new());
+        let job = Job {
+            address: "127.0.0.1".parse().unwrap(),
+            port: 22,
+            scope_id: 0,
+            protocol: None,
+        };
+
+        let result = probe_after_knock(
+            &job,
+            &[9000, 7000, 8000],
+            |addr| events.borrow_mut().push(addr.port()),
+            |job| {
+                events.borrow_mut().push(job.port);
+                ScanResult::new(job.address, job.port, PortState::Open)
+            },
+        );
+
+        assert_eq!(result.state, PortState::Open);
+        assert_eq!(events.into_inner(), vec![9000, 7000, 8000, 22]);
+    }
+
+    #[test]
+    fn test_probe_after_empty_knock() {
+        let mut knocks = 0;
+        let job = Job {
+            address: "127.0.0.1".parse().unwrap(),
+            port: 22,
+            scope_id: 0,
+            protocol: None,
+        };
+
+        probe_after_knock(
+            &job,
+            &[],
+            |_| knocks += 1,
+            |job| ScanResult::new(job.address, job.port, PortState::Closed),
+        );
+        assert_eq!(knocks, 0);
+    }
+}
@@ -0,0 +1,1170 @@
+pub mod aggregate;
+pub mod banner;
+pub mod connect;
+pub mod coverage;
+pub mod detect;
+pub mod diff;
+pub mod dns;
+pub mod fingerprint;
+pub mod guidance;
+pub mod history;
+pub mod hook;
+pub mod jobs;
+pub mod knock;
+pub mod plan;
+pub mod pool;
+pub mod proxy;
+pub mod random;
+pub mod rate;
+pub mod raw;
+pub mod result;
+pub mod rtt;
+pub mod sanity;
+pub mod services;
+pub mod tls;
+pub mod trace;
+pub mod udp;
+pub mod watch;
+
+use crate::{
+    args::{
+        interfaces,
+        parser::{PortRange, Protocol},
+        target::{check_allowlist, Target},
+    },
+    input_parse::Args,
+    output::severity,
+};
+use connect::{ConnectOptions, PostConnect};
+use dns::ReverseDnsCache;
+use hook::Hook;
+use jobs::Job;
+use pool::{Jitter, WorkerPool};
+use rate::{RateController, SlowStart};
+use raw::ScanType;
+use result::ScanResult;
+use rtt::HostTimeouts;
+use std::{
+    io::Write,
+    net::{IpAddr, SocketAddr},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, SystemTime},
+};
+use trace::Trace;
+
+/// Default number of connection attempts kept in flight at once.
+pub const DEFAULT_CONCURRENCY: usize = 100;
+
+/// Default upper bound on the number of (host, port) jobs a scan may enqueue without `--yes`.
+pub const DEFAULT_MAX_JOBS: u64 = 10_000_000;
+
+/// Returns the total number of (host, port) jobs the targets and ports expand to.
+pub fn job_count(targets: &[Target], ports: &PortRange) -> u128 {
+    targets
+        .iter()
+        .fold(0u128, |hosts, target| hosts.saturating_add(target.count()))
+        .saturating_mul(ports.count() as u128)
+}
+
+/// Refuses scans whose job count exceeds `max_jobs` unless they were explicitly confirmed.
+///
+/// # Returns
+///
+/// * `Ok(())` - If the scan is within the limit or `confirmed` is set.
+/// * `Err(String)` - If the limit is exceeded, with the computed total and the threshold.
+pub fn check_job_limit(total: u128, max_jobs: u64, confirmed: bool) -> Result<(), String> {
+    if confirmed || total <= max_jobs as u128 {
+        return Ok(());
+    }
+
+    Err(format!(
+        "Scan would run {} jobs, exceeding the limit of {}. Pass --yes or raise --max-jobs to proceed.",
+        total, max_jobs
+    ))
+}
+
+/// A handle to a scan running in the background, used to cancel it.
+#[derive(Debug, Clone, Default)]
+pub struct ScanHandle {
+    stop: Arc<AtomicBool>,
+}
+
+impl ScanHandle {
+    /// Asks the scan to stop; workers finish their in-flight probes and no new jobs start.
+    pub fn cancel(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns whether `cancel` has been called.
+    pub fn is_cancelled(&self) -> bool {
+        self.stop.load(Ordering::Relaxed)
+    }
+}
+
+/// Runs a TCP connect scan over every target and port described by `args`.
+///
+/// Results are returned sorted by address and port, with one result per (host, port). Under
+/// `--strict`, a probe that errored fails the whole scan; see `check_results`.
+pub fn run(args: &Args) -> Result<Vec<ScanResult>, String> {
+    check_args(args)?;
+    let trace = open_trace(args)?;
+    let results = execute(args, trace.as_ref(), &AtomicBool::new(false));
+    check_results(args, &results)?;
+    Ok(results)
+}
+
+/// Fails under `--strict` if any probe in `results` errored, for example on a permission
+/// error or an interface without an address for the target, naming the first one.
+///
+/// `run` checks its own results; callers of `scan_with_handle` and `scan_streaming` check
+/// what they collected.
+pub fn check_results(args: &Args, results: &[ScanResult]) -> Result<(), String> {
+    match results.iter().find(|r| r.state == result::PortState::Error) {
+        Some(failed) if args.strict => Err(format!(
+            "--strict: probe of {} failed: {}",
+            SocketAddr::new(failed.address, failed.port),
+            failed.error.as_deref().unwrap_or("unknown error")
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Scans exactly `addrs`, ignoring the targets and ports of `args`.
+///
+/// This is the lowest-level entry point for embedders that already resolved their jobs: the
+/// sockets go straight to the worker pool, probed and annotated with every other option of
+/// `args`, and the results come back sorted and deduplicated like `run`. No job limit or
+/// allowlist applies, since the caller chose every socket; the scan only fails if the
+/// `--trace-file` cannot be created or, under `--strict`, a probe errors.
+pub fn scan_socket_addrs(addrs: &[SocketAddr], args: &Args) -> Result<Vec<ScanResult>, String> {
+    let trace = open_trace(args)?;
+    let jobs = addrs.iter().copied().map(Job::from);
+    let results = execute_jobs(args, jobs, trace.as_ref(), &AtomicBool::new(false));
+    check_results(args, &results)?;
+    Ok(results)
+}
+
+/// Starts a scan on a background thread and returns a handle that can cancel it.
+///
+/// The job limit is checked, and the trace file created, before the thread is spawned. Joining
+/// after `ScanHandle::cancel` returns the results collected so far, sorted like `run`.
+pub fn scan_with_handle(args: &Args) -> Result<(ScanHandle, JoinHandle<Vec<ScanResult>>), String> {
+    check_args(args)?;
+    let trace = open_trace(args)?;
+
+    let handle = ScanHandle::default();
+    let stop = Arc::clone(&handle.stop);
+    let args = args.clone();
+    let join = thread::spawn(move || execute(&args, trace.as_ref(), &stop));
+
+    Ok((handle, join))
+}
+
+/// Starts a scan on a background thread and streams each result as soon as it is probed.
+///
+/// Results arrive in completion order and are not deduplicated, so overlapping targets can
+/// yield the same (host, port) more than once. The receiver closes when the scan completes;
+/// dropping it stops the scan. Fails before spawning if the job limit is exceeded or the trace
+/// file cannot be created. Under `--strict` the scan stops after the first probe that errors.
+pub fn scan_streaming(args: &Args) -> Result<(Receiver<ScanResult>, JoinHandle<()>), String> {
+    check_args(args)?;
+    let trace = open_trace(args)?;
+
+    let (tx, rx) = mpsc::channel();
+    let args = args.clone();
+    let join = thread::spawn(move || {
+        let jobs = job_stream(&args);
+        let names = ReverseDnsCache::new(dns::reverse_lookup);
+        let hook = args.on_open.clone().map(Hook::start);
+        let timeouts = host_timeouts(&args);
+        let context = ProbeContext {
+            names: &names,
+            trace: trace.as_ref(),
+            hook: hook.as_ref(),
+            timeouts: timeouts.as_ref(),
+        };
+        let stop = AtomicBool::new(false);
+        worker_pool(&args).stream(
+            jobs,
+            &stop,
+            |job| {
+                let result = annotated_probe(&args, &context, job);
+                if args.strict && result.state == result::PortState::Error {
+                    stop.store(true, Ordering::Relaxed);
+                }
+                result
+            },
+            tx,
+        );
+    });
+
+    Ok((rx, join))
+}
+
+/// Writes the jobs a scan of `args` would probe to `writer` as a JSON plan, with each job's
+/// protocol, and returns how many there were. Fails like `run` before writing anything.
+pub fn emit_plan<W: Write>(args: &Args, writer: &mut W) -> Result<usize, String> {
+    check_args(args)?;
+    let jobs = job_stream(args).map(|job| plan::PlanJob {
+        address: job.address,
+        port: job.port,
+        protocol: Protocol::of(scan_type_for(args, &job)),
+        scope_id: job.scope_id,
+    });
+    plan::write_plan(writer, jobs).map_err(|e| format!("Failed to write scan plan: {}", e))
+}
+
+/// Returns the (host, port) pairs `args` plan that `results` don't cover, coalesced per host.
+///
+/// A scan that ran to completion has no gaps; one that was cancelled lists what remains.
+pub fn coverage_gaps(args: &Args, results: &[ScanResult]) -> Vec<coverage::CoverageGap> {
+    coverage::not_scanned(job_stream(args), results)
+}
+
+fn check_args(args: &Args) -> Result<(), String> {
+    if let (true, Some(skipped)) = (args.strict, args.skipped.first()) {
+        return Err(format!(
+            "--strict: cannot scan target {}: {}",
+            skipped.target, skipped.reason
+        ));
+    }
+    if let (true, Some(skipped)) = (args.strict, args.skipped_ports.first()) {
+        return Err(format!(
+            "--strict: cannot scan ports {}: {}",
+            skipped.ports, skipped.reason
+        ));
+    }
+    if args.fingerprint && args.scan_type.flags().is_none() {
+        return Err(format!(
+            "--fingerprint needs a raw scan type(null, fin or xmas), not {}",
+            args.scan_type
+        ));
+    }
+    if let (Some(_), true, None) = (args.shard, args.shuffle, args.seed) {
+        return Err(
+            "--shard with --shuffle needs --seed, so every shard shuffles the jobs alike"
+                .to_string(),
+        );
+    }
+    let tagged_udp = args
+        .port_protocols
+        .iter()
+        .find(|(_, protocol)| *protocol == Protocol::Udp);
+    if let (Some(((port, _), _)), false) = (tagged_udp, args.proxies.is_empty()) {
+        return Err(format!(
+            "Port {}/udp cannot be tunneled through --proxy",
+            port
+        ));
+    }
+    if args.scan_type != ScanType::Connect {
+        if !args.proxies.is_empty() {
+            return Err(format!(
+                "--scan-type {} cannot be tunneled through --proxy",
+                args.scan_type
+            ));
+        }
+        if args.scan_type.flags().is_some() {
+            raw::check_privileges(args.scan_type)?;
+        }
+    }
+    if let Some(allowlist) = &args.allowlist {
+        match &args.pairs {
+            Some(pairs) => {
+                let hosts: Vec<Target> = pairs
+                    .iter()
+                    .map(|job| Target::Single(job.address))
+                    .collect();
+                check_allowlist(&hosts, allowlist)?;
+            }
+            None => check_allowlist(&args.targets, allowlist)?,
+        }
+    }
+    let total = match &args.pairs {
+        Some(pairs) => pairs.len() as u128,
+        None => {
+            // Link-local targets are scanned once per interface.
+            let (link_local, other): (Vec<Target>, Vec<Target>) = args
+                .targets
+                .iter()
+                .cloned()
+                .partition(|target| target.is_within(&interfaces::link_local_block()));
+            job_count(&link_local, &args.ports)
+                .saturating_mul(args.interfaces.len().max(1) as u128)
+                .saturating_add(job_count(&other, &args.ports))
+        }
+    };
+    let total = args.shard.map_or(total, |shard| shard.share(total));
+    check_job_limit(total, args.max_jobs, args.yes)
+}
+
+/// Creates the `--trace-file` for a scan that is about to start, if one was given.
+fn open_trace(args: &Args) -> Result<Option<Trace>, String> {
+    args.trace_file.as_deref().map(Trace::start).transpose()
+}
+
+fn execute(args: &Args, trace: Option<&Trace>, stop: &AtomicBool) -> Vec<ScanResult> {
+    execute_jobs(args, job_stream(args), trace, stop)
+}
+
+/// Returns the jobs `args` describe, shuffled with `args.seed` (or fresh entropy) if asked to,
+/// or only the baseline's open ports with `--only-ports-from-baseline`. A `--pairs-file`
+/// replaces the targets and ports altogether.
+///
+/// With `--shard`, only the shard's slice of those jobs is kept. When link-local targets found
+/// `args.interfaces`, link-local jobs are repeated per interface. Hosts in
+/// `args.recently_scanned` are left out.
+fn job_stream(args: &Args) -> Box<dyn Iterator<Item = Job> + Send + '_> {
+    let mut jobs = planned_jobs(args);
+    if let Some(shard) = args.shard {
+        jobs = Box::new(jobs::shard(jobs, shard));
+    }
+    if !args.recently_scanned.is_empty() {
+        let recent = &args.recently_scanned;
+        jobs = Box::new(jobs.filter(move |job| !recent.contains(&job.address)));
+    }
+    jobs = Box::new(jobs::per_protocol(
+        jobs,
+        &args.port_protocols,
+        Protocol::of(args.scan_type),
+    ));
+    if args.interfaces.is_empty() {
+        return jobs;
+    }
+    let scopes: Arc<[u32]> = args.interfaces.iter().map(|i| i.index).collect();
+    Box::new(jobs::per_interface(jobs, scopes))
+}
+
+fn planned_jobs(args: &Args) -> Box<dyn Iterator<Item = Job> + Send + '_> {
+    if let Some(pairs) = &args.pairs {
+        Box::new(pairs.iter().copied())
+    } else if let Some(baseline) = args.baseline.as_ref().filter(|_| args.only_baseline_ports) {
+        Box::new(jobs::baseline_jobs(&args.targets, &args.ports, baseline).into_iter())
+    } else if args.shuffle {
+        let seed = args.seed.unwrap_or_else(random::entropy_seed);
+        Box::new(jobs::shuffled_jobs(&args.targets, &args.ports, seed))
+    } else {
+        Box::new(jobs::jobs(&args.targets, &args.ports, &args.priority_ports))
+    }
+}
+
+fn execute_jobs<I>(
+    args: &Args,
+    jobs: I,
+    trace: Option<&Trace>,
+    stop: &AtomicBool,
+) -> Vec<ScanResult>
+where
+    I: Iterator<Item = Job> + Send,
+{
+    let names = ReverseDnsCache::new(dns::reverse_lookup);
+    let hook = args.on_open.clone().map(Hook::start);
+    let timeouts = host_timeouts(args);
+    let context = ProbeContext {
+        names: &names,
+        trace,
+        hook: hook.as_ref(),
+        timeouts: timeouts.as_ref(),
+    };
+    let results = worker_pool(args).run(jobs, stop, |job| annotated_probe(args, &context, job));
+    aggregate::dedup(results)
+}
+
+/// State the workers of one scan share while probing.
+struct ProbeContext<'a, F> {
+    names: &'a ReverseDnsCache<F>,
+    trace: Option<&'a Trace>,
+    hook: Option<&'a Hook>,
+    /// Per-host timeouts learned from round trips, with `--rtt-timeout`.
+    timeouts: Option<&'a HostTimeouts>,
+}
+
+/// Returns the per-host timeout table if `--rtt-timeout` is set.
+fn host_timeouts(args: &Args) -> Option<HostTimeouts> {
+    args.rtt_timeout
+        .then(|| HostTimeouts::new(Duration::from_millis(args.timeout as u64)))
+}
+
+/// Probes the job with its host's timeout, feeds an answer's round trip back into that timeout,
+/// records the job in the trace if there is one, strips control sequences from its banner if
+/// asked, fills in the interface, severity and hostname of its result, runs the embedder's
+/// result hook on it, and hands an open result to the `--on-open` hook.
+fn annotated_probe<F>(args: &Args, context: &ProbeContext<'_, F>, job: &Job) -> ScanResult
+where
+    F: Fn(IpAddr) -> Option<String>,
+{
+    let ProbeContext {
+        names,
+        trace,
+        hook,
+        timeouts,
+    } = *context;
+    let timeout = timeouts.map_or(Duration::from_millis(args.timeout as u64), |timeouts| {
+        timeouts.timeout_for(job.address)
+    });
+    let mut result = probe_job(args, job, timeout);
+    if let (Some(timeouts), Some(latency)) = (timeouts, result.latency) {
+        // Only answers time a round trip; a timeout says nothing about the host's distance.
+        if matches!(
+            result.state,
+            result::PortState::Open | result::PortState::Closed
+        ) {
+            timeouts.record(job.address, latency);
+        }
+    }
+    result.timestamp = Some(humantime::format_rfc3339_millis(SystemTime::now()).to_string());
+    if !args.explain {
+        result.detail = None;
+    }
+    if let Some(trace) = trace {
+        trace.record(&result);
+    }
+    let result = with_interface(args, job, with_quiet_banner(args, result));
+    let mut result = with_hostname(args, names, with_severity(result));
+    if let Some(result_hook) = &args.result_hook {
+        result_hook.process(&mut result);
+    }
+    if let Some(hook) = hook {
+        hook.notify(&result);
+    }
+    result
+}
+
+/// Strips control sequences from the result's banner with `--quiet-banner`, keeping the banner
+/// as read alongside with `--raw-banner`. Binary banners are already hex-escaped.
+fn with_quiet_banner(args: &Args, result: ScanResult) -> ScanResult {
+    match &result.banner {
+        Some(banner) if args.quiet_banner && !result.banner_binary => ScanResult {
+            banner: Some(banner::sanitize_banner(banner)),
+            banner_raw: args.raw_banner.then(|| banner.clone()),
+            ..result
+        },
+        _ => result,
+    }
+}
+
+/// Rates how risky it is that the result's service is exposed, if it is open.
+fn with_severity(result: ScanResult) -> ScanResult {
+    if result.state != result::PortState::Open {
+        return result;
+    }
+    ScanResult {
+        severity: Some(severity::service_severity(&result)),
+        ..result
+    }
+}
+
+/// Tags the result of a job scoped to an interface with that interface's name.
+fn with_interface(args: &Args, job: &Job, result: ScanResult) -> ScanResult {
+    let interface = args
+        .interfaces
+        .iter()
+        .find(|interface| job.scope_id != 0 && interface.index == job.scope_id);
+    match interface {
+        Some(interface) => ScanResult {
+            interface: Some(interface.name.clone()),
+            ..result
+        },
+        None => result,
+    }
+}
+
+/// Fills in the result's hostname from the scan's reverse DNS cache, if `--reverse-dns` is set.
+fn with_hostname<F>(args: &Args, names: &ReverseDnsCache<F>, result: ScanResult) -> ScanResult
+where
+    F: Fn(IpAddr) -> Option<String>,
+{
+    if !args.reverse_dns {
+        return result;
+    }
+    ScanResult {
+        hostname: names.get(result.address),
+        ..result
+    }
+}
+
+fn worker_pool(args: &Args) -> WorkerPool {
+    let mut pool = WorkerPool::new(args.concurrency).with_batch_size(args.batch_size);
+    if let Some(max_results) = args.max_results {
+        pool = pool.with_max_open(max_results);
+    }
+    if let Some(window) = args.window {
+        pool = pool.with_window(window);
+    }
+    if let Some(max_ms) = args.jitter {
+        pool = pool.with_jitter(Jitter {
+            max: Duration::from_millis(max_ms as u64),
+            seed: args.seed.unwrap_or_else(random::entropy_seed),
+        });
+    }
+    match args.min_rate {
+        Some(floor) => pool.with_min_rate(RateController::new(floor)),
+        None if args.slow_start => pool.with_slow_start(SlowStart::new(args.concurrency)),
+        None => pool,
+    }
+}
+
+/// Returns the scan type `job` is probed with: its protocol applied to `--scan-type`.
+///
+/// Jobs that never went through `job_stream`, like those of `scan_socket_addrs`, have no
+/// protocol yet and take the first `/tcp` or `/udp` suffix of their port in `--ports`.
+fn scan_type_for(args: &Args, job: &Job) -> ScanType {
+    let protocol = job.protocol.or_else(|| {
+        args.port_protocols
+            .iter()
+            .find(|((start, end), _)| (*start..=*end).contains(&job.port))
+            .map(|(_, protocol)| *protocol)
+    });
+    protocol.map_or(args.scan_type, |protocol| {
+        protocol.scan_type(args.scan_type)
+    })
+}
+
+/// Probes the job with a raw segment for NULL, FIN and Xmas scans and with a datagram for UDP
+/// scans. Connect scans knock if configured, probe directly or through the proxy chain, and
+/// inspect TLS and identify the service on open ports if asked to. Direct probes that grab a
+/// banner identify the service on the same connection instead of opening another one.
+fn probe_job(args: &Args, job: &Job, timeout: Duration) -> ScanResult {
+    // Scoped link-local jobs already leave through the interface their scope names.
+    let interface = args.interface.as_ref().filter(|_| job.scope_id == 0);
+    let source = match interface.map(|i| i.source_for(job.address)) {
+        Some(Ok(source)) => Some(source),
+        Some(Err(e)) => {
+            return ScanResult {
+                error: Some(e),
+                ..ScanResult::new(job.address, job.port, result::PortState::Error)
+            }
+        }
+        None => None,
+    };
+    match scan_type_for(args, job) {
+        ScanType::Connect => {}
+        ScanType::Udp => return udp::probe(job, source, timeout),
+        scan_type => return raw::probe(job, scan_type, interface, args.fingerprint, timeout),
+    }
+    let probe_timeout = Duration::from_millis(args.probe_timeout.unwrap_or(args.timeout) as u64);
+    let grab = args.banner || args.post_connect == PostConnect::GrabThenClose;
+    let banner = grab.then_some(args.banner_options);
+    let detect_on_banner = grab && args.service_detect && args.proxies.is_empty();
+    let options = ConnectOptions {
+        timeout,
+        banner,
+        detect: detect_on_banner.then_some(probe_timeout),
+        fast_open: args.fast_open,
+        post_connect: args.post_connect,
+        retries: args.retries,
+        retry_on_reset: args.retry_on_reset,
+        source,
+    };
+
+    knock::probe_after_knock(
+        job,
+        &args.knock,
+        |addr| knock::knock_port(addr, timeout),
+        |job| {
+            let result = if args.proxies.is_empty() {
+                connect::probe(job, &options)
+            } else {
+                proxy::probe(job, &args.proxies, timeout, banner.as_ref())
+            };
+            if result.state != result::PortState::Open {
+                return result;
+            }
+            let addr = job.socket_addr();
+            ScanResult {
+                tls: args
+                    .tls
+                    .then(|| tls::inspect(addr, &args.proxies, timeout).ok())
+                    .flatten(),
+                service: result.service.clone().or_else(|| {
+                    (args.service_detect && !detect_on_banner)
+                        .then(|| detect::detect(addr, &args.proxies, timeout, probe_timeout))
+                        .flatten()
+                }),
+                ..result
+            }
+        },
+    )
+}
+
+// Unit tests >------------------------------------------------------------<
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::args::target::parse_targets;
+
+    #[test]
+    fn test_job_count() {
+        let targets = parse_targets("10.0.0.1,10.0.1.0/24".to_string()).unwrap();
+        let ports = PortRange::Range(vec![(1, 1024)]);
+        assert_eq!(job_count(&targets, &ports), 257 * 1024);
+    }
+
+    #[test]
+    fn test_job_count_saturates() {
+        let all = Target::Cidr("::".parse().unwrap(), 0);
+        let targets = vec![all.clone(), all];
+        let ports = PortRange::Range(vec![(1, 65535)]);
+        assert_eq!(job_count(&targets, &ports), u128::MAX);
+    }
+
+    #[test]
+    fn test_check_job_limit_triggers() {
+        let targets = parse_targets("10.0.0.0/8".to_string()).unwrap();
+        let total = job_count(&targets, &PortRange::Range(vec![(1, 1024)]));
+        assert_eq!(
+            check_job_limit(total, DEFAULT_MAX_JOBS, false),
+            Err(format!(
+                "Scan would run 17179869184 jobs, exceeding the limit of {}. Pass --yes or raise --max-jobs to proceed.",
+                DEFAULT_MAX_JOBS
+            ))
+        );
+    }
+
+    #[test]
+    fn test_check_job_limit_overrides() {
+        let total = 17_179_869_184;
+        assert_eq!(check_job_limit(total, DEFAULT_MAX_JOBS, true), Ok(()));
+        assert_eq!(check_job_limit(total, u64::MAX, false), Ok(()));
+        assert_eq!(check_job_limit(1024, DEFAULT_MAX_JOBS, false), Ok(()));
+    }
+
+    /// Binds `count` listeners on 127.0.0.1 and returns them with their ports, sorted, and those
+    /// ports as a `--ports` list. Scanning only these keeps a test from connecting to, and
+    /// taking the accept of, a listener another test is running.
+    fn owned_ports(count: usize) -> (Vec<std::net::TcpListener>, Vec<u16>, String) {
+        let listeners: Vec<std::net::TcpListener> = (0..count)
+            .map(|_| std::net::TcpListener::bind("127.0.0.1:0").unwrap())
+            .collect();
+        let mut ports: Vec<u16> = listeners
+            .iter()
+            .map(|listener| listener.local_addr().unwrap().port())
+            .collect();
+        ports.sort_unstable();
+        let list = ports
+            .iter()
+            .map(u16::to_string)
+            .collect::<Vec<_>>()
+            .join(",");
+        (listeners, ports, list)
+    }
+
+    #[test]
+    fn test_scan_with_handle_cancel_returns_partial_results() {
+        let (_listeners, ports, list) = owned_ports(32);
+        // Holding every connection keeps the scan running well past the cancel.
+        let args = Args::parse_from([
+            "port_hawk",
+            "127.0.0.1",
+            "-p",
+            &list,
+            "-c",
+            "2",
+            "--keep-open",
+            "50ms",
+        ]);
+        let (handle, join) = scan_with_handle(&args).unwrap();
+
+        thread::sleep(Duration::from_millis(20));
+        handle.cancel();
+        let results = join.join().unwrap();
+
+        assert!(handle.is_cancelled());
+        assert!(results.len() < ports.len());
+        assert!(results.iter().all(|r| ports.contains(&r.port)));
+        assert!(results.windows(2).all(|w| w[0].port < w[1].port));
+    }
+
+    #[test]
+    fn test_job_stream_schedules_only_listed_pairs() {
+        let path = std::env::temp_dir().join(format!("port_hawk_pairs_{}", std::process::id()));
+        std::fs::write(&path, "10.0.0.1:22\n10.0.0.9 8080\n").unwrap();
+        let args = Args::parse_from([
+            "port_hawk",
+            "10.0.0.0/24",
+            "-p",
+            "1-1024",
+            "--pairs-file",
+            path.to_str().unwrap(),
+        ]);
+        std::fs::remove_file(&path).unwrap();
+
+        let jobs: Vec<Job> = job_stream(&args).collect();
+        assert_eq!(
+            jobs,
+            vec![
+                Job {
+                    address: "10.0.0.1".parse().unwrap(),
+                    port: 22,
+                    scope_id: 0,
+                    protocol: Some(Protocol::Tcp),
+                },
+                Job {
+                    address: "10.0.0.9".parse().unwrap(),
+                    port: 8080,
+                    scope_id: 0,
+                    protocol: Some(Protocol::Tcp),
+                },
+            ]
+        );
+        assert_eq!(coverage_gaps(&args, &[]).len(), 2);
+    }
+
+    #[test]
+    fn test_strict_aborts_on_bad_target() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port().to_string();
+        let lenient = Args::parse_from(["port_hawk", "127.0.0.1,10.0.0.300/24", "-p", &port]);
+
+        let results = run(&lenient).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].state, result::PortState::Open);
+
+        let strict = Args {
+            strict: true,
+            ..lenient
+        };
+        let error = run(&strict).unwrap_err();
+        assert!(
+            error.starts_with("--strict: cannot scan target 10.0.0.300/24: "),
+            "{}",
+            error
+        );
+        assert!(scan_streaming(&strict).is_err());
+    }
+
+    #[test]
+    fn test_run_scans_a_port_over_both_protocols() {
+        let (_listeners, ports, _) = owned_ports(1);
+        let list = format!("{0}/tcp,{0}/udp", ports[0]);
+        let args = Args::parse_from(["port_hawk", "127.0.0.1", "-p", &list]);
+
+        let results = run(&args).unwrap();
+        let outcomes: Vec<(Protocol, result::PortState)> =
+            results.iter().map(|r| (r.protocol, r.state)).collect();
+        // Nothing listens on the UDP port, so loopback answers with port unreachable.
+        assert_eq!(
+            outcomes,
+            vec![
+                (Protocol::Tcp, result::PortState::Open),
+                (Protocol::Udp, result::PortState::Closed)
+            ]
+        );
+        let line = results[1].to_string();
+        assert!(
+            line.starts_with(&format!("127.0.0.1:{}/udp closed", ports[0])),
+            "{}",
+            line
+        );
+    }
+
+    #[test]
+    fn test_strict_aborts_on_invalid_port() {
+        let (_listeners, ports, _) = owned_ports(1);
+        let list = format!("{},99999", ports[0]);
+        let lenient = Args::parse_from(["port_hawk", "127.0.0.1", "-p", &list]);
+
+        let results = run(&lenient).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].state, result::PortState::Open);
+
+        let strict = Args {
+            strict: true,
+            ..lenient
+        };
+        assert_eq!(
+            run(&strict),
+            Err("--strict: cannot scan ports 99999: Invalid port: 99999".to_string())
+        );
+    }
+
+    #[test]
+    fn test_strict_aborts_on_permission_error() {
+        // Sending to the broadcast address without SO_BROADCAST is refused with EACCES.
+        let lenient = Args::parse_from(["port_hawk", "255.255.255.255", "-p", "9/udp"]);
+
+        let results = run(&lenient).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].state, result::PortState::Error);
+
+        let strict = Args {
+            strict: true,
+            ..lenient
+        };
+        let error = run(&strict).unwrap_err();
+        assert!(
+            error.starts_with("--strict: probe of 255.255.255.255:9 failed: udp probe failed: "),
+            "{}",
+            error
+        );
+        assert!(error.contains("Permission denied"), "{}", error);
+
+        let (results, scan) = scan_streaming(&strict).unwrap();
+        scan.join().unwrap();
+        let results: Vec<ScanResult> = results.into_iter().collect();
+        assert!(check_results(&strict, &results).is_err());
+    }
+
+    #[test]
+    fn test_scan_with_handle_checks_job_limit() {
+        let args = Args::parse_from(["port_hawk", "10.0.0.0/8"]);
+        assert!(scan_with_handle(&args).is_err());
+    }
+
+    #[test]
+    fn test_scan_streaming_delivers_every_result() {
+        let (_listeners, ports, list) = owned_ports(5);
+        let args = Args::parse_from(["port_hawk", "127.0.0.1", "-p", &list]);
+
+        let (rx, join) = scan_streaming(&args).unwrap();
+        let mut results: Vec<ScanResult> = rx.iter().collect();
+        join.join().unwrap();
+
+        results.sort_by_key(|r| r.port);
+        let scanned: Vec<u16> = results.iter().map(|r| r.port).collect();
+        assert_eq!(scanned, ports);
+        assert_eq!(results[0].state, result::PortState::Open);
+        assert_eq!(results[0].severity, Some(severity::Severity::Info));
+    }
+
+    #[test]
+    fn test_scan_streaming_stamps_results_in_completion_order() {
+        let (_listeners, _, list) = owned_ports(10);
+        let args = Args::parse_from(["port_hawk", "127.0.0.1", "-p", &list, "-c", "1"]);
+
+        let (rx, join) = scan_streaming(&args).unwrap();
+        let stamps: Vec<SystemTime> = rx
+            .iter()
+            .map(|r| humantime::parse_rfc3339(r.timestamp.as_deref().unwrap()).unwrap())
+            .collect();
+        join.join().unwrap();
+
+        assert_eq!(stamps.len(), 10);
+        assert!(stamps.windows(2).all(|w| w[0] <= w[1]));
+    }
+
+    #[test]
+    fn test_result_hook_changes_reach_output() {
+        use crate::output::{self, Report};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let args = Args::builder()
+            .address("127.0.0.1")
+            .ports(port.to_string())
+            .result_hook(|result: &mut ScanResult| {
+                result.banner = Some(format!("cmdb: web-01 port {}", result.port));
+            })
+            .build()
+            .unwrap();
+
+        let report = Report {
+            results: run(&args).unwrap(),
+            ..Report::default()
+        };
+        let options = output::OutputOptions::default();
+        let mut buf = Vec::new();
+        output::write_results(&mut buf, &report, &options).unwrap();
+
+        let text = String::from_utf8(buf).unwrap();
+        assert!(
+            text.contains(&format!("cmdb: web-01 port {}", port)),
+            "{}",
+            text
+        );
+    }
+
+    #[test]
+    fn test_run_traces_every_probe() {
+        let (_listeners, ports, list) = owned_ports(3);
+        let path = std::env::temp_dir().join(format!("port_hawk_run_trace_{}", std::process::id()));
+        let trace_file = path.to_str().unwrap();
+        let args = Args::parse_from([
+            "port_hawk",
+            "127.0.0.1",
+            "-p",
+            &list,
+            "--trace-file",
+            trace_file,
+        ]);
+
+        // Only a scan that starts creates the trace.
+        let _ = std::fs::remove_file(&path);
+        emit_plan(&args, &mut Vec::new()).unwrap();
+        assert!(!path.exists());
+
+        run(&args).unwrap();
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let mut entries: Vec<trace::TraceEntry> = contents
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        entries.sort_by_key(|entry| entry.port);
+        let traced: Vec<u16> = entries.iter().map(|entry| entry.port).collect();
+        assert_eq!(traced, ports);
+        assert_eq!(entries[0].state, result::PortState::Open);
+        std::fs::remove_file(path).unwrap();
+
+        let unwritable = Args {
+            trace_file: Some(
+                std::env::temp_dir()
+                    .join("port_hawk_no_such_dir")
+                    .join("trace"),
+            ),
+            ..args
+        };
+        assert!(run(&unwritable)
+            .unwrap_err()
+            .starts_with("Failed to create trace file "));
+    }
+
+    #[test]
+    fn test_scan_socket_addrs_scans_only_given_sockets() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let open = listener.local_addr().unwrap();
+        let closed = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        // The targets and ports of `args` are ignored.
+        let args = Args::parse_from(["port_hawk", "10.0.0.0/8", "-p", "1-1024"]);
+
+        let results = scan_socket_addrs(&[closed, open], &args).unwrap();
+        let scanned: Vec<SocketAddr> = results
+            .iter()
+            .map(|r| SocketAddr::new(r.address, r.port))
+            .collect();
+        let mut expected = vec![open, closed];
+        expected.sort();
+        assert_eq!(scanned, expected);
+        let state = |addr: SocketAddr| {
+            results
+                .iter()
+                .find(|r| r.port == addr.port())
+                .unwrap()
+                .state
+        };
+        assert_eq!(state(open), result::PortState::Open);
+        assert_eq!(state(closed), result::PortState::Closed);
+    }
+
+    #[test]
+    fn test_job_stream_skips_recently_scanned_hosts() {
+        let path = std::env::temp_dir().join(format!("port_hawk_history_{}", std::process::id()));
+        let started_at = humantime::format_rfc3339_seconds(std::time::SystemTime::now());
+        let report = crate::output::Report {
+            started_at: Some(started_at.to_string()),
+            results: vec![ScanResult::new(
+                "10.0.0.2".parse().unwrap(),
+                22,
+                result::PortState::Open,
+            )],
+            ..crate::output::Report::default()
+        };
+        std::fs::write(&path, serde_json::to_string(&report).unwrap()).unwrap();
+        let args = Args::parse_from([
+            "port_hawk",
+            "10.0.0.0/30",
+            "-p",
+            "22",
+            "--scan-history",
+            path.to_str().unwrap(),
+            "--skip-if-scanned-within",
+            "1h",
+        ]);
+        std::fs::remove_file(path).unwrap();
+
+        let hosts: Vec<IpAddr> = job_stream(&args).map(|job| job.address).collect();
+        let expected: Vec<IpAddr> = ["10.0.0.0", "10.0.0.1", "10.0.0.3"]
+            .iter()
+            .map(|host| host.parse().unwrap())
+            .collect();
+        assert_eq!(hosts, expected);
+    }
+
+    #[test]
+    fn test_probe_job_sends_from_interface_address() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let mut args = Args::parse_from(["port_hawk", "127.0.0.1"]);
+        args.interface = Some(interfaces::OutgoingInterface {
+            name: "lo".to_string(),
+            ipv4: Some("127.0.0.1".parse().unwrap()),
+            ipv6: None,
+        });
+        let job = |address: &str| Job {
+            address: address.parse().unwrap(),
+            port,
+            scope_id: 0,
+            protocol: None,
+        };
+
+        let result = probe_job(&args, &job("127.0.0.1"), Duration::from_secs(1));
+        assert_eq!(result.state, result::PortState::Open);
+        assert_eq!(result.source_addr, Some("127.0.0.1".parse().unwrap()));
+
+        let result = probe_job(&args, &job("::1"), Duration::from_secs(1));
+        assert_eq!(result.state, result::PortState::Error);
+        assert_eq!(
+            result.error.as_deref(),
+            Some("Interface lo has no IPv6 address")
+        );
+    }
+
+    #[test]
+    fn test_scan_type_for_tagged_ports() {
+        let args = Args::parse_from([
+            "port_hawk",
+            "127.0.0.1",
+            "--scan-type",
+            "udp",
+            "--ports",
+            "80/tcp,53/udp,161-162",
+        ]);
+        let scan_types = |args: &Args| {
+            job_stream(args)
+                .map(|job| (job.port, scan_type_for(args, &job)))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            scan_types(&args),
+            vec![
+                (80, ScanType::Connect),
+                (53, ScanType::Udp),
+                (161, ScanType::Udp),
+                (162, ScanType::Udp)
+            ]
+        );
+
+        let args = Args::parse_from(["port_hawk", "127.0.0.1", "--ports", "22,53/udp"]);
+        assert_eq!(
+            scan_types(&args),
+            vec![(22, ScanType::Connect), (53, ScanType::Udp)]
+        );
+        // Untagged jobs handed in directly fall back to the port's suffix.
+        let job = Job::from("127.0.0.1:53".parse::<SocketAddr>().unwrap());
+        assert_eq!(scan_type_for(&args, &job), ScanType::Udp);
+
+        let args = Args::parse_from(["port_hawk", "127.0.0.1", "--ports", "53/tcp,53/udp"]);
+        assert_eq!(
+            scan_types(&args),
+            vec![(53, ScanType::Connect), (53, ScanType::Udp)]
+        );
+    }
+
+    #[test]
+    fn test_job_stream_same_seed_same_order() {
+        let args = Args::parse_from(["port_hawk", "10.0.0.0/28", "--shuffle", "--seed", "9"]);
+        let first: Vec<Job> = job_stream(&args).collect();
+        let second: Vec<Job> = job_stream(&args).collect();
+
+        assert_eq!(first.len(), 16 * 1024);
+        assert_eq!(first, second);
+        assert_ne!(
+            first,
+            jobs::jobs(&args.targets, &args.ports, &[]).collect::<Vec<Job>>()
+        );
+    }
+
+    #[test]
+    fn test_run_dedups_overlapping_targets() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port().to_string();
+        let args = Args::parse_from(["port_hawk", "127.0.0.1,127.0.0.1/32", "-p", &port]);
+
+        let results = run(&args).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].state, result::PortState::Open);
+    }
+
+    #[test]
+    fn test_probe_job_service_detect_uses_probe_timeout() {
+        use std::io::{Read, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        // The first connection is the port probe; the second is service detection, answered
+        // well after --timeout but within --probe-timeout.
+        let server = thread::spawn(move || {
+            drop(listener.accept().unwrap());
+            let (mut stream, _) = listener.accept().unwrap();
+            let _ = stream.read(&mut [0u8; 64]);
+            thread::sleep(Duration::from_millis(300));
+            let _ = stream.write_all(b"HTTP/1.0 200 OK\r\n\r\n");
+        });
+        let args = Args::parse_from([
+            "port_hawk",
+            "127.0.0.1",
+            "--timeout",
+            "100",
+            "--service-detect",
+            "--probe-timeout",
+            "700",
+        ]);
+        let job = Job {
+            address: "127.0.0.1".parse().unwrap(),
+            port,
+            scope_id: 0,
+            protocol: None,
+        };
+
+        let result = probe_job(&args, &job, Duration::from_millis(100));
+        server.join().unwrap();
+        assert_eq!(result.state, result::PortState::Open);
+        assert_eq!(result.service.as_deref(), Some("http"));
+    }
+
+    #[test]
+    fn test_detected_service_sets_severity_on_any_port() {
+        use std::io::{Read, Write};
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        // A telnet server on an ephemeral port: by port number it would only be `info`.
+        let server = thread::spawn(move || {
+            drop(listener.accept().unwrap());
+            let (mut stream, _) = listener.accept().unwrap();
+            stream
+                .write_all(&[0xff, 0xfd, 0x18, 0xff, 0xfd, 0x20])
+                .unwrap();
+            let _ = stream.read(&mut [0u8; 64]);
+        });
+        let args = Args::parse_from([
+            "port_hawk",
+            "127.0.0.1",
+            "-p",
+            &port.to_string(),
+            "--service-detect",
+        ]);
+
+        let results = run(&args).unwrap();
+        server.join().unwrap();
+        assert_eq!(results[0].service.as_deref(), Some("telnet"));
+        assert_eq!(results[0].severity, Some(severity::Severity::Critical));
+    }
+
+    #[test]
+    fn test_quiet_banner_keeps_raw_banner_only_when_asked() {
+        let open = ScanResult {
+            banner: Some("\x1b[31m220 ready\x1b[0m\r\n".to_string()),
+            ..ScanResult::new("127.0.0.1".parse().unwrap(), 25, result::PortState::Open)
+        };
+
+        let args = Args::parse_from(["port_hawk", "127.0.0.1", "--quiet-banner"]);
+        let quiet = with_quiet_banner(&args, open.clone());
+        assert_eq!(quiet.banner.as_deref(), Some("220 ready "));
+        assert_eq!(quiet.banner_raw, None);
+
+        let args = Args::parse_from(["port_hawk", "127.0.0.1", "--quiet-banner", "--raw-banner"]);
+        let raw = with_quiet_banner(&args, open.clone());
+        assert_eq!(raw.banner.as_deref(), Some("220 ready "));
+        assert_eq!(raw.banner_raw, open.banner);
+
+        let args = Args::parse_from(["port_hawk", "127.0.0.1"]);
+        assert_eq!(with_quiet_banner(&args, open.clone()), open);
+    }
+}
@@ -0,0 +1,157 @@
+use crate::{
+    args::{
+        parser::{PortProtocols, Protocol},
+        target::normalize_address,
+    },
+    scan::jobs::Job,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    io::{self, Write},
+    net::IpAddr,
+};
+
+/// One job of a scan plan, as `--emit-plan` writes and `--plan` reads it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlanJob {
+    pub address: IpAddr,
+    pub port: u16,
+    pub protocol: Protocol,
+    /// Interface index an IPv6 link-local address is scoped to; 0 when unscoped.
+    #[serde(default, skip_serializing_if = "is_unscoped")]
+    pub scope_id: u32,
+}
+
+fn is_unscoped(scope_id: &u32) -> bool {
+    *scope_id == 0
+}
+
+impl PlanJob {
+    /// Returns the job to probe.
+    pub fn job(&self) -> Job {
+        Job {
+            address: normalize_address(self.address),
+            port: self.port,
+            scope_id: self.scope_id,
+            protocol: Some(self.protocol),
+        }
+    }
+}
+
+/// Writes `jobs` as a JSON array, one job per line, and returns how many were written.
+///
+/// Jobs are written as they come, so a huge plan is never held in memory.
+pub fn write_plan<W, I>(writer: &mut W, jobs: I) -> io::Result<usize>
+where
+    W: Write,
+    I: IntoIterator<Item = PlanJob>,
+{
+    write!(writer, "[")?;
+    let mut written = 0;
+    for job in jobs {
+        let separator = if written == 0 { "\n  " } else { ",\n  " };
+        write!(writer, "{}", separator)?;
+        serde_json::to_writer(&mut *writer, &job)?;
+        written += 1;
+    }
+    writeln!(writer, "\n]")?;
+    Ok(written)
+}
+
+/// Parses a plan written by `write_plan` (or any JSON array of `{address, port, protocol}`).
+///
+/// # Returns
+///
+/// * `Ok((Vec<Job>, PortProtocols))` - If the plan parses, returns its jobs in order, each
+///   with its protocol, and every protocol each of their ports is scanned over, like `--ports`
+///   items with a suffix.
+/// * `Err(String)` - If it is not a plan, returns an error message.
+pub fn parse_plan(contents: &str) -> Result<(Vec<Job>, PortProtocols), String> {
+    let plan: Vec<PlanJob> =
+        serde_json::from_str(contents).map_err(|e| format!("Invalid scan plan: {}", e))?;
+
+    let mut protocols: PortProtocols = Vec::new();
+    for job in &plan {
+        let tagged = ((job.port, job.port), job.protocol);
+        if !protocols.contains(&tagged) {
+            protocols.push(tagged);
+        }
+    }
+    Ok((plan.iter().map(PlanJob::job).collect(), protocols))
+}
+
+// Unit tests >------------------------------------------------------------<
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plan_job(address: &str, port: u16, protocol: Protocol) -> PlanJob {
+        PlanJob {
+            address: address.parse().unwrap(),
+            port,
+            protocol,
+            scope_id: 0,
+        }
+    }
+
+    #[test]
+    fn test_plan_round_trips_through_emit_and_read() {
+        let plan = vec![
+            plan_job("10.0.0.1", 22, Protocol::Tcp),
+            plan_job("10.0.0.1", 53, Protocol::Udp),
+            PlanJob {
+                scope_id: 3,
+                ..plan_job("fe80::1", 22, Protocol::Tcp)
+            },
+        ];
+
+        let mut buf = Vec::new();
+        assert_eq!(write_plan(&mut buf, plan.clone()).unwrap(), 3);
+        let written = String::from_utf8(buf).unwrap();
+        assert_eq!(
+            written,
+            "[\n  \
+             {\"address\":\"10.0.0.1\",\"port\":22,\"protocol\":\"tcp\"},\n  \
+             {\"address\":\"10.0.0.1\",\"port\":53,\"protocol\":\"udp\"},\n  \
+             {\"address\":\"fe80::1\",\"port\":22,\"protocol\":\"tcp\",\"scope_id\":3}\n\
+             ]\n"
+        );
+
+        let (jobs, protocols) = parse_plan(&written).unwrap();
+        assert_eq!(jobs, plan.iter().map(PlanJob::job).collect::<Vec<_>>());
+        assert_eq!(
+            protocols,
+            vec![((22, 22), Protocol::Tcp), ((53, 53), Protocol::Udp)]
+        );
+
+        let mut empty = Vec::new();
+        write_plan(&mut empty, []).unwrap();
+        assert_eq!(
+            parse_plan(std::str::from_utf8(&empty).unwrap()),
+            Ok((Vec::new(), Vec::new()))
+        );
+    }
+
+    #[test]
+    fn test_parse_plan_keeps_each_job_protocol() {
+        let mut buf = Vec::new();
+        write_plan(
+            &mut buf,
+            [
+                plan_job("10.0.0.1", 53, Protocol::Udp),
+                plan_job("10.0.0.2", 53, Protocol::Tcp),
+            ],
+        )
+        .unwrap();
+        let (jobs, protocols) = parse_plan(std::str::from_utf8(&buf).unwrap()).unwrap();
+        assert_eq!(
+            jobs.iter().map(|job| job.protocol).collect::<Vec<_>>(),
+            vec![Some(Protocol::Udp), Some(Protocol::Tcp)]
+        );
+        assert_eq!(
+            protocols,
+            vec![((53, 53), Protocol::Udp), ((53, 53), Protocol::Tcp)]
+        );
+        assert!(parse_plan("10.0.0.1:22").is_err());
+    }
+}
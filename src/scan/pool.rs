@@ -0,0 +1,584 @@
+use crate::scan::{
+    jobs::Job,
+    random::SplitMix64,
+    rate::{RateController, SlowStart},
+    result::{PortState, ScanResult},
+};
+use std::{
+    net::IpAddr,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        mpsc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// How often the rate monitor wakes to notice that the scan is over.
+const MONITOR_POLL: Duration = Duration::from_millis(10);
+
+/// Jobs a worker takes from the queue at once by default.
+///
+/// Probes spend far longer on the network than on the queue lock, so one at a time keeps even
+/// small scans spread over every worker.
+pub const DEFAULT_BATCH_SIZE: usize = 1;
+
+/// Splits a scan into windows of `size` jobs with a `pause` between them, spreading it over
+/// time instead of sending every probe in one burst.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Window {
+    pub size: usize,
+    pub pause: Duration,
+}
+
+/// A random delay of up to `max` before each probe, breaking up the regular cadence of a scan.
+///
+/// Each job's delay is derived from `seed` and the job itself rather than drawn in order, so a
+/// seeded scan waits the same before every probe however its jobs are spread over workers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Jitter {
+    pub max: Duration,
+    pub seed: u64,
+}
+
+impl Jitter {
+    /// Returns how long to wait before probing `job`, in `0..max` at microsecond resolution.
+    pub fn delay(&self, job: &Job) -> Duration {
+        let micros = self.max.as_micros() as u64;
+        if micros == 0 {
+            return Duration::ZERO;
+        }
+        let address = match job.address {
+            IpAddr::V4(address) => u32::from(address) as u128,
+            IpAddr::V6(address) => u128::from(address),
+        };
+        let key = (address as u64) ^ ((address >> 64) as u64).rotate_left(17);
+        let key = key ^ (u64::from(job.port) << 32 | u64::from(job.scope_id));
+        let draw = SplitMix64::new(self.seed ^ SplitMix64::new(key).next_u64()).next_u64();
+        Duration::from_micros(draw % micros)
+    }
+}
+
+/// A pool of worker threads pulling jobs from a shared queue.
+///
+/// The pool starts with a fixed number of workers and, with a `RateController`, adds more
+/// while throughput stays below the controller's floor. With a `SlowStart`, only some of the
+/// workers pull jobs at first, and the controller decides how many as the scan goes.
+#[derive(Debug, Clone, Copy)]
+pub struct WorkerPool {
+    workers: usize,
+    rate: Option<RateController>,
+    slow_start: Option<SlowStart>,
+    batch_size: usize,
+    max_open: Option<usize>,
+    window: Option<Window>,
+    jitter: Option<Jitter>,
+}
+
+impl WorkerPool {
+    /// Creates a pool with the given number of workers (at least one).
+    pub fn new(workers: usize) -> Self {
+        Self {
+            workers: workers.max(1),
+            rate: None,
+            slow_start: None,
+            batch_size: DEFAULT_BATCH_SIZE,
+            max_open: None,
+            window: None,
+            jitter: None,
+        }
+    }
+
+    /// Stops the pool once `max_open` open ports have been found.
+    ///
+    /// Workers count open results on a shared counter; the one that reaches the cap stops every
+    /// worker from pulling new jobs, and open results of probes still in flight are dropped, so
+    /// no more than `max_open` open ports are ever returned.
+    pub fn with_max_open(self, max_open: usize) -> Self {
+        Self {
+            max_open: Some(max_open),
+            ..self
+        }
+    }
+
+    /// Makes each worker take `batch_size` jobs (at least one) per trip to the shared queue.
+    ///
+    /// Larger batches mean less contention on the queue lock in huge scans, but a worker holds
+    /// on to its whole batch, so the last jobs are spread over fewer workers.
+    pub fn with_batch_size(self, batch_size: usize) -> Self {
+        Self {
+            batch_size: batch_size.max(1),
+            ..self
+        }
+    }
+
+    /// Hands out jobs in windows of `window.size` (at least one), holding every worker back for
+    /// `window.pause` once a window is handed out.
+    ///
+    /// The pause starts when the last job of a window leaves the queue, so probes of that window
+    /// still in flight finish during it.
+    pub fn with_window(self, window: Window) -> Self {
+        Self {
+            window: Some(Window {
+                size: window.size.max(1),
+                ..window
+            }),
+            ..self
+        }
+    }
+
+    /// Makes each worker wait `jitter.delay` of the job before probing it.
+    ///
+    /// The wait holds back only that worker, so it composes with windows and rate control:
+    /// those decide when jobs are handed out, the jitter when each one is sent.
+    pub fn with_jitter(self, jitter: Jitter) -> Self {
+        Self {
+            jitter: Some(jitter),
+            ..self
+        }
+    }
+
+    /// Lets the pool grow past its initial size to keep throughput above the controller's floor.
+    pub fn with_min_rate(self, rate: RateController) -> Self {
+        Self {
+            rate: Some(rate),
+            ..self
+        }
+    }
+
+    /// Ramps up from the controller's initial concurrency to the pool size instead of starting at
+    /// full size, backing off while too many probes fail.
+    pub fn with_slow_start(self, slow_start: SlowStart) -> Self {
+        Self {
+            slow_start: Some(SlowStart {
+                ceiling: self.workers,
+                ..slow_start
+            }),
+            ..self
+        }
+    }
+
+    /// Runs `probe` over every job and returns the results in completion order.
+    ///
+    /// Workers stop pulling new jobs once `stop` is set; in-flight probes finish and their
+    /// results are kept, while the rest of each worker's batch is dropped. All workers have
+    /// exited by the time this returns.
+    pub fn run<I, F>(&self, jobs: I, stop: &AtomicBool, probe: F) -> Vec<ScanResult>
+    where
+        I: Iterator<Item = Job> + Send,
+        F: Fn(&Job) -> ScanResult + Sync,
+    {
+        let (tx, rx) = mpsc::channel();
+        self.stream(jobs, stop, probe, tx);
+        rx.into_iter().collect()
+    }
+
+    /// Runs `probe` over every job, sending each result on `tx` as soon as it is ready.
+    ///
+    /// Stops like `run`, and also once the receiving end has been dropped. Returns after all
+    /// workers have exited, at which point `tx` and every clone of it are gone.
+    pub fn stream<I, F>(&self, jobs: I, stop: &AtomicBool, probe: F, tx: mpsc::Sender<ScanResult>)
+    where
+        I: Iterator<Item = Job> + Send,
+        F: Fn(&Job) -> ScanResult + Sync,
+    {
+        let jobs = Mutex::new(Windowed {
+            jobs,
+            window: self.window,
+            handed_out: 0,
+            stop,
+        });
+        let (completed, failed) = (AtomicU64::new(0), AtomicU64::new(0));
+        let open = AtomicUsize::new(0);
+        let drained = AtomicBool::new(false);
+        let active = AtomicUsize::new(self.slow_start.map_or(usize::MAX, |s| s.initial));
+
+        thread::scope(|s| {
+            let worker = |index: usize, tx: mpsc::Sender<ScanResult>| {
+                let (jobs, probe, completed, failed, drained, active, open) =
+                    (&jobs, &probe, &completed, &failed, &drained, &active, &open);
+                let (batch_size, max_open, jitter) = (self.batch_size, self.max_open, self.jitter);
+                move || 'pull: loop {
+                    if stop.load(Ordering::Relaxed) || drained.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    if index >= active.load(Ordering::Relaxed) {
+                        thread::sleep(MONITOR_POLL);
+                        continue;
+                    }
+                    let batch: Vec<Job> = jobs.lock().unwrap().by_ref().take(batch_size).collect();
+                    if batch.len() < batch_size {
+                        drained.store(true, Ordering::Relaxed);
+                    }
+                    if batch.is_empty() {
+                        break;
+                    }
+
+                    for job in batch {
+                        if stop.load(Ordering::Relaxed)
+                            || max_open.is_some_and(|max| open.load(Ordering::Relaxed) >= max)
+                        {
+                            break 'pull;
+                        }
+                        if let Some(jitter) = jitter {
+                            sleep_unless_stopped(jitter.delay(&job), stop);
+                            if stop.load(Ordering::Relaxed) {
+                                break 'pull;
+                            }
+                        }
+                        let result = probe(&job);
+                        if let (Some(max), PortState::Open) = (max_open, result.state) {
+                            let found = open.fetch_add(1, Ordering::Relaxed) + 1;
+                            if found > max {
+                                break 'pull;
+                            }
+                            if found == max {
+                                // No more jobs are taken once the cap is hit, as if the queue ran dry.
+                                drained.store(true, Ordering::Relaxed);
+                            }
+                        }
+                        let unhealthy =
+                            matches!(result.state, PortState::Filtered | PortState::Error);
+                        if tx.send(result).is_err() {
+                            break 'pull;
+                        }
+                        if unhealthy {
+                            failed.fetch_add(1, Ordering::Relaxed);
+                        }
+                        completed.fetch_add(1, Ordering::Relaxed);
+                    }
+                }
+            };
+
+            for index in 0..self.workers {
+                s.spawn(worker(index, tx.clone()));
+            }
+
+            if let Some(slow_start) = self.slow_start {
+                let (completed, failed, drained, active) = (&completed, &failed, &drained, &active);
+                s.spawn(move || {
+                    let mut window = (Instant::now(), 0, 0);
+                    while !stop.load(Ordering::Relaxed) && !drained.load(Ordering::Relaxed) {
+                        thread::sleep(MONITOR_POLL);
+                        if window.0.elapsed() < slow_start.interval {
+                            continue;
+                        }
+
+                        let (done, bad) = (
+                            completed.load(Ordering::Relaxed),
+                            failed.load(Ordering::Relaxed),
+                        );
+                        let next = slow_start.next_concurrency(
+                            active.load(Ordering::Relaxed),
+                            done - window.1,
+                            bad - window.2,
+                        );
+                        active.store(next, Ordering::Relaxed);
+                        window = (Instant::now(), done, bad);
+                    }
+                });
+            }
+
+            if let Some(rate) = self.rate {
+                let (tx, completed, drained) = (tx.clone(), &completed, &drained);
+                let mut workers = self.workers;
+                s.spawn(move || {
+                    let (mut window_start, mut window_completed) = (Instant::now(), 0);
+                    while !stop.load(Ordering::Relaxed) && !drained.load(Ordering::Relaxed) {
+                        thread::sleep(MONITOR_POLL);
+                        if window_start.elapsed() < rate.interval {
+                            continue;
+                        }
+
+                        let done = completed.load(Ordering::Relaxed);
+                        let target = rate.next_concurrency(
+                            workers,
+                            done - window_completed,
+                            window_start.elapsed(),
+                        );
+                        for index in workers..target {
+                            s.spawn(worker(index, tx.clone()));
+                        }
+                        workers = target;
+                        (window_start, window_completed) = (Instant::now(), done);
+                    }
+                });
+            }
+        });
+    }
+}
+
+/// The pool's job queue: passes jobs through, pausing between windows when one is set.
+///
+/// Workers pull from it under the queue lock, so a pause holds all of them back at once.
+struct Windowed<'a, I> {
+    jobs: I,
+    window: Option<Window>,
+    handed_out: usize,
+    stop: &'a AtomicBool,
+}
+
+impl<I: Iterator<Item = Job>> Iterator for Windowed<'_, I> {
+    type Item = Job;
+
+    fn next(&mut self) -> Option<Job> {
+        let job = self.jobs.next()?;
+        if let Some(window) = self.window {
+            if self.handed_out > 0 && self.handed_out % window.size == 0 {
+                sleep_unless_stopped(window.pause, self.stop);
+            }
+            self.handed_out += 1;
+        }
+        Some(job)
+    }
+}
+
+/// Sleeps for `duration`, in short steps so that a stopped scan ends without sitting it out.
+fn sleep_unless_stopped(duration: Duration, stop: &AtomicBool) {
+    let resume = Instant::now() + duration;
+    while !stop.load(Ordering::Relaxed) && Instant::now() < resume {
+        thread::sleep(MONITOR_POLL.min(resume.saturating_duration_since(Instant::now())));
+    }
+}
+
+// Unit tests >------------------------------------------------------------<
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scan::result::PortState;
+
+    #[test]
+    fn test_worker_pool_runs_every_job() {
+        let jobs = (1..=100).map(|port| Job {
+            address: "127.0.0.1".parse().unwrap(),
+            port,
+            scope_id: 0,
+            protocol: None,
+        });
+        let stop = AtomicBool::new(false);
+        let results = WorkerPool::new(4).run(jobs, &stop, |job| {
+            ScanResult::new(job.address, job.port, PortState::Closed)
+        });
+
+        let mut ports: Vec<u16> = results.iter().map(|r| r.port).collect();
+        ports.sort_unstable();
+        assert_eq!(ports, (1..=100).collect::<Vec<u16>>());
+    }
+
+    #[test]
+    fn test_worker_pool_stops_when_flagged() {
+        let jobs = (1..=100).map(|port| Job {
+            address: "127.0.0.1".parse().unwrap(),
+            port,
+            scope_id: 0,
+            protocol: None,
+        });
+        let stop = AtomicBool::new(false);
+        let results = WorkerPool::new(1).run(jobs, &stop, |job| {
+            if job.port == 10 {
+                stop.store(true, Ordering::Relaxed);
+            }
+            ScanResult::new(job.address, job.port, PortState::Closed)
+        });
+
+        assert_eq!(results.len(), 10);
+    }
+
+    #[test]
+    fn test_worker_pool_stops_after_max_open() {
+        let jobs = (1..=1000).map(|port| Job {
+            address: "127.0.0.1".parse().unwrap(),
+            port,
+            scope_id: 0,
+            protocol: None,
+        });
+        let probed = AtomicUsize::new(0);
+        let stop = AtomicBool::new(false);
+        let results = WorkerPool::new(4).with_max_open(5).run(jobs, &stop, |job| {
+            probed.fetch_add(1, Ordering::Relaxed);
+            thread::sleep(Duration::from_millis(1));
+            let state = if job.port % 2 == 0 {
+                PortState::Open
+            } else {
+                PortState::Closed
+            };
+            ScanResult::new(job.address, job.port, state)
+        });
+
+        let open = results
+            .iter()
+            .filter(|r| r.state == PortState::Open)
+            .count();
+        assert_eq!(open, 5);
+        // Only the probes in flight when the cap was hit ran past it.
+        assert!(probed.load(Ordering::Relaxed) < 20);
+    }
+
+    #[test]
+    fn test_worker_pool_grows_below_min_rate() {
+        let jobs = (1..=200).map(|port| Job {
+            address: "127.0.0.1".parse().unwrap(),
+            port,
+            scope_id: 0,
+            protocol: None,
+        });
+        let (in_flight, peak) = (AtomicU64::new(0), AtomicU64::new(0));
+        let rate = RateController {
+            interval: Duration::from_millis(50),
+            ..RateController::new(1000.0)
+        };
+        let stop = AtomicBool::new(false);
+
+        let results = WorkerPool::new(1)
+            .with_min_rate(rate)
+            .run(jobs, &stop, |job| {
+                let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                thread::sleep(Duration::from_millis(10));
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                ScanResult::new(job.address, job.port, PortState::Closed)
+            });
+
+        assert_eq!(results.len(), 200);
+        assert!(peak.load(Ordering::SeqCst) > 1);
+    }
+
+    #[test]
+    fn test_worker_pool_slow_start_ramps_up() {
+        let jobs = (1..=300).map(|port| Job {
+            address: "127.0.0.1".parse().unwrap(),
+            port,
+            scope_id: 0,
+            protocol: None,
+        });
+        let (in_flight, first_peak, peak) =
+            (AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0));
+        let slow_start = SlowStart {
+            initial: 2,
+            interval: Duration::from_millis(60),
+            ..SlowStart::new(16)
+        };
+        let stop = AtomicBool::new(false);
+
+        let results = WorkerPool::new(16)
+            .with_slow_start(slow_start)
+            .run(jobs, &stop, |job| {
+                let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                if job.port <= 10 {
+                    first_peak.fetch_max(now, Ordering::SeqCst);
+                }
+                thread::sleep(Duration::from_millis(5));
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                ScanResult::new(job.address, job.port, PortState::Closed)
+            });
+
+        assert_eq!(results.len(), 300);
+        // The first jobs ran two at a time; by the end the pool was working at full size.
+        assert!(first_peak.load(Ordering::SeqCst) <= 2);
+        assert!(peak.load(Ordering::SeqCst) > 2);
+    }
+
+    #[test]
+    fn test_worker_pool_pulls_in_batches() {
+        let pulls = Mutex::new(Vec::new());
+        let jobs = (1..=100)
+            .map(|port| Job {
+                address: "127.0.0.1".parse().unwrap(),
+                port,
+                scope_id: 0,
+                protocol: None,
+            })
+            .inspect(|_| pulls.lock().unwrap().push(thread::current().id()));
+        let stop = AtomicBool::new(false);
+
+        let results = WorkerPool::new(4)
+            .with_batch_size(10)
+            .run(jobs, &stop, |job| {
+                thread::sleep(Duration::from_millis(1));
+                ScanResult::new(job.address, job.port, PortState::Closed)
+            });
+
+        assert_eq!(results.len(), 100);
+        // The queue is only advanced under its lock, so every batch is one worker's run of ten.
+        let pulls = pulls.into_inner().unwrap();
+        assert_eq!(pulls.len(), 100);
+        assert!(pulls
+            .chunks(10)
+            .all(|batch| batch.iter().all(|id| *id == batch[0])));
+    }
+
+    #[test]
+    fn test_worker_pool_pauses_between_windows() {
+        let started = Mutex::new(Vec::new());
+        let jobs = (1..=15).map(|port| Job {
+            address: "127.0.0.1".parse().unwrap(),
+            port,
+            scope_id: 0,
+            protocol: None,
+        });
+        let window = Window {
+            size: 5,
+            pause: Duration::from_millis(80),
+        };
+        let stop = AtomicBool::new(false);
+
+        let results = WorkerPool::new(4)
+            .with_window(window)
+            .run(jobs, &stop, |job| {
+                started.lock().unwrap().push((job.port, Instant::now()));
+                ScanResult::new(job.address, job.port, PortState::Closed)
+            });
+
+        assert_eq!(results.len(), 15);
+        let mut started = started.into_inner().unwrap();
+        started.sort_unstable_by_key(|(port, _)| *port);
+        let windows: Vec<(Instant, Instant)> = started
+            .chunks(5)
+            .map(|window| {
+                let times = window.iter().map(|(_, at)| *at);
+                (times.clone().min().unwrap(), times.max().unwrap())
+            })
+            .collect();
+        // Every window starts a full pause after the previous one was handed out.
+        for pair in windows.windows(2) {
+            assert!(pair[1].0 - pair[0].1 >= Duration::from_millis(70));
+        }
+    }
+
+    #[test]
+    fn test_jitter_delays_are_bounded_and_seeded() {
+        let jobs: Vec<Job> = ["10.0.0.1", "fd00::1"]
+            .iter()
+            .flat_map(|address| {
+                (1..=500).map(move |port| Job {
+                    address: address.parse().unwrap(),
+                    port,
+                    scope_id: 0,
+                    protocol: None,
+                })
+            })
+            .collect();
+        let jitter = Jitter {
+            max: Duration::from_millis(50),
+            seed: 7,
+        };
+        let delays = |jitter: Jitter| jobs.iter().map(|job| jitter.delay(job)).collect::<Vec<_>>();
+
+        let first = delays(jitter);
+        assert!(first.iter().all(|delay| *delay < Duration::from_millis(50)));
+        // Spread over the range rather than clustered at one end.
+        assert!(first.iter().any(|delay| *delay < Duration::from_millis(10)));
+        assert!(first.iter().any(|delay| *delay > Duration::from_millis(40)));
+        assert_eq!(delays(jitter), first);
+        assert_ne!(delays(Jitter { seed: 8, ..jitter }), first);
+        assert_eq!(
+            Jitter {
+                max: Duration::ZERO,
+                ..jitter
+            }
+            .delay(&jobs[0]),
+            Duration::ZERO
+        );
+    }
+}
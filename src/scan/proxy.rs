@@ -0,0 +1,517 @@
+use crate::scan::{
+    banner::{decode_banner, read_banner, BannerOptions},
+    jobs::Job,
+    result::{PortState, ScanResult},
+};
+use std::{
+    fmt,
+    io::{self, Read, Write},
+    net::{IpAddr, SocketAddr, TcpStream, ToSocketAddrs},
+    time::{Duration, Instant},
+};
+
+const SOCKS_VERSION: u8 = 0x05;
+const NO_AUTHENTICATION: u8 = 0x00;
+const CMD_CONNECT: u8 = 0x01;
+const ATYP_IPV4: u8 = 0x01;
+const ATYP_DOMAIN: u8 = 0x03;
+const ATYP_IPV6: u8 = 0x04;
+
+const REPLY_SUCCEEDED: u8 = 0x00;
+const REPLY_CONNECTION_REFUSED: u8 = 0x05;
+
+/// HTTP proxies answer a CONNECT to a target that refused the connection with one of these.
+const HTTP_BAD_GATEWAY: u16 = 502;
+const HTTP_SERVICE_UNAVAILABLE: u16 = 503;
+const HTTP_PROXY_AUTHENTICATION_REQUIRED: u16 = 407;
+
+/// Upper bound on the size of an HTTP proxy's response head.
+const MAX_HTTP_HEAD_BYTES: usize = 8192;
+
+/// Represents a proxy that connections are tunneled through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Proxy {
+    Socks5(SocketAddr),
+    /// An HTTP proxy that tunnels with `CONNECT`.
+    Http(SocketAddr),
+}
+
+impl Proxy {
+    fn addr(&self) -> SocketAddr {
+        match self {
+            Proxy::Socks5(addr) | Proxy::Http(addr) => *addr,
+        }
+    }
+}
+
+/// Represents how a proxy turned down a request to open a tunnel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Refusal {
+    /// A SOCKS5 reply code other than "succeeded".
+    Socks5(u8),
+    /// An HTTP `CONNECT` response status outside 2xx.
+    Http(u16),
+}
+
+impl Refusal {
+    /// Returns the state of a port the proxy could not reach: `Closed` if the proxy's own
+    /// connect was refused, `Filtered` for anything else (timeouts, policy denials).
+    pub fn state(&self) -> PortState {
+        match self {
+            Refusal::Socks5(REPLY_CONNECTION_REFUSED)
+            | Refusal::Http(HTTP_BAD_GATEWAY | HTTP_SERVICE_UNAVAILABLE) => PortState::Closed,
+            _ => PortState::Filtered,
+        }
+    }
+}
+
+impl fmt::Display for Refusal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Refusal::Socks5(reply) => write!(f, "reply {}", reply),
+            Refusal::Http(status) => write!(f, "status {}", status),
+        }
+    }
+}
+
+/// Represents why a connection through a proxy chain did not reach the target.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChainError {
+    /// The chain itself broke at the given 0-based hop, before the target was asked for.
+    Hop { hop: usize, reason: String },
+    /// The last proxy tried the target and turned the tunnel down.
+    Target(Refusal),
+}
+
+/// Parses a proxy URL such as `socks5://127.0.0.1:1080` or `http://proxy.corp:3128`,
+/// resolving a hostname if needed.
+///
+/// # Returns
+///
+/// * `Ok(Proxy)` - If the scheme is supported and the address resolves.
+/// * `Err(String)` - If the parsing fails, returns an error message.
+pub fn parse_proxy(url: &str) -> Result<Proxy, String> {
+    let unsupported = || format!("Unsupported proxy scheme: {}", url);
+    let (scheme, addr) = url.split_once("://").ok_or_else(unsupported)?;
+    let proxy: fn(SocketAddr) -> Proxy = match scheme {
+        "socks5" => Proxy::Socks5,
+        "http" => Proxy::Http,
+        _ => return Err(unsupported()),
+    };
+
+    addr.trim_end_matches('/')
+        .to_socket_addrs()
+        .ok()
+        .and_then(|mut addrs| addrs.next())
+        .map(proxy)
+        .ok_or_else(|| format!("Invalid proxy address: {}", url))
+}
+
+/// Opens a stream to `target` that traverses every proxy of `proxies`, in order.
+///
+/// The first proxy is connected to directly; each following proxy, and finally the target,
+/// is reached with a SOCKS5 or HTTP CONNECT, whichever the proxy before it speaks, sent
+/// through the tunnel built so far. `timeout` bounds the initial connect and every handshake
+/// read and write.
+pub fn connect_chain(
+    proxies: &[Proxy],
+    target: SocketAddr,
+    timeout: Duration,
+) -> Result<TcpStream, ChainError> {
+    let hop_error = |hop: usize| {
+        move |e: std::io::Error| ChainError::Hop {
+            hop,
+            reason: e.to_string(),
+        }
+    };
+
+    let first = proxies.first().ok_or_else(|| ChainError::Hop {
+        hop: 0,
+        reason: "empty proxy chain".to_string(),
+    })?;
+    let mut stream = TcpStream::connect_timeout(&first.addr(), timeout).map_err(hop_error(0))?;
+    stream
+        .set_read_timeout(Some(timeout))
+        .and_then(|_| stream.set_write_timeout(Some(timeout)))
+        .map_err(hop_error(0))?;
+
+    for (hop, pair) in proxies.windows(2).enumerate() {
+        let (via, next) = (&pair[0], &pair[1]);
+        if let Err(refusal) = open_tunnel(&mut stream, via, next.addr()).map_err(hop_error(hop))? {
+            return Err(ChainError::Hop {
+                hop,
+                reason: format!("proxy refused next hop {} ({})", next.addr(), refusal),
+            });
+        }
+    }
+
+    let last = proxies.len() - 1;
+    match open_tunnel(&mut stream, &proxies[last], target).map_err(hop_error(last))? {
+        Ok(()) => Ok(stream),
+        Err(refusal) => Err(ChainError::Target(refusal)),
+    }
+}
+
+/// Asks `proxy`, which `stream` leads to, to open a tunnel to `target`.
+///
+/// The outer `Err` means the handshake itself failed; the inner one that the proxy answered
+/// but turned the tunnel down.
+fn open_tunnel(
+    stream: &mut TcpStream,
+    proxy: &Proxy,
+    target: SocketAddr,
+) -> io::Result<Result<(), Refusal>> {
+    Ok(match proxy {
+        Proxy::Socks5(_) => match socks5_connect(stream, target)? {
+            REPLY_SUCCEEDED => Ok(()),
+            reply => Err(Refusal::Socks5(reply)),
+        },
+        Proxy::Http(_) => match http_connect(stream, target)? {
+            200..=299 => Ok(()),
+            status => Err(Refusal::Http(status)),
+        },
+    })
+}
+
+/// Probes a single TCP port through a proxy chain and classifies the outcome.
+///
+/// A successful tunnel is `Open`, a refusal the proxy got from the target (SOCKS5 "connection
+/// refused", HTTP 502 or 503) is `Closed`, any other refusal is `Filtered`, and a broken chain
+/// is `Error`.
+pub fn probe(
+    job: &Job,
+    proxies: &[Proxy],
+    timeout: Duration,
+    banner: Option<&BannerOptions>,
+) -> ScanResult {
+    let start = Instant::now();
+    let result = ScanResult::new(job.address, job.port, PortState::Open);
+
+    match connect_chain(proxies, SocketAddr::new(job.address, job.port), timeout) {
+        Ok(mut stream) => {
+            let latency = start.elapsed();
+            let banner = banner.and_then(|options| {
+                read_banner(&mut stream, options, timeout)
+                    .map(|raw| decode_banner(&raw, options.charset))
+            });
+            ScanResult {
+                latency: Some(latency),
+                detail: Some(format!("tunnel opened after {}ms", latency.as_millis())),
+                banner_binary: banner.as_ref().is_some_and(|banner| banner.binary),
+                banner: banner.map(|banner| banner.text),
+                ..result
+            }
+        }
+        Err(ChainError::Target(refusal)) => {
+            let latency = start.elapsed();
+            ScanResult {
+                state: refusal.state(),
+                latency: Some(latency),
+                detail: Some(format!(
+                    "proxy answered {} after {}ms",
+                    refusal,
+                    latency.as_millis()
+                )),
+                ..result
+            }
+        }
+        Err(ChainError::Hop { hop, reason }) => ScanResult {
+            state: PortState::Error,
+            error: Some(format!("proxy hop {}: {}", hop + 1, reason)),
+            ..result
+        },
+    }
+}
+
+/// Sends an HTTP CONNECT for `target` on `stream` and returns the response status.
+///
+/// The response head is read a byte at a time so that nothing the target sends through the
+/// tunnel is consumed with it. A proxy that asks for credentials fails the handshake.
+fn http_connect(stream: &mut TcpStream, target: SocketAddr) -> io::Result<u16> {
+    stream.write_all(format!("CONNECT {0} HTTP/1.1\r\nHost: {0}\r\n\r\n", target).as_bytes())?;
+
+    let mut head = Vec::new();
+    let mut byte = [0u8; 1];
+    while !head.ends_with(b"\r\n\r\n") {
+        if head.len() >= MAX_HTTP_HEAD_BYTES {
+            return Err(io::Error::other("HTTP proxy response too large"));
+        }
+        stream.read_exact(&mut byte)?;
+        head.push(byte[0]);
+    }
+
+    // "HTTP/1.1 200 Connection established"
+    let status = std::str::from_utf8(&head)
+        .ok()
+        .and_then(|head| head.strip_prefix("HTTP/1."))
+        .and_then(|rest| rest.get(2..5))
+        .and_then(|status| status.parse().ok())
+        .ok_or_else(|| io::Error::other("malformed HTTP proxy response"))?;
+    if status == HTTP_PROXY_AUTHENTICATION_REQUIRED {
+        return Err(io::Error::other("proxy requires authentication"));
+    }
+    Ok(status)
+}
+
+/// Performs a SOCKS5 no-auth greeting and CONNECT on `stream`, returning the reply code.
+fn socks5_connect(stream: &mut TcpStream, target: SocketAddr) -> std::io::Result<u8> {
+    stream.write_all(&[SOCKS_VERSION, 1, NO_AUTHENTICATION])?;
+    let mut choice = [0u8; 2];
+    stream.read_exact(&mut choice)?;
+    if choice != [SOCKS_VERSION, NO_AUTHENTICATION] {
+        return Err(std::io::Error::other(
+            "proxy requires unsupported authentication",
+        ));
+    }
+
+    let mut request = vec![SOCKS_VERSION, CMD_CONNECT, 0x00];
+    match target.ip() {
+        IpAddr::V4(v4) => {
+            request.push(ATYP_IPV4);
+            request.extend_from_slice(&v4.octets());
+        }
+        IpAddr::V6(v6) => {
+            request.push(ATYP_IPV6);
+            request.extend_from_slice(&v6.octets());
+        }
+    }
+    request.extend_from_slice(&target.port().to_be_bytes());
+    stream.write_all(&request)?;
+
+    // VER REP RSV ATYP, then the bound address and port, which are read and discarded.
+    let mut reply = [0u8; 4];
+    stream.read_exact(&mut reply)?;
+    let address_len = match reply[3] {
+        ATYP_IPV4 => 4,
+        ATYP_IPV6 => 16,
+        ATYP_DOMAIN => {
+            let mut len = [0u8; 1];
+            stream.read_exact(&mut len)?;
+            len[0] as usize
+        }
+        _ => return Err(std::io::Error::other("malformed SOCKS5 reply")),
+    };
+    let mut bound = vec![0u8; address_len + 2];
+    stream.read_exact(&mut bound)?;
+
+    Ok(reply[1])
+}
+
+// Unit tests >------------------------------------------------------------<
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        io,
+        net::{Ipv4Addr, Shutdown, TcpListener},
+        sync::mpsc::{self, Receiver},
+        thread,
+    };
+
+    /// A minimal SOCKS5 server for one connection; it reports the CONNECT target it was asked
+    /// for and, if `forward` is set, connects there and relays bytes both ways.
+    fn mock_proxy(forward: bool) -> (Proxy, Receiver<SocketAddr>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let proxy = Proxy::Socks5(listener.local_addr().unwrap());
+        let (tx, rx) = mpsc::channel();
+
+        thread::spawn(move || {
+            let (mut client, _) = listener.accept().unwrap();
+            let mut greeting = [0u8; 3];
+            client.read_exact(&mut greeting).unwrap();
+            client
+                .write_all(&[SOCKS_VERSION, NO_AUTHENTICATION])
+                .unwrap();
+
+            let mut request = [0u8; 10];
+            client.read_exact(&mut request).unwrap();
+            let ip = Ipv4Addr::new(request[4], request[5], request[6], request[7]);
+            let target =
+                SocketAddr::new(IpAddr::V4(ip), u16::from_be_bytes([request[8], request[9]]));
+            tx.send(target).unwrap();
+
+            let upstream = if forward {
+                TcpStream::connect(target).ok()
+            } else {
+                None
+            };
+            let reply = match (&upstream, forward) {
+                (Some(_), _) => REPLY_SUCCEEDED,
+                (None, true) => REPLY_CONNECTION_REFUSED,
+                (None, false) => 0x01,
+            };
+            client
+                .write_all(&[SOCKS_VERSION, reply, 0, ATYP_IPV4, 0, 0, 0, 0, 0, 0])
+                .unwrap();
+
+            if let Some(upstream) = upstream {
+                let (mut client_read, mut upstream_write) =
+                    (client.try_clone().unwrap(), upstream.try_clone().unwrap());
+                thread::spawn(move || io::copy(&mut client_read, &mut upstream_write));
+                let (mut upstream_read, mut client_write) = (upstream, client);
+                let _ = io::copy(&mut upstream_read, &mut client_write);
+                let _ = client_write.shutdown(Shutdown::Write);
+            }
+        });
+
+        (proxy, rx)
+    }
+
+    /// A minimal HTTP proxy for one connection; it reports the request head it got and answers
+    /// with `status`, relaying bytes from the target after a 200.
+    fn mock_http_proxy(status: u16) -> (Proxy, Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let proxy = Proxy::Http(listener.local_addr().unwrap());
+        let (tx, rx) = mpsc::channel();
+
+        thread::spawn(move || {
+            let (mut client, _) = listener.accept().unwrap();
+            let mut head = Vec::new();
+            let mut byte = [0u8; 1];
+            while !head.ends_with(b"\r\n\r\n") {
+                client.read_exact(&mut byte).unwrap();
+                head.push(byte[0]);
+            }
+            let head = String::from_utf8(head).unwrap();
+            let target: SocketAddr = head.split(' ').nth(1).unwrap().parse().unwrap();
+            tx.send(head).unwrap();
+
+            write!(client, "HTTP/1.1 {} Whatever\r\nVia: mock\r\n\r\n", status).unwrap();
+            if status == 200 {
+                let mut upstream = TcpStream::connect(target).unwrap();
+                let _ = io::copy(&mut upstream, &mut client);
+            }
+        });
+
+        (proxy, rx)
+    }
+
+    #[test]
+    fn test_parse_proxy() {
+        assert_eq!(
+            parse_proxy("socks5://127.0.0.1:1080"),
+            Ok(Proxy::Socks5("127.0.0.1:1080".parse().unwrap()))
+        );
+        assert_eq!(
+            parse_proxy("http://127.0.0.1:3128/"),
+            Ok(Proxy::Http("127.0.0.1:3128".parse().unwrap()))
+        );
+        assert_eq!(
+            parse_proxy("ftp://127.0.0.1:21"),
+            Err("Unsupported proxy scheme: ftp://127.0.0.1:21".to_string())
+        );
+        assert_eq!(
+            parse_proxy("socks5://nowhere"),
+            Err("Invalid proxy address: socks5://nowhere".to_string())
+        );
+    }
+
+    #[test]
+    fn test_connect_chain_two_hops() {
+        let target = TcpListener::bind("127.0.0.1:0").unwrap();
+        let target_addr = target.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = target.accept().unwrap();
+            stream.write_all(b"hello through two hops").unwrap();
+        });
+
+        let (second, second_rx) = mock_proxy(true);
+        let (first, first_rx) = mock_proxy(true);
+
+        let mut stream =
+            connect_chain(&[first, second], target_addr, Duration::from_secs(2)).unwrap();
+        let mut received = String::new();
+        stream.read_to_string(&mut received).unwrap();
+        server.join().unwrap();
+
+        assert_eq!(first_rx.recv().unwrap(), second.addr());
+        assert_eq!(second_rx.recv().unwrap(), target_addr);
+        assert_eq!(received, "hello through two hops");
+    }
+
+    #[test]
+    fn test_probe_hop_failure_is_error() {
+        let (second, _second_rx) = mock_proxy(true);
+        let (first, first_rx) = mock_proxy(false);
+        let job = Job {
+            address: "127.0.0.1".parse().unwrap(),
+            port: 80,
+            scope_id: 0,
+            protocol: None,
+        };
+
+        let result = probe(&job, &[first, second], Duration::from_secs(2), None);
+
+        assert_eq!(first_rx.recv().unwrap(), second.addr());
+        assert_eq!(result.state, PortState::Error);
+        assert!(result.error.unwrap().starts_with("proxy hop 1:"));
+    }
+
+    #[test]
+    fn test_probe_refused_target_is_closed() {
+        let closed_port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let (proxy, _rx) = mock_proxy(true);
+        let job = Job {
+            address: "127.0.0.1".parse().unwrap(),
+            port: closed_port,
+            scope_id: 0,
+            protocol: None,
+        };
+
+        let result = probe(&job, &[proxy], Duration::from_secs(2), None);
+        assert_eq!(result.state, PortState::Closed);
+    }
+
+    #[test]
+    fn test_http_connect_tunnel_is_open() {
+        let target = TcpListener::bind("127.0.0.1:0").unwrap();
+        let target_addr = target.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = target.accept().unwrap();
+            stream.write_all(b"SSH-2.0-OpenSSH_9.6\r\n").unwrap();
+        });
+        let (proxy, rx) = mock_http_proxy(200);
+        let job = Job {
+            address: target_addr.ip(),
+            port: target_addr.port(),
+            scope_id: 0,
+            protocol: None,
+        };
+
+        let banner = BannerOptions::default();
+        let result = probe(&job, &[proxy], Duration::from_secs(2), Some(&banner));
+        server.join().unwrap();
+
+        assert_eq!(
+            rx.recv().unwrap(),
+            format!("CONNECT {0} HTTP/1.1\r\nHost: {0}\r\n\r\n", target_addr)
+        );
+        assert_eq!(result.state, PortState::Open);
+        assert_eq!(result.banner.as_deref(), Some("SSH-2.0-OpenSSH_9.6\r\n"));
+    }
+
+    #[test]
+    fn test_http_connect_refusals_map_to_states() {
+        let job = Job {
+            address: "127.0.0.1".parse().unwrap(),
+            port: 22,
+            scope_id: 0,
+            protocol: None,
+        };
+        for (status, state) in [
+            (502, PortState::Closed),
+            (503, PortState::Closed),
+            (504, PortState::Filtered),
+            (403, PortState::Filtered),
+            (407, PortState::Error),
+        ] {
+            let (proxy, _rx) = mock_http_proxy(status);
+            let result = probe(&job, &[proxy], Duration::from_secs(2), None);
+            assert_eq!(result.state, state, "status {}", status);
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use std::time::Duration;
+
+/// Upper bound on the concurrency `--min-rate` may grow a scan to.
+pub const MAX_CONCURRENCY: usize = 2048;
+
+/// How often the measured probe rate is compared against the floor.
+pub const RATE_CHECK_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Concurrency a `--slow-start` scan begins with.
+pub const SLOW_START_CONCURRENCY: usize = 8;
+
+/// How often a slow-start scan reconsiders its concurrency.
+pub const SLOW_START_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Share of failed probes in a window above which a slow-start scan backs off.
+pub const MAX_FAILURE_RATIO: f64 = 0.2;
+
+/// A feedback controller that raises concurrency while throughput is below a floor.
+///
+/// Slow probes (usually timeouts on filtered ports) hold workers for the full timeout, so the
+/// only way to keep the rate up is to have more of them in flight.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateController {
+    /// Minimum probes per second.
+    pub floor: f64,
+    /// Concurrency is never raised above this.
+    pub ceiling: usize,
+    /// How often the rate is measured.
+    pub interval: Duration,
+}
+
+impl RateController {
+    /// Creates a controller for `floor` probes per second with the default ceiling and interval.
+    pub fn new(floor: f64) -> Self {
+        Self {
+            floor,
+            ceiling: MAX_CONCURRENCY,
+            interval: RATE_CHECK_INTERVAL,
+        }
+    }
+
+    /// Returns the concurrency to use after `completed` probes finished within `elapsed`.
+    ///
+    /// Below the floor, concurrency grows in proportion to the shortfall, at most doubling per
+    /// adjustment so a single slow window cannot overshoot. It is never lowered.
+    pub fn next_concurrency(&self, current: usize, completed: u64, elapsed: Duration) -> usize {
+        let rate = completed as f64 / elapsed.as_secs_f64().max(f64::EPSILON);
+        if rate >= self.floor || current >= self.ceiling {
+            return current;
+        }
+
+        let needed = if rate > 0.0 {
+            (current as f64 * self.floor / rate).ceil() as usize
+        } else {
+            usize::MAX
+        };
+        needed.min(current.saturating_mul(2)).min(self.ceiling)
+    }
+}
+
+/// A controller that starts a scan at low concurrency and ramps up while probes stay healthy.
+///
+/// Every window, concurrency doubles (up to the ceiling) unless too many probes timed out or
+/// failed, in which case it halves. This avoids opening the full number of connections at
+/// once against a network that cannot take them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SlowStart {
+    /// Concurrency of the first window.
+    pub initial: usize,
+    /// Concurrency is never raised above this.
+    pub ceiling: usize,
+    /// How long each window lasts.
+    pub interval: Duration,
+    /// Backing off starts when failed probes make up more than this share of a window.
+    pub max_failure_ratio: f64,
+}
+
+impl SlowStart {
+    /// Creates a controller ramping up to `ceiling` with the default start, window and ratio.
+    pub fn new(ceiling: usize) -> Self {
+        let ceiling = ceiling.max(1);
+        Self {
+            initial: SLOW_START_CONCURRENCY.min(ceiling),
+            ceiling,
+            interval: SLOW_START_INTERVAL,
+            max_failure_ratio: MAX_FAILURE_RATIO,
+        }
+    }
+
+    /// Returns the concurrency to use after a window in which `completed` probes finished and
+    /// `failed` of them timed out or errored.
+    ///
+    /// A window without completed probes carries no signal and leaves concurrency unchanged.
+    pub fn next_concurrency(&self, current: usize, completed: u64, failed: u64) -> usize {
+        if completed == 0 {
+            return current;
+        }
+        if failed as f64 / completed as f64 > self.max_failure_ratio {
+            (current / 2).max(1)
+        } else {
+            current.saturating_mul(2).min(self.ceiling)
+        }
+    }
+}
+
+// Unit tests >------------------------------------------------------------<
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slow_start_ramps_up_while_healthy() {
+        let slow_start = SlowStart::new(100);
+        assert_eq!(slow_start.initial, 8);
+        assert_eq!(slow_start.next_concurrency(8, 40, 0), 16);
+        // At the threshold still counts as healthy.
+        assert_eq!(slow_start.next_concurrency(16, 50, 10), 32);
+        assert_eq!(slow_start.next_concurrency(64, 50, 1), 100);
+        assert_eq!(slow_start.next_concurrency(100, 50, 1), 100);
+        assert_eq!(SlowStart::new(4).initial, 4);
+    }
+
+    #[test]
+    fn test_slow_start_backs_off_when_failures_spike() {
+        let slow_start = SlowStart::new(100);
+        assert_eq!(slow_start.next_concurrency(64, 50, 11), 32);
+        assert_eq!(slow_start.next_concurrency(1, 10, 10), 1);
+        // No probe finished, so there is nothing to react to.
+        assert_eq!(slow_start.next_concurrency(16, 0, 0), 16);
+    }
+
+    #[test]
+    fn test_next_concurrency_raises_below_floor() {
+        let controller = RateController::new(100.0);
+        // 10 workers managing 60 probes/s need 17 to reach 100/s.
+        assert_eq!(
+            controller.next_concurrency(10, 30, Duration::from_millis(500)),
+            17
+        );
+        // A stalled window doubles rather than jumping straight to the ceiling.
+        assert_eq!(
+            controller.next_concurrency(10, 0, Duration::from_millis(500)),
+            20
+        );
+    }
+
+    #[test]
+    fn test_next_concurrency_keeps_rate_above_floor() {
+        let controller = RateController::new(100.0);
+        assert_eq!(
+            controller.next_concurrency(10, 60, Duration::from_millis(500)),
+            10
+        );
+    }
+
+    #[test]
+    fn test_next_concurrency_respects_ceiling() {
+        let controller = RateController {
+            ceiling: 12,
+            ..RateController::new(1000.0)
+        };
+        assert_eq!(
+            controller.next_concurrency(10, 5, Duration::from_millis(500)),
+            12
+        );
+        assert_eq!(
+            controller.next_concurrency(12, 5, Duration::from_millis(500)),
+            12
+        );
+    }
+}
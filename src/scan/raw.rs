@@ -0,0 +1,683 @@
+use crate::{
+    args::interfaces::OutgoingInterface,
+    scan::{
+        fingerprint::{Fingerprint, PROBE_OPTIONS},
+        jobs::Job,
+        result::{FilterReason, PortState, ScanResult, TcpResponse},
+    },
+};
+use clap::ValueEnum;
+use std::{
+    fmt, io, mem,
+    net::{IpAddr, SocketAddr, TcpListener, UdpSocket},
+    os::fd::{AsRawFd, FromRawFd, OwnedFd},
+    time::{Duration, Instant},
+};
+
+pub const FLAG_FIN: u8 = 0x01;
+pub const FLAG_SYN: u8 = 0x02;
+pub const FLAG_RST: u8 = 0x04;
+pub const FLAG_PSH: u8 = 0x08;
+pub const FLAG_ACK: u8 = 0x10;
+pub const FLAG_URG: u8 = 0x20;
+
+const ICMP_UNREACHABLE: u8 = 3;
+const ICMPV6_UNREACHABLE: u8 = 1;
+const IPV6_HEADER_LEN: usize = 40;
+
+/// Length of the option-less TCP header the probes send.
+const TCP_HEADER_LEN: usize = 20;
+const TCP_WINDOW: u16 = 1024;
+
+/// Represents how ports are probed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ScanType {
+    /// Full TCP connect (no privileges needed)
+    Connect,
+    /// Raw TCP segment with no flags set
+    Null,
+    /// Raw TCP segment with only FIN set
+    Fin,
+    /// Raw TCP segment with FIN, PSH and URG set
+    Xmas,
+    /// Empty UDP datagram; an ICMP port unreachable means closed (no privileges needed)
+    Udp,
+}
+
+impl ScanType {
+    /// Returns the TCP flags a raw probe of this type carries, or `None` for connect and UDP
+    /// scans.
+    pub fn flags(&self) -> Option<u8> {
+        match self {
+            ScanType::Connect | ScanType::Udp => None,
+            ScanType::Null => Some(0),
+            ScanType::Fin => Some(FLAG_FIN),
+            ScanType::Xmas => Some(FLAG_FIN | FLAG_PSH | FLAG_URG),
+        }
+    }
+}
+
+impl fmt::Display for ScanType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ScanType::Connect => "connect",
+            ScanType::Null => "null",
+            ScanType::Fin => "fin",
+            ScanType::Xmas => "xmas",
+            ScanType::Udp => "udp",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Fails unless the process may open raw sockets, which NULL, FIN and Xmas scans need.
+pub fn check_privileges(scan_type: ScanType) -> Result<(), String> {
+    raw_socket(libc::AF_INET, libc::IPPROTO_TCP)
+        .map(drop)
+        .map_err(|e| {
+            format!(
+                "--scan-type {} needs raw socket privileges: {}",
+                scan_type, e
+            )
+        })
+}
+
+/// Builds an option-less TCP header from `source` to `destination` with the given flags.
+///
+/// The checksum covers the IPv4 or IPv6 pseudo-header, so both addresses must be of the same
+/// family.
+pub fn build_segment(
+    source: SocketAddr,
+    destination: SocketAddr,
+    flags: u8,
+    sequence: u32,
+) -> Vec<u8> {
+    build_segment_with_options(source, destination, flags, sequence, &[])
+}
+
+/// Builds a TCP header like `build_segment`, followed by `options`, whose length must be a
+/// multiple of four.
+pub fn build_segment_with_options(
+    source: SocketAddr,
+    destination: SocketAddr,
+    flags: u8,
+    sequence: u32,
+    options: &[u8],
+) -> Vec<u8> {
+    debug_assert_eq!(options.len() % 4, 0, "TCP options must fill whole words");
+    let mut segment = vec![0u8; TCP_HEADER_LEN + options.len()];
+    segment[0..2].copy_from_slice(&source.port().to_be_bytes());
+    segment[2..4].copy_from_slice(&destination.port().to_be_bytes());
+    segment[4..8].copy_from_slice(&sequence.to_be_bytes());
+    segment[12] = ((segment.len() / 4) as u8) << 4;
+    segment[13] = flags;
+    segment[14..16].copy_from_slice(&TCP_WINDOW.to_be_bytes());
+    segment[TCP_HEADER_LEN..].copy_from_slice(options);
+
+    let checksum = tcp_checksum(source.ip(), destination.ip(), &segment);
+    segment[16..18].copy_from_slice(&checksum.to_be_bytes());
+    segment
+}
+
+/// Classifies a TCP segment received in answer to a NULL, FIN or Xmas probe.
+///
+/// Per RFC 793 a closed port answers with RST and an open one stays silent, so only RST
+/// segments between the probed pair of ports count; everything else returns `None`.
+pub fn classify_reply(segment: &[u8], probe: SocketAddr, target: SocketAddr) -> Option<PortState> {
+    if segment.len() < TCP_HEADER_LEN {
+        return None;
+    }
+    let source_port = u16::from_be_bytes([segment[0], segment[1]]);
+    let destination_port = u16::from_be_bytes([segment[2], segment[3]]);
+    if source_port != target.port() || destination_port != probe.port() {
+        return None;
+    }
+    (segment[13] & FLAG_RST != 0).then_some(PortState::Closed)
+}
+
+/// Classifies an ICMP or ICMPv6 destination unreachable received in answer to a probe.
+///
+/// `message` starts at the ICMP header. The message quotes the probe's IP header and the
+/// start of its TCP header, so only unreachables about the probed pair of ports count;
+/// everything else returns `None`. Codes that do not say why (e.g. port unreachable) count as
+/// host unreachable.
+pub fn classify_unreachable(
+    message: &[u8],
+    probe: SocketAddr,
+    target: SocketAddr,
+) -> Option<FilterReason> {
+    let (kind, code) = (*message.first()?, *message.get(1)?);
+    let quoted = message.get(8..)?;
+    let (reason, ports) = match target {
+        SocketAddr::V4(_) if kind == ICMP_UNREACHABLE => {
+            let reason = match code {
+                0 | 6 | 11 => FilterReason::NetUnreachable,
+                9 | 10 | 13 => FilterReason::AdminProhibited,
+                _ => FilterReason::HostUnreachable,
+            };
+            let header_len = ((*quoted.first()? & 0x0f) as usize) * 4;
+            let destination: [u8; 4] = quoted.get(16..20)?.try_into().ok()?;
+            if *quoted.get(9)? != libc::IPPROTO_TCP as u8
+                || IpAddr::from(destination) != target.ip()
+            {
+                return None;
+            }
+            (reason, quoted.get(header_len..header_len + 4)?)
+        }
+        SocketAddr::V6(_) if kind == ICMPV6_UNREACHABLE => {
+            let reason = match code {
+                0 => FilterReason::NetUnreachable,
+                1 | 5 | 6 => FilterReason::AdminProhibited,
+                _ => FilterReason::HostUnreachable,
+            };
+            let destination: [u8; 16] = quoted.get(24..40)?.try_into().ok()?;
+            if *quoted.get(6)? != libc::IPPROTO_TCP as u8
+                || IpAddr::from(destination) != target.ip()
+            {
+                return None;
+            }
+            (reason, quoted.get(IPV6_HEADER_LEN..IPV6_HEADER_LEN + 4)?)
+        }
+        _ => return None,
+    };
+    let source_port = u16::from_be_bytes([ports[0], ports[1]]);
+    let destination_port = u16::from_be_bytes([ports[2], ports[3]]);
+    (source_port == probe.port() && destination_port == target.port()).then_some(reason)
+}
+
+/// Probes a single TCP port with a raw segment of the given scan type.
+///
+/// An RST is `Closed` and silence until `timeout` is `OpenFiltered`: a filtering firewall and
+/// an open port look the same to these scans. An ICMP unreachable about the probe is
+/// `Filtered`, with the reason its code gives.
+///
+/// With an `interface`, the segment is sent from its address and, on Linux, the sockets are
+/// bound to the device itself. With `fingerprint`, the segment carries `PROBE_OPTIONS` and an
+/// RST reply is fingerprinted.
+pub fn probe(
+    job: &Job,
+    scan_type: ScanType,
+    interface: Option<&OutgoingInterface>,
+    fingerprint: bool,
+    timeout: Duration,
+) -> ScanResult {
+    let result = ScanResult::new(job.address, job.port, PortState::Error);
+    let flags = scan_type.flags().unwrap_or_default();
+    match raw_probe(job, flags, interface, fingerprint, timeout) {
+        Ok(reply) => ScanResult {
+            detail: Some(reply.detail(timeout)),
+            state: reply.state,
+            response: Some(reply.response),
+            filter_reason: reply.filter_reason,
+            latency: reply.latency,
+            fingerprint: reply.fingerprint,
+            ..result
+        },
+        Err(e) => ScanResult {
+            error: Some(format!("raw {} probe failed: {}", scan_type, e)),
+            ..result
+        },
+    }
+}
+
+/// What came back for a raw probe.
+struct Reply {
+    state: PortState,
+    response: TcpResponse,
+    filter_reason: Option<FilterReason>,
+    latency: Option<Duration>,
+    fingerprint: Option<Fingerprint>,
+}
+
+impl Reply {
+    /// Explains the reply, e.g. "rst after 4ms" or "no reply within 1000ms".
+    fn detail(&self, timeout: Duration) -> String {
+        let ms = self.latency.unwrap_or(timeout).as_millis();
+        match (self.response, self.filter_reason) {
+            (TcpResponse::NoResponse, _) => format!("no reply within {}ms", ms),
+            (_, Some(FilterReason::HostUnreachable)) => {
+                format!("icmp host unreachable after {}ms", ms)
+            }
+            (_, Some(FilterReason::NetUnreachable)) => {
+                format!("icmp network unreachable after {}ms", ms)
+            }
+            (_, Some(FilterReason::AdminProhibited)) => {
+                format!("icmp administratively prohibited after {}ms", ms)
+            }
+            _ => format!("rst after {}ms", ms),
+        }
+    }
+}
+
+fn raw_probe(
+    job: &Job,
+    flags: u8,
+    interface: Option<&OutgoingInterface>,
+    fingerprint: bool,
+    timeout: Duration,
+) -> io::Result<Reply> {
+    let target = SocketAddr::new(job.address, job.port);
+    let family = match job.address {
+        IpAddr::V4(_) => libc::AF_INET,
+        IpAddr::V6(_) => libc::AF_INET6,
+    };
+    let local = match interface {
+        Some(interface) => interface
+            .source_for(job.address)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?,
+        None => unspecified(job.address),
+    };
+
+    // The route to the target decides the source address, unless an interface fixed it; a
+    // bound listener reserves the source port for the duration of the probe so concurrent
+    // probes cannot see each other's replies.
+    let route = UdpSocket::bind(SocketAddr::new(local, 0))?;
+    route.connect(target)?;
+    let reserved = TcpListener::bind(SocketAddr::new(route.local_addr()?.ip(), 0))?;
+    let source = reserved.local_addr()?;
+
+    let socket = raw_socket(family, libc::IPPROTO_TCP)?;
+    let icmp = match job.address {
+        IpAddr::V4(_) => raw_socket(family, libc::IPPROTO_ICMP)?,
+        IpAddr::V6(_) => raw_socket(family, libc::IPPROTO_ICMPV6)?,
+    };
+    if let Some(interface) = interface {
+        bind_to_device(&socket, &interface.name)?;
+        bind_to_device(&icmp, &interface.name)?;
+    }
+    let options: &[u8] = if fingerprint { &PROBE_OPTIONS } else { &[] };
+    let segment = build_segment_with_options(source, target, flags, sequence(source), options);
+    let (address, address_len) = sockaddr(SocketAddr::new(job.address, 0));
+    let start = Instant::now();
+    // SAFETY: `segment` and `address` are valid for the lengths passed.
+    let sent = unsafe {
+        libc::sendto(
+            socket.as_raw_fd(),
+            segment.as_ptr().cast(),
+            segment.len(),
+            0,
+            (&address as *const libc::sockaddr_storage).cast(),
+            address_len,
+        )
+    };
+    if sent < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let mut buffer = [0u8; 1500];
+    while let Some(remaining) = timeout.checked_sub(start.elapsed()) {
+        let Some(ready) = wait_readable([&socket, &icmp], remaining)? else {
+            break;
+        };
+        let (received, from) = receive(ready, &mut buffer)?;
+
+        // IPv4 raw sockets deliver the IP header too; IPv6 ones start at the TCP or ICMPv6
+        // header.
+        let packet = &buffer[..received];
+        let payload = match job.address {
+            IpAddr::V4(_) => packet
+                .get(((packet[0] & 0x0f) as usize) * 4..)
+                .unwrap_or(&[]),
+            IpAddr::V6(_) => packet,
+        };
+        if std::ptr::eq(ready, &icmp) {
+            // Unreachables come from whichever router gave up, not from the target.
+            if let Some(reason) = classify_unreachable(payload, source, target) {
+                return Ok(Reply {
+                    state: PortState::Filtered,
+                    response: TcpResponse::Unreachable,
+                    filter_reason: Some(reason),
+                    latency: Some(start.elapsed()),
+                    fingerprint: None,
+                });
+            }
+        } else if from == job.address {
+            if let Some(state) = classify_reply(payload, source, target) {
+                let ttl = match job.address {
+                    IpAddr::V4(_) => packet.get(8).copied(),
+                    IpAddr::V6(_) => None,
+                };
+                let window = u16::from_be_bytes([payload[14], payload[15]]);
+                return Ok(Reply {
+                    state,
+                    response: TcpResponse::Rst,
+                    filter_reason: None,
+                    latency: Some(start.elapsed()),
+                    fingerprint: fingerprint.then(|| Fingerprint::new(ttl, window)),
+                });
+            }
+        }
+    }
+
+    Ok(Reply {
+        state: PortState::OpenFiltered,
+        response: TcpResponse::NoResponse,
+        filter_reason: None,
+        latency: None,
+        fingerprint: None,
+    })
+}
+
+fn raw_socket(family: libc::c_int, protocol: libc::c_int) -> io::Result<OwnedFd> {
+    // SAFETY: plain socket(2) call; a non-negative result is a fresh descriptor we now own.
+    let fd = unsafe { libc::socket(family, libc::SOCK_RAW, protocol) };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // SAFETY: `fd` is a valid, open descriptor that nothing else owns.
+    Ok(unsafe { OwnedFd::from_raw_fd(fd) })
+}
+
+/// Restricts `socket` to sending and receiving through the interface called `name`.
+#[cfg(target_os = "linux")]
+fn bind_to_device(socket: &OwnedFd, name: &str) -> io::Result<()> {
+    // SAFETY: `name` is valid for the length passed; the kernel copies it.
+    let status = unsafe {
+        libc::setsockopt(
+            socket.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_BINDTODEVICE,
+            name.as_ptr().cast(),
+            name.len() as libc::socklen_t,
+        )
+    };
+    if status != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Other platforms have no SO_BINDTODEVICE; the interface's source address still applies.
+#[cfg(not(target_os = "linux"))]
+fn bind_to_device(_socket: &OwnedFd, _name: &str) -> io::Result<()> {
+    Ok(())
+}
+
+/// Waits up to `timeout` for either socket to have a packet, returning the first that does.
+fn wait_readable(sockets: [&OwnedFd; 2], timeout: Duration) -> io::Result<Option<&OwnedFd>> {
+    let mut fds = sockets.map(|socket| libc::pollfd {
+        fd: socket.as_raw_fd(),
+        events: libc::POLLIN,
+        revents: 0,
+    });
+    let millis = timeout.as_millis().clamp(1, libc::c_int::MAX as u128) as libc::c_int;
+    // SAFETY: `fds` is a valid array of the length passed.
+    let ready = unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, millis) };
+    if ready < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(fds
+        .iter()
+        .zip(sockets)
+        .find(|(fd, _)| fd.revents & libc::POLLIN != 0)
+        .map(|(_, socket)| socket))
+}
+
+/// Receives one packet, returning its length and the address it came from.
+fn receive(socket: &OwnedFd, buffer: &mut [u8]) -> io::Result<(usize, IpAddr)> {
+    // SAFETY: an all-zero sockaddr_storage is a valid value.
+    let mut from: libc::sockaddr_storage = unsafe { mem::zeroed() };
+    let mut from_len = mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+    // SAFETY: `buffer` and `from` are valid for the lengths passed.
+    let received = unsafe {
+        libc::recvfrom(
+            socket.as_raw_fd(),
+            buffer.as_mut_ptr().cast(),
+            buffer.len(),
+            0,
+            (&mut from as *mut libc::sockaddr_storage).cast(),
+            &mut from_len,
+        )
+    };
+    if received < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let address = match from.ss_family as libc::c_int {
+        libc::AF_INET => {
+            // SAFETY: the kernel filled in a sockaddr_in for AF_INET.
+            let v4 =
+                unsafe { *(&from as *const libc::sockaddr_storage).cast::<libc::sockaddr_in>() };
+            IpAddr::from(u32::from_be(v4.sin_addr.s_addr).to_be_bytes())
+        }
+        _ => {
+            // SAFETY: raw sockets only receive from AF_INET or AF_INET6 peers.
+            let v6 =
+                unsafe { *(&from as *const libc::sockaddr_storage).cast::<libc::sockaddr_in6>() };
+            IpAddr::from(v6.sin6_addr.s6_addr)
+        }
+    };
+    Ok((received as usize, address))
+}
+
+/// Converts `addr` to the C socket address representation.
+pub(crate) fn sockaddr(addr: SocketAddr) -> (libc::sockaddr_storage, libc::socklen_t) {
+    // SAFETY: an all-zero sockaddr_storage is a valid value.
+    let mut storage: libc::sockaddr_storage = unsafe { mem::zeroed() };
+    let len = match addr {
+        SocketAddr::V4(v4) => {
+            // SAFETY: sockaddr_storage is large and aligned enough for any sockaddr.
+            let sin = unsafe {
+                &mut *(&mut storage as *mut libc::sockaddr_storage).cast::<libc::sockaddr_in>()
+            };
+            sin.sin_family = libc::AF_INET as libc::sa_family_t;
+            sin.sin_port = v4.port().to_be();
+            sin.sin_addr.s_addr = u32::from(*v4.ip()).to_be();
+            mem::size_of::<libc::sockaddr_in>()
+        }
+        SocketAddr::V6(v6) => {
+            // SAFETY: sockaddr_storage is large and aligned enough for any sockaddr.
+            let sin6 = unsafe {
+                &mut *(&mut storage as *mut libc::sockaddr_storage).cast::<libc::sockaddr_in6>()
+            };
+            sin6.sin6_family = libc::AF_INET6 as libc::sa_family_t;
+            sin6.sin6_port = v6.port().to_be();
+            sin6.sin6_addr.s6_addr = v6.ip().octets();
+            mem::size_of::<libc::sockaddr_in6>()
+        }
+    };
+    (storage, len as libc::socklen_t)
+}
+
+pub(crate) fn unspecified(address: IpAddr) -> IpAddr {
+    match address {
+        IpAddr::V4(_) => IpAddr::from([0u8; 4]),
+        IpAddr::V6(_) => IpAddr::from([0u8; 16]),
+    }
+}
+
+/// Derives a sequence number from the probe's source, which is all these probes need.
+fn sequence(source: SocketAddr) -> u32 {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.subsec_nanos())
+        .unwrap_or_default();
+    nanos ^ ((source.port() as u32) << 16)
+}
+
+/// Computes the TCP checksum of `segment` over the IPv4 or IPv6 pseudo-header.
+fn tcp_checksum(source: IpAddr, destination: IpAddr, segment: &[u8]) -> u16 {
+    let mut pseudo = Vec::with_capacity(40 + segment.len());
+    match (source, destination) {
+        (IpAddr::V4(source), IpAddr::V4(destination)) => {
+            pseudo.extend_from_slice(&source.octets());
+            pseudo.extend_from_slice(&destination.octets());
+            pseudo.extend_from_slice(&[0, libc::IPPROTO_TCP as u8]);
+            pseudo.extend_from_slice(&(segment.len() as u16).to_be_bytes());
+        }
+        _ => {
+            pseudo.extend_from_slice(&ipv6_octets(source));
+            pseudo.extend_from_slice(&ipv6_octets(destination));
+            pseudo.extend_from_slice(&(segment.len() as u32).to_be_bytes());
+            pseudo.extend_from_slice(&[0, 0, 0, libc::IPPROTO_TCP as u8]);
+        }
+    }
+    pseudo.extend_from_slice(segment);
+
+    let mut sum: u32 = pseudo
+        .chunks(2)
+        .map(|pair| u16::from_be_bytes([pair[0], pair.get(1).copied().unwrap_or(0)]) as u32)
+        .sum();
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+fn ipv6_octets(address: IpAddr) -> [u8; 16] {
+    match address {
+        IpAddr::V4(v4) => v4.to_ipv6_mapped().octets(),
+        IpAddr::V6(v6) => v6.octets(),
+    }
+}
+
+// Unit tests >------------------------------------------------------------<
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addrs() -> (SocketAddr, SocketAddr) {
+        (
+            "10.0.0.1:40000".parse().unwrap(),
+            "10.0.0.2:80".parse().unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_scan_type_flags() {
+        assert_eq!(ScanType::Connect.flags(), None);
+        assert_eq!(ScanType::Null.flags(), Some(0));
+        assert_eq!(ScanType::Fin.flags(), Some(FLAG_FIN));
+        assert_eq!(ScanType::Xmas.flags(), Some(0x29));
+    }
+
+    #[test]
+    fn test_build_segment_sets_flags_for_each_scan_type() {
+        let (source, destination) = addrs();
+        for (scan_type, flags) in [
+            (ScanType::Null, 0x00),
+            (ScanType::Fin, 0x01),
+            (ScanType::Xmas, 0x29),
+        ] {
+            let segment = build_segment(source, destination, scan_type.flags().unwrap(), 7);
+            assert_eq!(segment[13], flags, "{}", scan_type);
+            assert_eq!(segment[13] & (FLAG_SYN | FLAG_ACK | FLAG_RST), 0);
+        }
+    }
+
+    #[test]
+    fn test_build_segment_header_fields() {
+        let (source, destination) = addrs();
+        let segment = build_segment(source, destination, FLAG_FIN, 0x01020304);
+
+        assert_eq!(&segment[0..2], &40000u16.to_be_bytes());
+        assert_eq!(&segment[2..4], &80u16.to_be_bytes());
+        assert_eq!(&segment[4..8], &[1, 2, 3, 4]);
+        assert_eq!(segment[12], 0x50);
+        // A correct checksum makes the sum over pseudo-header and segment verify to zero.
+        assert_eq!(tcp_checksum(source.ip(), destination.ip(), &segment), 0);
+
+        let source = "[2001:db8::1]:40000".parse().unwrap();
+        let destination = "[2001:db8::2]:80".parse().unwrap();
+        let segment = build_segment(source, destination, 0, 1);
+        assert_eq!(tcp_checksum(source.ip(), destination.ip(), &segment), 0);
+    }
+
+    #[test]
+    fn test_build_segment_with_fingerprint_options() {
+        let (source, destination) = addrs();
+        let segment = build_segment_with_options(source, destination, FLAG_FIN, 1, &PROBE_OPTIONS);
+
+        assert_eq!(segment.len(), TCP_HEADER_LEN + PROBE_OPTIONS.len());
+        // Data offset counts the options: 36 bytes is nine 32-bit words.
+        assert_eq!(segment[12], 0x90);
+        assert_eq!(&segment[TCP_HEADER_LEN..], &PROBE_OPTIONS);
+        assert_eq!(tcp_checksum(source.ip(), destination.ip(), &segment), 0);
+    }
+
+    #[test]
+    fn test_classify_reply() {
+        let (probe, target) = addrs();
+        let rst = build_segment(target, probe, FLAG_RST | FLAG_ACK, 0);
+        assert_eq!(classify_reply(&rst, probe, target), Some(PortState::Closed));
+
+        let other_port = build_segment("10.0.0.2:81".parse().unwrap(), probe, FLAG_RST, 0);
+        assert_eq!(classify_reply(&other_port, probe, target), None);
+
+        let ack = build_segment(target, probe, FLAG_ACK, 0);
+        assert_eq!(classify_reply(&ack, probe, target), None);
+        assert_eq!(classify_reply(&rst[..10], probe, target), None);
+    }
+
+    /// Builds an ICMP unreachable of `kind` and `code` quoting a TCP probe from `probe` to
+    /// `target`, the way a router would.
+    fn unreachable(kind: u8, code: u8, probe: SocketAddr, target: SocketAddr) -> Vec<u8> {
+        let mut message = vec![kind, code, 0, 0, 0, 0, 0, 0];
+        match (probe.ip(), target.ip()) {
+            (IpAddr::V4(source), IpAddr::V4(destination)) => {
+                let mut header = [0u8; 20];
+                header[0] = 0x45;
+                header[9] = libc::IPPROTO_TCP as u8;
+                header[12..16].copy_from_slice(&source.octets());
+                header[16..20].copy_from_slice(&destination.octets());
+                message.extend_from_slice(&header);
+            }
+            (source, destination) => {
+                let mut header = [0u8; 40];
+                header[0] = 0x60;
+                header[6] = libc::IPPROTO_TCP as u8;
+                header[8..24].copy_from_slice(&ipv6_octets(source));
+                header[24..40].copy_from_slice(&ipv6_octets(destination));
+                message.extend_from_slice(&header);
+            }
+        }
+        message.extend_from_slice(&build_segment(probe, target, 0, 1)[..8]);
+        message
+    }
+
+    #[test]
+    fn test_classify_unreachable_ipv4() {
+        let (probe, target) = addrs();
+        for (code, reason) in [
+            (0, FilterReason::NetUnreachable),
+            (1, FilterReason::HostUnreachable),
+            (3, FilterReason::HostUnreachable),
+            (13, FilterReason::AdminProhibited),
+            (10, FilterReason::AdminProhibited),
+        ] {
+            let message = unreachable(ICMP_UNREACHABLE, code, probe, target);
+            assert_eq!(
+                classify_unreachable(&message, probe, target),
+                Some(reason),
+                "code {}",
+                code
+            );
+        }
+
+        // Echo replies, other probes' errors and truncated quotes are ignored.
+        let message = unreachable(0, 0, probe, target);
+        assert_eq!(classify_unreachable(&message, probe, target), None);
+        let other = "10.0.0.2:81".parse().unwrap();
+        let message = unreachable(ICMP_UNREACHABLE, 13, probe, other);
+        assert_eq!(classify_unreachable(&message, probe, target), None);
+        let message = unreachable(ICMP_UNREACHABLE, 13, probe, target);
+        assert_eq!(classify_unreachable(&message[..30], probe, target), None);
+    }
+
+    #[test]
+    fn test_classify_unreachable_ipv6() {
+        let probe = "[2001:db8::1]:40000".parse().unwrap();
+        let target = "[2001:db8::2]:80".parse().unwrap();
+        for (code, reason) in [
+            (0, FilterReason::NetUnreachable),
+            (1, FilterReason::AdminProhibited),
+            (3, FilterReason::HostUnreachable),
+        ] {
+            let message = unreachable(ICMPV6_UNREACHABLE, code, probe, target);
+            assert_eq!(classify_unreachable(&message, probe, target), Some(reason));
+        }
+        // An ICMPv4 type means nothing in an ICMPv6 message.
+        let message = unreachable(ICMP_UNREACHABLE, 1, probe, target);
+        assert_eq!(classify_unreachable(&message, probe, target), None);
+    }
+}
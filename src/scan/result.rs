@@ -0,0 +1,261 @@
+use crate::{
+    args::parser::Protocol,
+    output::severity::Severity,
+    scan::{fingerprint::Fingerprint, tls::TlsInfo},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    net::{IpAddr, SocketAddr},
+    time::Duration,
+};
+
+/// Represents the classified state of a scanned port.
+///
+/// `OpenFiltered` is what NULL, FIN and Xmas scans report for a silent port, which is either
+/// open or behind a firewall. `Error` means the probe itself failed (e.g. a proxy hop broke),
+/// so nothing is known about the port; the reason is in `ScanResult::error`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PortState {
+    Open,
+    Closed,
+    #[serde(rename = "open|filtered")]
+    OpenFiltered,
+    Filtered,
+    Error,
+}
+
+impl fmt::Display for PortState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = match self {
+            PortState::Open => "open",
+            PortState::Closed => "closed",
+            PortState::OpenFiltered => "open|filtered",
+            PortState::Filtered => "filtered",
+            PortState::Error => "error",
+        };
+        write!(f, "{}", state)
+    }
+}
+
+/// Represents how an open port behaved after the handshake, when its banner was read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OpenSubState {
+    /// The service sent a banner.
+    Banner,
+    /// The service sent nothing before the timeout or closed cleanly.
+    NoData,
+    /// The service reset the connection right after accepting it.
+    OpenButReset,
+}
+
+/// Represents how the target's TCP stack answered a probe.
+///
+/// The connect engine infers this from how `connect` completed, since the kernel does not
+/// expose the packets themselves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TcpResponse {
+    /// The handshake completed, so the port answered with SYN/ACK.
+    SynAck,
+    /// The port answered with RST.
+    Rst,
+    /// Nothing came back before the timeout, typically a firewall silently dropping packets.
+    NoResponse,
+    /// The connect failed without a TCP answer, typically after an ICMP unreachable.
+    Unreachable,
+}
+
+/// Represents why a port was classified `Filtered`.
+///
+/// Silent drops and explicit ICMP rejections usually come from different kinds of firewall;
+/// the unreachable codes also tell a dead host from a missing route.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterReason {
+    /// Nothing came back before the timeout.
+    Timeout,
+    /// An ICMP "communication administratively prohibited", or a local firewall, rejected it.
+    AdminProhibited,
+    /// An ICMP host unreachable came back.
+    HostUnreachable,
+    /// An ICMP network unreachable came back, or there is no route to the network.
+    NetUnreachable,
+}
+
+/// Represents the outcome of probing a single (host, port) pair.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScanResult {
+    pub address: IpAddr,
+    pub port: u16,
+    /// Protocol the port was probed over; left out of JSON for TCP.
+    #[serde(default, skip_serializing_if = "Protocol::is_tcp")]
+    pub protocol: Protocol,
+    /// Interface a link-local address was reached through, when scanned per interface.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interface: Option<String>,
+    /// PTR name of the address, when reverse DNS is enabled and the lookup succeeded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hostname: Option<String>,
+    pub state: PortState,
+    /// How the port answered, when the engine observed the TCP exchange directly.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<TcpResponse>,
+    /// Why the port is filtered, for `PortState::Filtered` results whose cause was observed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filter_reason: Option<FilterReason>,
+    #[serde(rename = "latency_ms", with = "latency_ms")]
+    pub latency: Option<Duration>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub banner: Option<String>,
+    /// The banner was binary, so it is hex-escaped (`\x00`) rather than decoded.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub banner_binary: bool,
+    /// The banner as decoded, before `--quiet-banner` stripped its control sequences; only
+    /// kept with `--raw-banner`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub banner_raw: Option<String>,
+    /// How the open port behaved while its banner was read.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sub_state: Option<OpenSubState>,
+    /// Local address the probe's connection was made from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_addr: Option<IpAddr>,
+    /// Why the probe failed, for `PortState::Error` results.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// What the service disclosed in its TLS handshake, when TLS inspection is enabled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls: Option<TlsInfo>,
+    /// Service identified from what the port said, when service detection is enabled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service: Option<String>,
+    /// How risky the exposed service is, for open ports.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub severity: Option<Severity>,
+    /// What the reply revealed about the target's stack, when fingerprinting raw scans.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fingerprint: Option<Fingerprint>,
+    /// How the engine arrived at the state, e.g. "timed out after 1000ms", when `--explain`
+    /// is set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    /// RFC 3339 UTC time, to the millisecond, the probe completed at.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<String>,
+}
+
+impl ScanResult {
+    /// Creates a result with the given classification and no optional details.
+    pub fn new(address: IpAddr, port: u16, state: PortState) -> Self {
+        Self {
+            address,
+            port,
+            protocol: Protocol::Tcp,
+            interface: None,
+            hostname: None,
+            state,
+            response: None,
+            filter_reason: None,
+            latency: None,
+            banner: None,
+            banner_binary: false,
+            banner_raw: None,
+            sub_state: None,
+            source_addr: None,
+            error: None,
+            tls: None,
+            service: None,
+            severity: None,
+            fingerprint: None,
+            detail: None,
+            timestamp: None,
+        }
+    }
+
+    /// Returns the banner as text output shows it: trimmed, with control characters escaped.
+    /// Binary banners are already escaped, and escaping again would double their backslashes.
+    pub fn printable_banner(&self) -> Option<String> {
+        let banner = self.banner.as_deref()?;
+        if self.banner_binary {
+            return Some(banner.to_string());
+        }
+        Some(banner.trim().escape_debug().to_string())
+    }
+}
+
+impl fmt::Display for ScanResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.interface, self.address) {
+            (Some(interface), IpAddr::V6(address)) => {
+                write!(f, "[{}%{}]:{}", address, interface, self.port)?
+            }
+            _ => write!(f, "{}", SocketAddr::new(self.address, self.port))?,
+        }
+        if !self.protocol.is_tcp() {
+            write!(f, "/{}", self.protocol)?;
+        }
+        if let Some(hostname) = &self.hostname {
+            write!(f, " ({})", hostname)?;
+        }
+        write!(f, " {}", self.state)?;
+        if self.sub_state == Some(OpenSubState::OpenButReset) {
+            write!(f, " (reset after accept)")?;
+        }
+        if let Some(latency) = self.latency {
+            write!(f, " ({}ms)", latency.as_millis())?;
+        }
+        if let Some(severity) = self.severity.filter(|s| *s > Severity::Info) {
+            write!(f, " ({} risk)", severity)?;
+        }
+        if let Some(service) = &self.service {
+            write!(f, " [{}]", service)?;
+        }
+        if let Some(banner) = self.printable_banner() {
+            write!(f, " {}", banner)?;
+        }
+        if let Some(tls) = &self.tls {
+            write!(f, " [{}", tls.version)?;
+            if let Some(alpn) = &tls.alpn {
+                write!(f, " {}", alpn)?;
+            }
+            for issue in &tls.issues {
+                write!(f, ", {}", issue)?;
+            }
+            write!(f, "]")?;
+        }
+        if let Some(Fingerprint {
+            ttl: Some(ttl),
+            os: Some(os),
+            ..
+        }) = self.fingerprint
+        {
+            write!(f, " (ttl {}, likely {})", ttl, os)?;
+        }
+        if let Some(error) = &self.error {
+            write!(f, " ({})", error)?;
+        }
+        Ok(())
+    }
+}
+
+/// Serializes latencies as fractional milliseconds, which is what consumers of the output expect.
+pub(crate) mod latency_ms {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(latency: &Option<Duration>, s: S) -> Result<S::Ok, S::Error> {
+        match latency {
+            Some(latency) => s.serialize_some(&(latency.as_secs_f64() * 1000.0)),
+            None => s.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Duration>, D::Error> {
+        let ms = Option::<f64>::deserialize(d)?;
+        ms.map(|ms| Duration::try_from_secs_f64(ms / 1000.0).map_err(D::Error::custom))
+            .transpose()
+    }
+}
@@ -0,0 +1,92 @@
+use std::{collections::HashMap, net::IpAddr, sync::Mutex, time::Duration};
+
+/// How many round trips a host's scaled timeout allows for.
+pub const RTT_MULTIPLIER: u32 = 4;
+
+/// Scaled timeouts never drop below this, so a near host's jitter doesn't filter its ports.
+pub const MIN_RTT_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Scaled timeouts never grow past this, however slow a host answers.
+pub const MAX_RTT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Returns the per-port timeout for a host whose smoothed round-trip time is `rtt`:
+/// `RTT_MULTIPLIER` round trips, clamped to `MIN_RTT_TIMEOUT..=MAX_RTT_TIMEOUT`.
+pub fn scaled_timeout(rtt: Duration) -> Duration {
+    rtt.saturating_mul(RTT_MULTIPLIER)
+        .clamp(MIN_RTT_TIMEOUT, MAX_RTT_TIMEOUT)
+}
+
+/// Per-host timeouts learned from the round trips of a scan's own probes, shared by every
+/// worker.
+///
+/// A host is probed with the initial timeout until one of its ports answers; from then on
+/// each answer refines its smoothed round-trip time (as TCP does, RFC 6298: 7/8 of the old
+/// estimate plus 1/8 of the new sample) and its ports get `scaled_timeout` of it. A distant
+/// host thus gets more time than the initial timeout, and a near one less.
+#[derive(Debug)]
+pub struct HostTimeouts {
+    initial: Duration,
+    rtts: Mutex<HashMap<IpAddr, Duration>>,
+}
+
+impl HostTimeouts {
+    /// Creates the table, probing hosts that have not answered yet with `initial`.
+    pub fn new(initial: Duration) -> Self {
+        Self {
+            initial,
+            rtts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Folds a round trip to `address` into its smoothed estimate.
+    pub fn record(&self, address: IpAddr, rtt: Duration) {
+        let mut rtts = self.rtts.lock().unwrap_or_else(|e| e.into_inner());
+        rtts.entry(address)
+            .and_modify(|smoothed| *smoothed = (*smoothed * 7 + rtt) / 8)
+            .or_insert(rtt);
+    }
+
+    /// Returns the timeout to probe `address` with.
+    pub fn timeout_for(&self, address: IpAddr) -> Duration {
+        let rtts = self.rtts.lock().unwrap_or_else(|e| e.into_inner());
+        rtts.get(&address)
+            .map_or(self.initial, |rtt| scaled_timeout(*rtt))
+    }
+}
+
+// Unit tests >------------------------------------------------------------<
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scaled_timeout_is_clamped_multiple_of_rtt() {
+        let ms = Duration::from_millis;
+        assert_eq!(scaled_timeout(ms(250)), ms(1000));
+        assert_eq!(scaled_timeout(ms(80)), ms(320));
+        assert_eq!(scaled_timeout(ms(1)), MIN_RTT_TIMEOUT);
+        assert_eq!(scaled_timeout(ms(5000)), MAX_RTT_TIMEOUT);
+        assert_eq!(scaled_timeout(Duration::MAX), MAX_RTT_TIMEOUT);
+    }
+
+    #[test]
+    fn test_host_timeouts_follow_each_hosts_rtt() {
+        let ms = Duration::from_millis;
+        let (near, far, silent) = (
+            "10.0.0.1".parse().unwrap(),
+            "203.0.113.9".parse().unwrap(),
+            "10.0.0.2".parse().unwrap(),
+        );
+        let timeouts = HostTimeouts::new(ms(1000));
+
+        timeouts.record(near, ms(2));
+        timeouts.record(far, ms(400));
+        assert_eq!(timeouts.timeout_for(near), MIN_RTT_TIMEOUT);
+        assert_eq!(timeouts.timeout_for(far), ms(1600));
+        assert_eq!(timeouts.timeout_for(silent), ms(1000));
+
+        // A later sample moves the estimate an eighth of the way: (7 * 400 + 800) / 8 = 450.
+        timeouts.record(far, ms(800));
+        assert_eq!(timeouts.timeout_for(far), ms(1800));
+    }
+}
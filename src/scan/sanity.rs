@@ -0,0 +1,123 @@
+use crate::{input_parse::Args, scan::job_count};
+use std::fmt;
+
+/// `--threads` values above this rarely help: the threads only do parsing and formatting.
+pub const MAX_SENSIBLE_THREADS: usize = 256;
+
+/// In-flight connects above this risk exhausting file descriptors or the local port range.
+pub const MAX_SENSIBLE_CONCURRENCY: usize = 5000;
+
+/// Timeouts below this are shorter than most network round trips.
+pub const MIN_SENSIBLE_TIMEOUT_MS: u32 = 10;
+
+/// Host counts at or above this make a full 65535-port sweep impractically long.
+pub const FULL_SWEEP_HOSTS: u128 = 256;
+
+/// Represents an argument combination that is allowed but probably not what the user meant.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Warning {
+    ManyThreads(usize),
+    HighConcurrency(usize),
+    ShortTimeout(u32),
+    FullPortSweep { hosts: u128 },
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Warning::ManyThreads(threads) => write!(
+                f,
+                "--threads {} only affects CPU-bound work; use --concurrency to control in-flight connects",
+                threads
+            ),
+            Warning::HighConcurrency(concurrency) => write!(
+                f,
+                "--concurrency {} may exhaust file descriptors or local ports; consider {} or fewer",
+                concurrency, MAX_SENSIBLE_CONCURRENCY
+            ),
+            Warning::ShortTimeout(timeout) => write!(
+                f,
+                "{}ms timeout will mark nearly everything filtered; try at least {}ms",
+                timeout, MIN_SENSIBLE_TIMEOUT_MS
+            ),
+            Warning::FullPortSweep { hosts } => write!(
+                f,
+                "scanning all 65535 ports on {} hosts will take a long time; consider narrowing --ports",
+                hosts
+            ),
+        }
+    }
+}
+
+/// Flags likely-misconfigured scans. The warnings are advisory; nothing here stops a scan.
+pub fn validate_sanity(args: &Args) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+
+    if args.threads > MAX_SENSIBLE_THREADS {
+        warnings.push(Warning::ManyThreads(args.threads));
+    }
+    if args.concurrency > MAX_SENSIBLE_CONCURRENCY {
+        warnings.push(Warning::HighConcurrency(args.concurrency));
+    }
+    if args.timeout < MIN_SENSIBLE_TIMEOUT_MS {
+        warnings.push(Warning::ShortTimeout(args.timeout));
+    }
+
+    let ports = args.ports.count();
+    if ports == u16::MAX as usize {
+        let hosts = job_count(&args.targets, &args.ports) / ports as u128;
+        if hosts >= FULL_SWEEP_HOSTS {
+            warnings.push(Warning::FullPortSweep { hosts });
+        }
+    }
+
+    warnings
+}
+
+// Unit tests >------------------------------------------------------------<
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_sanity_defaults_are_quiet() {
+        assert_eq!(validate_sanity(&Args::default()), vec![]);
+    }
+
+    #[test]
+    fn test_validate_sanity_pathological_arguments() {
+        let args = Args::parse_from([
+            "port_hawk",
+            "--threads",
+            "5000",
+            "--concurrency",
+            "20000",
+            "--timeout",
+            "1",
+        ]);
+        assert_eq!(
+            validate_sanity(&args),
+            vec![
+                Warning::ManyThreads(5000),
+                Warning::HighConcurrency(20000),
+                Warning::ShortTimeout(1),
+            ]
+        );
+        assert_eq!(
+            Warning::ShortTimeout(1).to_string(),
+            "1ms timeout will mark nearly everything filtered; try at least 10ms"
+        );
+    }
+
+    #[test]
+    fn test_validate_sanity_full_sweep_of_a_large_block() {
+        let args = Args::parse_from(["port_hawk", "10.0.0.0/16", "--all-ports"]);
+        assert_eq!(
+            validate_sanity(&args),
+            vec![Warning::FullPortSweep { hosts: 65536 }]
+        );
+
+        let args = Args::parse_from(["port_hawk", "10.0.0.0/30", "--all-ports"]);
+        assert_eq!(validate_sanity(&args), vec![]);
+    }
+}
@@ -0,0 +1,87 @@
+/// Well-known TCP services, sorted by port.
+const SERVICES: &[(u16, &str)] = &[
+    (20, "ftp-data"),
+    (21, "ftp"),
+    (22, "ssh"),
+    (23, "telnet"),
+    (25, "smtp"),
+    (53, "domain"),
+    (67, "dhcps"),
+    (68, "dhcpc"),
+    (69, "tftp"),
+    (80, "http"),
+    (88, "kerberos"),
+    (110, "pop3"),
+    (111, "rpcbind"),
+    (119, "nntp"),
+    (123, "ntp"),
+    (135, "msrpc"),
+    (137, "netbios-ns"),
+    (138, "netbios-dgm"),
+    (139, "netbios-ssn"),
+    (143, "imap"),
+    (161, "snmp"),
+    (162, "snmptrap"),
+    (179, "bgp"),
+    (389, "ldap"),
+    (443, "https"),
+    (445, "microsoft-ds"),
+    (465, "smtps"),
+    (500, "isakmp"),
+    (513, "login"),
+    (514, "shell"),
+    (515, "printer"),
+    (587, "submission"),
+    (631, "ipp"),
+    (636, "ldaps"),
+    (873, "rsync"),
+    (993, "imaps"),
+    (995, "pop3s"),
+    (1080, "socks"),
+    (1433, "ms-sql-s"),
+    (1521, "oracle"),
+    (1723, "pptp"),
+    (1900, "upnp"),
+    (2049, "nfs"),
+    (2375, "docker"),
+    (3000, "ppp"),
+    (3306, "mysql"),
+    (3389, "ms-wbt-server"),
+    (5353, "mdns"),
+    (5432, "postgresql"),
+    (5900, "vnc"),
+    (5985, "wsman"),
+    (6379, "redis"),
+    (8000, "http-alt"),
+    (8080, "http-proxy"),
+    (8443, "https-alt"),
+    (9200, "elasticsearch"),
+    (11211, "memcache"),
+    (27017, "mongodb"),
+];
+
+/// Returns the conventional service name for a TCP port, if it is a well-known one.
+pub fn service_name(port: u16) -> Option<&'static str> {
+    SERVICES
+        .binary_search_by_key(&port, |(p, _)| *p)
+        .ok()
+        .map(|i| SERVICES[i].1)
+}
+
+// Unit tests >------------------------------------------------------------<
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_services_sorted() {
+        assert!(SERVICES.windows(2).all(|w| w[0].0 < w[1].0));
+    }
+
+    #[test]
+    fn test_service_name() {
+        assert_eq!(service_name(22), Some("ssh"));
+        assert_eq!(service_name(443), Some("https"));
+        assert_eq!(service_name(4), None);
+    }
+}
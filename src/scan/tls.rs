@@ -0,0 +1,613 @@
+use crate::scan::proxy::{connect_chain, ChainError, Proxy};
+use serde::{Deserialize, Serialize};
+use std::{
+    io::{Read, Write},
+    net::{SocketAddr, TcpStream},
+    time::{Duration, SystemTime},
+};
+
+/// ALPN protocols offered in the ClientHello, in preference order.
+pub const ALPN_PROTOCOLS: &[&str] = &["h2", "http/1.1"];
+
+const CONTENT_ALERT: u8 = 21;
+const CONTENT_HANDSHAKE: u8 = 22;
+const HANDSHAKE_CLIENT_HELLO: u8 = 1;
+const HANDSHAKE_SERVER_HELLO: u8 = 2;
+const HANDSHAKE_CERTIFICATE: u8 = 11;
+const DER_SEQUENCE: u8 = 0x30;
+const DER_UTC_TIME: u8 = 0x17;
+const DER_GENERALIZED_TIME: u8 = 0x18;
+/// Context tag of the explicit version field that opens a v2 or v3 `TBSCertificate`.
+const DER_VERSION: u8 = 0xa0;
+const EXTENSION_SUPPORTED_GROUPS: u16 = 10;
+const EXTENSION_EC_POINT_FORMATS: u16 = 11;
+const EXTENSION_SIGNATURE_ALGORITHMS: u16 = 13;
+const EXTENSION_ALPN: u16 = 16;
+
+/// Upper bound on bytes buffered while waiting for a complete handshake message.
+const MAX_HANDSHAKE_BYTES: usize = 1 << 16;
+
+const CIPHER_SUITES: &[u16] = &[
+    0xC02B, 0xC02F, 0xC02C, 0xC030, 0xCCA9, 0xCCA8, 0xC013, 0xC014, 0x009C, 0x009D, 0x002F, 0x0035,
+];
+const SUPPORTED_GROUPS: &[u16] = &[0x001D, 0x0017, 0x0018];
+const SIGNATURE_ALGORITHMS: &[u16] = &[
+    0x0403, 0x0804, 0x0401, 0x0503, 0x0805, 0x0501, 0x0806, 0x0601, 0x0201,
+];
+
+/// What a TLS service disclosed in its ServerHello and certificate.
+///
+/// The handshake is never verified, so an expired or self-signed certificate does not stop
+/// the inspection; what is wrong with the certificate is listed in `issues` instead.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TlsInfo {
+    /// Negotiated protocol version, e.g. `TLS 1.2`.
+    pub version: String,
+    /// Negotiated cipher suite, as its IANA code point.
+    pub cipher_suite: u16,
+    /// Negotiated ALPN protocol, or `None` if the server did not select one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alpn: Option<String>,
+    /// Problems with the server's certificate, e.g. `self-signed certificate`; empty if it
+    /// looked valid or was not sent.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub issues: Vec<String>,
+}
+
+/// Connects to `addr`, directly or through `proxies`, and inspects the TLS service there.
+pub fn inspect(addr: SocketAddr, proxies: &[Proxy], timeout: Duration) -> Result<TlsInfo, String> {
+    let mut stream = if proxies.is_empty() {
+        let stream = TcpStream::connect_timeout(&addr, timeout).map_err(|e| e.to_string())?;
+        stream
+            .set_read_timeout(Some(timeout))
+            .and_then(|_| stream.set_write_timeout(Some(timeout)))
+            .map_err(|e| e.to_string())?;
+        stream
+    } else {
+        connect_chain(proxies, addr, timeout).map_err(|e| match e {
+            ChainError::Hop { hop, reason } => format!("proxy hop {}: {}", hop + 1, reason),
+            ChainError::Target(refusal) => format!("proxy refused target ({})", refusal),
+        })?
+    };
+
+    stream
+        .write_all(&client_hello(ALPN_PROTOCOLS))
+        .map_err(|e| e.to_string())?;
+    read_server_hello(&mut stream)
+}
+
+/// Builds a TLS 1.2 ClientHello record offering the given ALPN protocols.
+///
+/// TLS 1.3 is deliberately not offered: in 1.3 the ALPN choice travels encrypted, while a 1.2
+/// ServerHello carries it in the clear. The handshake is never completed, so the client random
+/// does not need to be unpredictable.
+pub fn client_hello(alpn: &[&str]) -> Vec<u8> {
+    let mut extensions = Vec::new();
+    push_extension(&mut extensions, EXTENSION_SUPPORTED_GROUPS, &{
+        let mut groups = Vec::new();
+        push_u16_list(&mut groups, SUPPORTED_GROUPS);
+        groups
+    });
+    push_extension(&mut extensions, EXTENSION_EC_POINT_FORMATS, &[1, 0]);
+    push_extension(&mut extensions, EXTENSION_SIGNATURE_ALGORITHMS, &{
+        let mut algorithms = Vec::new();
+        push_u16_list(&mut algorithms, SIGNATURE_ALGORITHMS);
+        algorithms
+    });
+    if !alpn.is_empty() {
+        let mut protocols = Vec::new();
+        for protocol in alpn {
+            protocols.push(protocol.len() as u8);
+            protocols.extend_from_slice(protocol.as_bytes());
+        }
+        let mut body = (protocols.len() as u16).to_be_bytes().to_vec();
+        body.extend_from_slice(&protocols);
+        push_extension(&mut extensions, EXTENSION_ALPN, &body);
+    }
+
+    let mut hello = vec![0x03, 0x03];
+    hello.extend_from_slice(&[0u8; 32]);
+    hello.push(0);
+    push_u16_list(&mut hello, CIPHER_SUITES);
+    hello.extend_from_slice(&[1, 0]);
+    hello.extend_from_slice(&(extensions.len() as u16).to_be_bytes());
+    hello.extend_from_slice(&extensions);
+
+    let mut handshake = vec![HANDSHAKE_CLIENT_HELLO];
+    handshake.extend_from_slice(&(hello.len() as u32).to_be_bytes()[1..]);
+    handshake.extend_from_slice(&hello);
+
+    let mut record = vec![CONTENT_HANDSHAKE, 0x03, 0x01];
+    record.extend_from_slice(&(handshake.len() as u16).to_be_bytes());
+    record.extend_from_slice(&handshake);
+    record
+}
+
+/// Reads TLS records from `reader` until a complete ServerHello arrives, and parses it.
+///
+/// The server's certificate, which a TLS 1.2 server sends in the clear right after the
+/// ServerHello, is checked for problems too. A certificate that never arrives or cannot be read
+/// leaves `TlsInfo::issues` empty rather than failing the inspection.
+///
+/// # Returns
+///
+/// * `Ok(TlsInfo)` - If a ServerHello was received.
+/// * `Err(String)` - If the peer sent an alert, something that is not TLS, or closed early.
+pub fn read_server_hello<R: Read>(reader: &mut R) -> Result<TlsInfo, String> {
+    let mut handshake = Vec::new();
+    let (kind, body) = read_handshake_message(reader, &mut handshake)?;
+    if kind != HANDSHAKE_SERVER_HELLO {
+        return Err(format!("Unexpected TLS handshake message: {}", kind));
+    }
+    let mut info = parse_server_hello(&body)?;
+
+    if let Ok((HANDSHAKE_CERTIFICATE, body)) = read_handshake_message(reader, &mut handshake) {
+        if let Some(leaf) = leaf_certificate(&body) {
+            info.issues = certificate_issues(leaf, SystemTime::now());
+        }
+    }
+    Ok(info)
+}
+
+/// Returns the next handshake message's type and body, reading records from `reader` until it
+/// is complete. `handshake` holds bytes already read past the previous message.
+fn read_handshake_message<R: Read>(
+    reader: &mut R,
+    handshake: &mut Vec<u8>,
+) -> Result<(u8, Vec<u8>), String> {
+    loop {
+        if handshake.len() >= 4 {
+            let length = u32::from_be_bytes([0, handshake[1], handshake[2], handshake[3]]) as usize;
+            if handshake.len() >= 4 + length {
+                let message: Vec<u8> = handshake.drain(..4 + length).collect();
+                return Ok((message[0], message[4..].to_vec()));
+            }
+        }
+        if handshake.len() > MAX_HANDSHAKE_BYTES {
+            return Err("TLS handshake message too large".to_string());
+        }
+
+        let mut header = [0u8; 5];
+        reader
+            .read_exact(&mut header)
+            .map_err(|e| format!("TLS handshake failed: {}", e))?;
+        if header[0] != CONTENT_HANDSHAKE && header[0] != CONTENT_ALERT {
+            return Err(format!("Unexpected TLS record type: {}", header[0]));
+        }
+        let length = u16::from_be_bytes([header[3], header[4]]) as usize;
+        let mut fragment = vec![0u8; length];
+        reader
+            .read_exact(&mut fragment)
+            .map_err(|e| format!("TLS handshake failed: {}", e))?;
+
+        if header[0] == CONTENT_ALERT {
+            return Err(format!(
+                "TLS alert: {}",
+                fragment.get(1).copied().unwrap_or_default()
+            ));
+        }
+        handshake.extend_from_slice(&fragment);
+    }
+}
+
+/// Parses the body of a ServerHello handshake message.
+fn parse_server_hello(body: &[u8]) -> Result<TlsInfo, String> {
+    let malformed = || "Malformed TLS ServerHello".to_string();
+    let mut reader = ByteReader(body);
+
+    let version = reader.u16().ok_or_else(malformed)?;
+    reader.take(32).ok_or_else(malformed)?;
+    let session_id = reader.u8().ok_or_else(malformed)?;
+    reader.take(session_id as usize).ok_or_else(malformed)?;
+    let cipher_suite = reader.u16().ok_or_else(malformed)?;
+    reader.u8().ok_or_else(malformed)?;
+
+    let mut alpn = None;
+    if let Some(length) = reader.u16() {
+        let mut extensions = ByteReader(reader.take(length as usize).ok_or_else(malformed)?);
+        while let Some(kind) = extensions.u16() {
+            let length = extensions.u16().ok_or_else(malformed)?;
+            let data = extensions.take(length as usize).ok_or_else(malformed)?;
+            if kind == EXTENSION_ALPN {
+                alpn = Some(parse_alpn(data).ok_or_else(malformed)?);
+            }
+        }
+    }
+
+    Ok(TlsInfo {
+        version: version_name(version),
+        cipher_suite,
+        alpn,
+        issues: Vec::new(),
+    })
+}
+
+/// Returns the server's own certificate, the first of a Certificate message's chain.
+fn leaf_certificate(body: &[u8]) -> Option<&[u8]> {
+    let mut reader = ByteReader(body);
+    let list = reader.u24()?;
+    let mut list = ByteReader(reader.take(list)?);
+    let length = list.u24()?;
+    list.take(length)
+}
+
+/// Lists what is wrong with the DER-encoded certificate `der` at `now`: whether it has expired
+/// or is not valid yet, and whether it is self-signed (its issuer is its subject).
+pub fn certificate_issues(der: &[u8], now: SystemTime) -> Vec<String> {
+    let Some(certificate) = parse_certificate(der) else {
+        return vec!["unreadable certificate".to_string()];
+    };
+    let mut issues = Vec::new();
+    if now > certificate.not_after {
+        issues.push(format!(
+            "certificate expired at {}",
+            humantime::format_rfc3339_seconds(certificate.not_after)
+        ));
+    }
+    if now < certificate.not_before {
+        issues.push(format!(
+            "certificate not valid before {}",
+            humantime::format_rfc3339_seconds(certificate.not_before)
+        ));
+    }
+    if certificate.issuer == certificate.subject {
+        issues.push("self-signed certificate".to_string());
+    }
+    issues
+}
+
+/// The parts of an X.509 certificate `certificate_issues` looks at.
+struct Certificate<'a> {
+    issuer: &'a [u8],
+    subject: &'a [u8],
+    not_before: SystemTime,
+    not_after: SystemTime,
+}
+
+fn parse_certificate(der: &[u8]) -> Option<Certificate<'_>> {
+    let (DER_SEQUENCE, certificate) = ByteReader(der).der()? else {
+        return None;
+    };
+    let (DER_SEQUENCE, tbs) = ByteReader(certificate).der()? else {
+        return None;
+    };
+    let mut tbs = ByteReader(tbs);
+    // Version (if present) and serial number, then the signature algorithm.
+    if tbs.der()?.0 == DER_VERSION {
+        tbs.der()?;
+    }
+    tbs.der()?;
+    let (_, issuer) = tbs.der()?;
+    let (DER_SEQUENCE, validity) = tbs.der()? else {
+        return None;
+    };
+    let (_, subject) = tbs.der()?;
+
+    let mut validity = ByteReader(validity);
+    let (tag, not_before) = validity.der()?;
+    let not_before = der_time(tag, not_before)?;
+    let (tag, not_after) = validity.der()?;
+    let not_after = der_time(tag, not_after)?;
+    Some(Certificate {
+        issuer,
+        subject,
+        not_before,
+        not_after,
+    })
+}
+
+/// Parses an X.509 validity time: a UTCTime (`YYMMDDHHMMSSZ`, years 1950 to 2049) or a
+/// GeneralizedTime (`YYYYMMDDHHMMSSZ`).
+fn der_time(tag: u8, value: &[u8]) -> Option<SystemTime> {
+    let text = std::str::from_utf8(value).ok()?.strip_suffix('Z')?;
+    let digits = match tag {
+        DER_UTC_TIME => {
+            let century = if text.get(..2)? >= "50" { "19" } else { "20" };
+            format!("{}{}", century, text)
+        }
+        DER_GENERALIZED_TIME => text.to_string(),
+        _ => return None,
+    };
+    if digits.len() != 14 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    humantime::parse_rfc3339(&format!(
+        "{}-{}-{}T{}:{}:{}Z",
+        &digits[..4],
+        &digits[4..6],
+        &digits[6..8],
+        &digits[8..10],
+        &digits[10..12],
+        &digits[12..]
+    ))
+    .ok()
+}
+
+/// Parses a ServerHello ALPN extension, which names exactly one protocol.
+fn parse_alpn(data: &[u8]) -> Option<String> {
+    let mut reader = ByteReader(data);
+    let list = reader.u16()?;
+    let mut list = ByteReader(reader.take(list as usize)?);
+    let length = list.u8()?;
+    let protocol = list.take(length as usize)?;
+    String::from_utf8(protocol.to_vec()).ok()
+}
+
+fn version_name(version: u16) -> String {
+    match version {
+        0x0300 => "SSL 3.0".to_string(),
+        0x0301 => "TLS 1.0".to_string(),
+        0x0302 => "TLS 1.1".to_string(),
+        0x0303 => "TLS 1.2".to_string(),
+        0x0304 => "TLS 1.3".to_string(),
+        other => format!("0x{:04x}", other),
+    }
+}
+
+fn push_u16_list(buffer: &mut Vec<u8>, values: &[u16]) {
+    buffer.extend_from_slice(&((values.len() * 2) as u16).to_be_bytes());
+    for value in values {
+        buffer.extend_from_slice(&value.to_be_bytes());
+    }
+}
+
+fn push_extension(buffer: &mut Vec<u8>, kind: u16, data: &[u8]) {
+    buffer.extend_from_slice(&kind.to_be_bytes());
+    buffer.extend_from_slice(&(data.len() as u16).to_be_bytes());
+    buffer.extend_from_slice(data);
+}
+
+/// A cursor over a byte slice whose reads return `None` once the slice runs out.
+struct ByteReader<'a>(&'a [u8]);
+
+impl<'a> ByteReader<'a> {
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        if self.0.len() < n {
+            return None;
+        }
+        let (head, tail) = self.0.split_at(n);
+        self.0 = tail;
+        Some(head)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.take(1).map(|b| b[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.take(2).map(|b| u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    fn u24(&mut self) -> Option<usize> {
+        self.take(3)
+            .map(|b| u32::from_be_bytes([0, b[0], b[1], b[2]]) as usize)
+    }
+
+    /// Reads one DER element and returns its tag and contents.
+    fn der(&mut self) -> Option<(u8, &'a [u8])> {
+        let tag = self.u8()?;
+        let length = match self.u8()? {
+            short @ 0..=0x7f => short as usize,
+            long @ 0x81..=0x84 => self
+                .take((long & 0x7f) as usize)?
+                .iter()
+                .fold(0, |length, byte| length << 8 | *byte as usize),
+            _ => return None,
+        };
+        Some((tag, self.take(length)?))
+    }
+}
+
+// Unit tests >------------------------------------------------------------<
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{net::TcpListener, thread};
+
+    /// A ServerHello record as a TLS 1.2 server sends it, selecting
+    /// ECDHE-RSA-AES128-GCM-SHA256 and ALPN `h2`.
+    const SERVER_HELLO_H2: &[u8] = &[
+        0x16, 0x03, 0x03, 0x00, 0x44, 0x02, 0x00, 0x00, 0x40, 0x03, 0x03, 0xa5, 0x4d, 0xca, 0x18,
+        0x25, 0x30, 0xbb, 0x1d, 0x6d, 0x13, 0x2c, 0xde, 0xd6, 0x23, 0x7b, 0x2e, 0xd9, 0x1e, 0x3f,
+        0x72, 0x1f, 0xcb, 0x19, 0x71, 0x17, 0x44, 0x94, 0xd6, 0x49, 0x3c, 0x9d, 0x5c, 0x00, 0xc0,
+        0x2f, 0x00, 0x00, 0x18, 0xff, 0x01, 0x00, 0x01, 0x00, 0x00, 0x0b, 0x00, 0x02, 0x01, 0x00,
+        0x00, 0x10, 0x00, 0x05, 0x00, 0x03, 0x02, 0x68, 0x32, 0x00, 0x17, 0x00, 0x00,
+    ];
+
+    /// The same ServerHello from a server that does not negotiate ALPN.
+    const SERVER_HELLO_NO_ALPN: &[u8] = &[
+        0x16, 0x03, 0x03, 0x00, 0x3b, 0x02, 0x00, 0x00, 0x37, 0x03, 0x03, 0xa5, 0x4d, 0xca, 0x18,
+        0x25, 0x30, 0xbb, 0x1d, 0x6d, 0x13, 0x2c, 0xde, 0xd6, 0x23, 0x7b, 0x2e, 0xd9, 0x1e, 0x3f,
+        0x72, 0x1f, 0xcb, 0x19, 0x71, 0x17, 0x44, 0x94, 0xd6, 0x49, 0x3c, 0x9d, 0x5c, 0x00, 0xc0,
+        0x2f, 0x00, 0x00, 0x0f, 0xff, 0x01, 0x00, 0x01, 0x00, 0x00, 0x0b, 0x00, 0x02, 0x01, 0x00,
+        0x00, 0x17, 0x00, 0x00,
+    ];
+
+    #[test]
+    fn test_read_server_hello_alpn() {
+        let info = read_server_hello(&mut &SERVER_HELLO_H2[..]).unwrap();
+        assert_eq!(
+            info,
+            TlsInfo {
+                version: "TLS 1.2".to_string(),
+                cipher_suite: 0xC02F,
+                alpn: Some("h2".to_string()),
+                issues: Vec::new(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_read_server_hello_without_alpn() {
+        let info = read_server_hello(&mut &SERVER_HELLO_NO_ALPN[..]).unwrap();
+        assert_eq!(info.alpn, None);
+        assert_eq!(info.version, "TLS 1.2");
+    }
+
+    #[test]
+    fn test_read_server_hello_split_across_records() {
+        let body = &SERVER_HELLO_H2[5..];
+        let mut records = Vec::new();
+        for chunk in body.chunks(20) {
+            records.extend_from_slice(&[CONTENT_HANDSHAKE, 0x03, 0x03, 0x00, chunk.len() as u8]);
+            records.extend_from_slice(chunk);
+        }
+
+        let info = read_server_hello(&mut &records[..]).unwrap();
+        assert_eq!(info.alpn, Some("h2".to_string()));
+    }
+
+    #[test]
+    fn test_read_server_hello_alert_and_plaintext() {
+        let alert = [CONTENT_ALERT, 0x03, 0x03, 0x00, 0x02, 0x02, 0x28];
+        assert_eq!(
+            read_server_hello(&mut &alert[..]),
+            Err("TLS alert: 40".to_string())
+        );
+
+        let http = b"HTTP/1.1 400 Bad Request\r\n\r\n";
+        assert_eq!(
+            read_server_hello(&mut &http[..]),
+            Err("Unexpected TLS record type: 72".to_string())
+        );
+    }
+
+    /// Encodes a DER element.
+    fn der(tag: u8, contents: &[u8]) -> Vec<u8> {
+        let mut element = vec![tag];
+        match contents.len() {
+            short @ 0..=0x7f => element.push(short as u8),
+            long => {
+                element.push(0x82);
+                element.extend_from_slice(&(long as u16).to_be_bytes());
+            }
+        }
+        element.extend_from_slice(contents);
+        element
+    }
+
+    /// Builds a certificate for `subject`, issued by `issuer`, valid between the two times.
+    fn certificate(issuer: &str, subject: &str, not_before: &str, not_after: &str) -> Vec<u8> {
+        let name = |cn: &str| {
+            let attribute = [der(0x06, &[0x55, 0x04, 0x03]), der(0x0c, cn.as_bytes())].concat();
+            der(DER_SEQUENCE, &der(0x31, &der(DER_SEQUENCE, &attribute)))
+        };
+        let algorithm = der(
+            DER_SEQUENCE,
+            &der(0x06, &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x02]),
+        );
+        let tbs = [
+            der(DER_VERSION, &der(0x02, &[2])),
+            der(0x02, &[0x01, 0x23]),
+            algorithm.clone(),
+            name(issuer),
+            der(
+                DER_SEQUENCE,
+                &[
+                    der(DER_UTC_TIME, not_before.as_bytes()),
+                    der(DER_GENERALIZED_TIME, not_after.as_bytes()),
+                ]
+                .concat(),
+            ),
+            name(subject),
+            der(
+                DER_SEQUENCE,
+                &[algorithm.clone(), der(0x03, &[0, 4, 1, 2])].concat(),
+            ),
+        ]
+        .concat();
+        der(
+            DER_SEQUENCE,
+            &[der(DER_SEQUENCE, &tbs), algorithm, der(0x03, &[0, 1, 2, 3])].concat(),
+        )
+    }
+
+    #[test]
+    fn test_certificate_issues_on_invalid_certificates() {
+        let now = humantime::parse_rfc3339("2024-05-01T12:00:00Z").unwrap();
+
+        let expired_self_signed = certificate(
+            "printer.local",
+            "printer.local",
+            "190101000000Z",
+            "20210101000000Z",
+        );
+        assert_eq!(
+            certificate_issues(&expired_self_signed, now),
+            vec![
+                "certificate expired at 2021-01-01T00:00:00Z".to_string(),
+                "self-signed certificate".to_string(),
+            ]
+        );
+
+        let not_yet_valid = certificate(
+            "Example CA",
+            "www.example.com",
+            "250101000000Z",
+            "20260101000000Z",
+        );
+        assert_eq!(
+            certificate_issues(&not_yet_valid, now),
+            vec!["certificate not valid before 2025-01-01T00:00:00Z".to_string()]
+        );
+
+        let valid = certificate(
+            "Example CA",
+            "www.example.com",
+            "240101000000Z",
+            "20250101000000Z",
+        );
+        assert!(certificate_issues(&valid, now).is_empty());
+        assert_eq!(
+            certificate_issues(b"not a certificate", now),
+            vec!["unreadable certificate".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_read_server_hello_records_certificate_issues() {
+        let leaf = certificate("router", "router", "100101000000Z", "20110101000000Z");
+        let mut chain = (leaf.len() as u32).to_be_bytes()[1..].to_vec();
+        chain.extend_from_slice(&leaf);
+        let mut body = (chain.len() as u32).to_be_bytes()[1..].to_vec();
+        body.extend_from_slice(&chain);
+        let mut message = vec![HANDSHAKE_CERTIFICATE];
+        message.extend_from_slice(&(body.len() as u32).to_be_bytes()[1..]);
+        message.extend_from_slice(&body);
+
+        let mut flight = SERVER_HELLO_H2.to_vec();
+        flight.extend_from_slice(&[CONTENT_HANDSHAKE, 0x03, 0x03]);
+        flight.extend_from_slice(&(message.len() as u16).to_be_bytes());
+        flight.extend_from_slice(&message);
+
+        let info = read_server_hello(&mut &flight[..]).unwrap();
+        assert_eq!(info.alpn, Some("h2".to_string()));
+        assert_eq!(
+            info.issues,
+            vec![
+                "certificate expired at 2011-01-01T00:00:00Z".to_string(),
+                "self-signed certificate".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_inspect_offers_alpn() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut hello = vec![0u8; client_hello(ALPN_PROTOCOLS).len()];
+            stream.read_exact(&mut hello).unwrap();
+            stream.write_all(SERVER_HELLO_H2).unwrap();
+            hello
+        });
+
+        let info = inspect(addr, &[], Duration::from_secs(2)).unwrap();
+        let hello = server.join().unwrap();
+
+        assert_eq!(info.alpn, Some("h2".to_string()));
+        assert_eq!(hello[0], CONTENT_HANDSHAKE);
+        assert!(hello.windows(12).any(|w| w == b"\x02h2\x08http/1.1"));
+    }
+}
@@ -0,0 +1,139 @@
+use crate::scan::result::{latency_ms, PortState, ScanResult, TcpResponse};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    net::IpAddr,
+    path::Path,
+    sync::Mutex,
+    time::{Duration, SystemTime},
+};
+
+/// One probe as recorded in the trace file, one JSON object per line.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TraceEntry {
+    /// When the probe finished, as an RFC 3339 timestamp with milliseconds.
+    pub timestamp: String,
+    /// Local address the probe's connection was made from, when it got that far.
+    pub src: Option<IpAddr>,
+    pub dst: IpAddr,
+    pub port: u16,
+    pub state: PortState,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<TcpResponse>,
+    #[serde(rename = "latency_ms", with = "latency_ms")]
+    pub latency: Option<Duration>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl TraceEntry {
+    /// Records `result` as finishing at `at`.
+    pub fn new(result: &ScanResult, at: SystemTime) -> Self {
+        Self {
+            timestamp: humantime::format_rfc3339_millis(at).to_string(),
+            src: result.source_addr,
+            dst: result.address,
+            port: result.port,
+            state: result.state,
+            response: result.response,
+            latency: result.latency,
+            error: result.error.clone(),
+        }
+    }
+}
+
+/// A JSONL log of every probe, shared by the workers of a scan.
+///
+/// Entries are buffered and written out by `flush`, or when the trace is dropped at the end of
+/// the scan.
+#[derive(Debug)]
+pub struct Trace {
+    writer: Mutex<BufWriter<File>>,
+}
+
+impl Trace {
+    /// Creates the trace file at `path`, truncating an existing one, and opens it for the
+    /// entries of one scan; every cycle of a `--watch` scan shares it.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Trace)` - If the file could be created.
+    /// * `Err(String)` - If it could not, with the path and the reason.
+    pub fn start(path: &Path) -> Result<Self, String> {
+        let file = File::create(path)
+            .map_err(|e| format!("Failed to create trace file {}: {}", path.display(), e))?;
+        Ok(Self {
+            writer: Mutex::new(BufWriter::new(file)),
+        })
+    }
+
+    /// Writes out the buffered entries, e.g. at the end of a `--watch` cycle.
+    ///
+    /// Like `record`, a failure is ignored rather than failing the scan.
+    pub fn flush(&self) {
+        let _ = self.writer.lock().unwrap().flush();
+    }
+
+    /// Appends an entry for `result`, timestamped now.
+    ///
+    /// Tracing is a debugging aid, so a failed write is ignored rather than failing the scan.
+    pub fn record(&self, result: &ScanResult) {
+        let entry = TraceEntry::new(result, SystemTime::now());
+        let mut writer = self.writer.lock().unwrap();
+        if serde_json::to_writer(&mut *writer, &entry).is_ok() {
+            let _ = writer.write_all(b"\n");
+        }
+    }
+}
+
+// Unit tests >------------------------------------------------------------<
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trace_records_each_probe() {
+        let path =
+            std::env::temp_dir().join(format!("port_hawk_trace_{}.jsonl", std::process::id()));
+        let trace = Trace::start(&path).unwrap();
+        let open = ScanResult {
+            source_addr: Some("127.0.0.1".parse().unwrap()),
+            latency: Some(Duration::from_millis(2)),
+            ..ScanResult::new("127.0.0.1".parse().unwrap(), 22, PortState::Open)
+        };
+        trace.record(&open);
+        trace.record(&ScanResult::new(
+            "10.0.0.1".parse().unwrap(),
+            80,
+            PortState::Filtered,
+        ));
+        drop(trace);
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let entries: Vec<TraceEntry> = contents
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].src, open.source_addr);
+        assert_eq!((entries[0].dst, entries[0].port), (open.address, 22));
+        assert_eq!(entries[0].state, PortState::Open);
+        assert_eq!(entries[0].latency, Some(Duration::from_millis(2)));
+        assert!(humantime::parse_rfc3339(&entries[0].timestamp).is_ok());
+        assert_eq!(entries[1].src, None);
+        assert_eq!(entries[1].state, PortState::Filtered);
+
+        // Starting again begins a fresh log.
+        drop(Trace::start(&path).unwrap());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "");
+        std::fs::remove_file(path).unwrap();
+
+        let missing = std::env::temp_dir()
+            .join("port_hawk_no_such_dir")
+            .join("trace.jsonl");
+        assert!(Trace::start(&missing)
+            .unwrap_err()
+            .starts_with("Failed to create trace file "));
+    }
+}
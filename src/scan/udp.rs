@@ -0,0 +1,106 @@
+use crate::{
+    args::parser::Protocol,
+    scan::{
+        jobs::Job,
+        raw::unspecified,
+        result::{PortState, ScanResult},
+    },
+};
+use std::{
+    io::{self, ErrorKind},
+    net::{IpAddr, SocketAddr, UdpSocket},
+    time::{Duration, Instant},
+};
+
+/// Probes a single UDP port with an empty datagram and classifies the outcome.
+///
+/// Any reply is `Open` and an ICMP port unreachable, which a connected socket reports as a
+/// refused connection, is `Closed`. Silence until `timeout` is `OpenFiltered`: most UDP
+/// services ignore an empty datagram, so a quiet port may be open or firewalled. The datagram
+/// is sent from `source` if given.
+pub fn probe(job: &Job, source: Option<IpAddr>, timeout: Duration) -> ScanResult {
+    let result = |state| ScanResult {
+        protocol: Protocol::Udp,
+        ..ScanResult::new(job.address, job.port, state)
+    };
+    let started = Instant::now();
+    let outcome = exchange(job.socket_addr(), source, timeout);
+    let elapsed = started.elapsed();
+    let ms = elapsed.as_millis();
+    match outcome {
+        Ok(()) => ScanResult {
+            latency: Some(elapsed),
+            detail: Some(format!("reply after {}ms", ms)),
+            ..result(PortState::Open)
+        },
+        Err(e) if e.kind() == ErrorKind::ConnectionRefused => ScanResult {
+            latency: Some(elapsed),
+            detail: Some(format!("port unreachable after {}ms", ms)),
+            ..result(PortState::Closed)
+        },
+        Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => ScanResult {
+            detail: Some(format!("no reply within {}ms", ms)),
+            ..result(PortState::OpenFiltered)
+        },
+        Err(e) => ScanResult {
+            error: Some(format!("udp probe failed: {}", e)),
+            ..result(PortState::Error)
+        },
+    }
+}
+
+/// Sends an empty datagram to `addr` and waits up to `timeout` for anything to come back.
+fn exchange(addr: SocketAddr, source: Option<IpAddr>, timeout: Duration) -> io::Result<()> {
+    let source = source.unwrap_or_else(|| unspecified(addr.ip()));
+    let socket = UdpSocket::bind(SocketAddr::new(source, 0))?;
+    socket.connect(addr)?;
+    socket.set_read_timeout(Some(timeout))?;
+    socket.send(&[])?;
+    socket.recv(&mut [0u8; 512]).map(drop)
+}
+
+// Unit tests >------------------------------------------------------------<
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    fn job(port: u16) -> Job {
+        Job {
+            address: "127.0.0.1".parse().unwrap(),
+            port,
+            scope_id: 0,
+            protocol: None,
+        }
+    }
+
+    #[test]
+    fn test_probe_classifies_udp_ports() {
+        let timeout = Duration::from_millis(200);
+
+        let echo = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let open = echo.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            let mut buf = [0u8; 16];
+            let (_, peer) = echo.recv_from(&mut buf).unwrap();
+            echo.send_to(b"pong", peer).unwrap();
+        });
+        assert_eq!(probe(&job(open), None, timeout).state, PortState::Open);
+        server.join().unwrap();
+
+        let closed = UdpSocket::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        assert_eq!(probe(&job(closed), None, timeout).state, PortState::Closed);
+
+        // Bound but never answering.
+        let silent = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let port = silent.local_addr().unwrap().port();
+        assert_eq!(
+            probe(&job(port), None, timeout).state,
+            PortState::OpenFiltered
+        );
+    }
+}
@@ -0,0 +1,179 @@
+use crate::{
+    input_parse::Args,
+    scan::{check_args, diff, execute_jobs, job_stream, jobs::Job, open_trace, result::ScanResult},
+};
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+/// How often the stop flag is checked while waiting for the next cycle.
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Controls how `--watch` repeats a scan.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatchOptions {
+    /// Time between the start of consecutive cycles.
+    pub interval: Duration,
+    /// Stop after this many cycles; unbounded when `None`.
+    pub max_cycles: Option<u64>,
+    /// After the first cycle, report only hosts whose port states changed since the previous one.
+    pub only_changes: bool,
+}
+
+/// The outcome of a single watch cycle.
+#[derive(Debug, Clone)]
+pub struct Cycle {
+    /// 1-based cycle number.
+    pub number: u64,
+    pub started_at: SystemTime,
+    /// How long the cycle's probes took.
+    pub elapsed: Duration,
+    /// The cycle's results; with `only_changes`, only those of hosts that changed.
+    pub results: Vec<ScanResult>,
+    /// The job plan the cycle ran; shared by every cycle of a watch.
+    pub plan: Arc<[Job]>,
+}
+
+/// Repeats the scan described by `args` every `options.interval`, calling `on_cycle` after
+/// each cycle.
+///
+/// Targets and ports are expanded once into a job plan that every cycle reuses, so hostnames
+/// are never re-resolved between cycles. The loop ends after `options.max_cycles` cycles or
+/// as soon as `stop` is set, and returns the number of cycles completed.
+///
+/// With `options.only_changes`, the first cycle reports the full baseline and later cycles
+/// report only hosts that differ from the cycle before them.
+pub fn watch<F>(
+    args: &Args,
+    options: &WatchOptions,
+    stop: &AtomicBool,
+    mut on_cycle: F,
+) -> Result<u64, String>
+where
+    F: FnMut(&Cycle),
+{
+    check_args(args)?;
+    let trace = open_trace(args)?;
+
+    let plan: Arc<[Job]> = job_stream(args).collect();
+    let mut completed = 0;
+    let mut previous: Option<Vec<ScanResult>> = None;
+
+    while !stop.load(Ordering::Relaxed) {
+        let started = Instant::now();
+        let started_at = SystemTime::now();
+        let results = execute_jobs(args, plan.iter().copied(), trace.as_ref(), stop);
+        if let Some(trace) = &trace {
+            trace.flush();
+        }
+        let elapsed = started.elapsed();
+        let reported = match &previous {
+            Some(previous) if options.only_changes => diff::changed_hosts(previous, &results),
+            _ => results.clone(),
+        };
+        let cycle = Cycle {
+            number: completed + 1,
+            started_at,
+            elapsed,
+            results: reported,
+            plan: Arc::clone(&plan),
+        };
+        on_cycle(&cycle);
+        if options.only_changes {
+            previous = Some(results);
+        }
+        completed += 1;
+
+        if options.max_cycles.is_some_and(|max| completed >= max) {
+            break;
+        }
+        wait_until(started + options.interval, stop);
+    }
+
+    Ok(completed)
+}
+
+fn wait_until(deadline: Instant, stop: &AtomicBool) {
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() || stop.load(Ordering::Relaxed) {
+            return;
+        }
+        thread::sleep(remaining.min(STOP_POLL_INTERVAL));
+    }
+}
+
+// Unit tests >------------------------------------------------------------<
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_watch_runs_cycles_and_reuses_plan() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port().to_string();
+        let args = Args::parse_from(["port_hawk", "localhost", "-p", &port]);
+        let options = WatchOptions {
+            interval: Duration::from_millis(10),
+            max_cycles: Some(2),
+            only_changes: false,
+        };
+
+        let mut cycles = Vec::new();
+        let completed = watch(&args, &options, &AtomicBool::new(false), |cycle| {
+            cycles.push(cycle.clone())
+        })
+        .unwrap();
+
+        assert_eq!(completed, 2);
+        assert_eq!(cycles[0].number, 1);
+        assert_eq!(cycles[1].number, 2);
+        assert!(Arc::ptr_eq(&cycles[0].plan, &cycles[1].plan));
+        let states = |cycle: &Cycle| cycle.results.iter().map(|r| r.state).collect::<Vec<_>>();
+        assert_eq!(states(&cycles[0]), states(&cycles[1]));
+        assert!(cycles[1].started_at >= cycles[0].started_at);
+    }
+
+    #[test]
+    fn test_watch_only_changes_is_quiet_when_nothing_changes() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port().to_string();
+        let args = Args::parse_from(["port_hawk", "127.0.0.1", "-p", &port]);
+        let options = WatchOptions {
+            interval: Duration::from_millis(10),
+            max_cycles: Some(2),
+            only_changes: true,
+        };
+
+        let mut cycles = Vec::new();
+        watch(&args, &options, &AtomicBool::new(false), |cycle| {
+            cycles.push(cycle.clone())
+        })
+        .unwrap();
+
+        assert_eq!(cycles[0].results.len(), 1);
+        assert!(cycles[1].results.is_empty());
+    }
+
+    #[test]
+    fn test_watch_stops_when_flagged() {
+        let args = Args::parse_from(["port_hawk", "127.0.0.1", "-p", "1"]);
+        let options = WatchOptions {
+            interval: Duration::from_secs(60),
+            max_cycles: None,
+            only_changes: false,
+        };
+        let stop = AtomicBool::new(false);
+
+        let completed = watch(&args, &options, &stop, |_| {
+            stop.store(true, Ordering::Relaxed)
+        });
+        assert_eq!(completed, Ok(1));
+    }
+}
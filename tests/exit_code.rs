@@ -0,0 +1,32 @@
+use std::{net::TcpListener, process::Command};
+
+fn scan_port(port: u16, extra: &[&str]) -> i32 {
+    Command::new(env!("CARGO_BIN_EXE_port_hawk"))
+        .args(["127.0.0.1", "--ports", &port.to_string()])
+        .args(extra)
+        .output()
+        .expect("Failed to execute command")
+        .status
+        .code()
+        .expect("Process terminated by signal")
+}
+
+#[test]
+fn test_exit_code_open_port() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    assert_eq!(scan_port(port, &["--exit-code"]), 0);
+}
+
+#[test]
+fn test_exit_code_no_open_port() {
+    let port = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+
+    assert_eq!(scan_port(port, &["--exit-code"]), 1);
+    assert_eq!(scan_port(port, &[]), 0);
+}